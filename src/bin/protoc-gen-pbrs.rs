@@ -0,0 +1,19 @@
+use pbrs::plugin::{self, CodeGeneratorRequest};
+use pbrs::runtime::Message;
+use std::error::Error;
+use std::io::{stdin, stdout, Read, Write};
+
+// Plugin that protoc runs for "--pbrs_out", reading a request from stdin and
+// writing the response to stdout
+fn main() -> Result<(), Box<dyn Error>> {
+  let mut input = Vec::new();
+
+  stdin().read_to_end(&mut input)?;
+
+  let request = CodeGeneratorRequest::decode(&input)?;
+  let response = plugin::run(&request);
+
+  stdout().write_all(&response.encode_to_vec())?;
+
+  Ok(())
+}
@@ -0,0 +1,450 @@
+use crate::parser::Options;
+use crate::runtime::{leak_descriptors, MessageDescriptor, TypeRegistry};
+use crate::{lexer, parser, tokeniser};
+use config::Config;
+use std::env::current_dir;
+use std::error::Error;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::fs::read_to_string;
+use std::io::{stdin, stdout, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::process::ExitCode;
+
+mod check;
+mod config;
+mod decode;
+mod encode;
+mod fmt;
+mod generate;
+mod lint;
+
+pub use check::check;
+pub use decode::decode;
+pub use encode::encode;
+pub use fmt::{fmt, format_source};
+pub use generate::generate;
+pub use lint::lint;
+
+pub type CliResult<T> = Result<T, Box<dyn Error>>;
+
+const USAGE: &str = "Usage: pbrs [COMMAND] [OPTIONS] [FILES]
+
+Commands:
+  generate  Generate Rust from .proto files, the default
+  check     Report problems that would stop files compiling
+  fmt       Format .proto files in place, or list them with --check
+  lint      Report names that don't follow the Protobuf style guide
+  decode    Print a binary message from stdin as text format or JSON
+  encode    Write a message from stdin in text format or JSON as binary
+
+Options:
+  -I, --proto-path <DIR>  Directory to find files in
+  -o, --out-dir <DIR>     Directory to write generated files to
+  --config <FILE>         TOML file of options
+  -q, --quiet             Only print errors
+  -v, --verbose           Print each file as it's read";
+
+// Flags that any command accepts
+const COMMON_FLAGS: [&str; 5] =
+  ["--config", "--help", "--proto-path", "--quiet", "--verbose"];
+
+// Flags that are given a value in the argument that follows them, unless
+// it's joined to them with "="
+const VALUE_FLAGS: [&str; 7] = [
+  "--config",
+  "--descriptor-set-in",
+  "--descriptor-set-out",
+  "--out-dir",
+  "--proto",
+  "--proto-path",
+  "--type"
+];
+
+// Short flags and the long flags they stand for
+const SHORT_FLAGS: [(&str, &str); 5] = [
+  ("-I", "--proto-path"),
+  ("-h", "--help"),
+  ("-o", "--out-dir"),
+  ("-q", "--quiet"),
+  ("-v", "--verbose")
+];
+
+// Mistakes in how pbrs was called, which exit with a different code to
+// failures of the command itself
+#[derive(Debug)]
+pub struct Usage(pub String);
+
+impl Display for Usage {
+  fn fmt(&self, f: &mut Formatter) -> FmtResult {
+    write!(f, "{}", self.0)
+  }
+}
+
+impl Error for Usage {}
+
+// Arguments of a command, split into the files it was given and its flags,
+// where flags with values are written as "--name value" or "--name=value"
+#[derive(Debug, Default)]
+pub struct Args {
+  pub inputs: Vec<String>,
+  flags: Vec<(String, Option<String>)>
+}
+
+impl Args {
+  pub fn parse(args: &[String]) -> Result<Self, Usage> {
+    let mut result = Self::default();
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+      if !arg.starts_with('-') {
+        result.inputs.push(arg.clone());
+
+        continue;
+      }
+
+      let (name, value) = match arg.split_once('=') {
+        Some((name, value)) => (name, Some(value.to_string())),
+        // Short flags may have their value joined to them, as in "-Iprotos"
+        None if !arg.starts_with("--") && arg.len() > 2 => {
+          (&arg[..2], Some(arg[2..].to_string()))
+        }
+        None => (arg.as_str(), None)
+      };
+      let name = SHORT_FLAGS
+        .iter()
+        .find(|(short, _)| *short == name)
+        .map_or(name, |(_, long)| long);
+      let value = match value {
+        None if VALUE_FLAGS.contains(&name) => Some(
+          args
+            .next()
+            .ok_or_else(|| Usage(format!("Missing value for {}", name)))?
+            .clone()
+        ),
+        value => value
+      };
+
+      result
+        .flags
+        .push((name.to_string(), value));
+    }
+
+    Ok(result)
+  }
+
+  // Value of a flag, where the last one given wins
+  pub fn value(&self, name: &str) -> Option<&str> {
+    self.values(name).pop()
+  }
+
+  pub fn values(&self, name: &str) -> Vec<&str> {
+    self
+      .flags
+      .iter()
+      .filter(|(flag, _)| flag == name)
+      .filter_map(|(_, value)| value.as_deref())
+      .collect()
+  }
+
+  pub fn switch(&self, name: &str) -> bool {
+    self
+      .flags
+      .iter()
+      .any(|(flag, _)| flag == name)
+  }
+
+  // Flags other than those of the command are codegen options, written as
+  // "--option" or "--option=value"
+  fn options(
+    &self,
+    allowed: &[&str],
+    options: Options
+  ) -> Result<Options, Usage> {
+    self
+      .flags
+      .iter()
+      .filter(|(flag, _)| !allowed.contains(&flag.as_str()))
+      .filter(|(flag, _)| !COMMON_FLAGS.contains(&flag.as_str()))
+      .try_fold(options, |options, (flag, value)| {
+        let flag = flag.trim_start_matches('-');
+        let option = match value {
+          Some(value) => format!("{}={}", flag, value),
+          None => flag.to_string()
+        };
+
+        options.option(&option).map_err(Usage)
+      })
+  }
+
+  fn expect_flags(&self, allowed: &[&str]) -> Result<(), Usage> {
+    let unknown = self.flags.iter().find(|(flag, _)| {
+      !allowed.contains(&flag.as_str())
+        && !COMMON_FLAGS.contains(&flag.as_str())
+    });
+
+    match unknown {
+      Some((flag, _)) => Err(Usage(format!("Unknown flag {}", flag))),
+      None => Ok(())
+    }
+  }
+
+  // Config given by "--config", or otherwise the nearest "pbrs.toml" to the
+  // current directory
+  fn config(&self) -> CliResult<Config> {
+    let path = match self.value("--config") {
+      Some(path) => Some(PathBuf::from(path)),
+      None => config::discover(&current_dir()?)
+    };
+
+    match path {
+      Some(path) => {
+        self.debug(format_args!("Reading {}", path.display()));
+
+        config::load(&path)
+      }
+      None => Ok(Config::default())
+    }
+  }
+
+  // Progress that's shown unless "--quiet" is given
+  fn info(&self, message: impl Display) {
+    if !self.switch("--quiet") {
+      eprintln!("{}", message);
+    }
+  }
+
+  // Progress that's only shown when "--verbose" is given
+  fn debug(&self, message: impl Display) {
+    if self.switch("--verbose") {
+      eprintln!("{}", message);
+    }
+  }
+}
+
+// File given to a command, along with its name relative to the directory
+// it was found in, as protoc names files
+pub struct Input {
+  pub path: PathBuf,
+  pub name: String
+}
+
+// Files matching each path or glob given to a command, looked for relative
+// to the current directory and then within each proto path
+fn inputs(args: &Args, config: &Config) -> CliResult<Vec<Input>> {
+  let roots = args
+    .values("--proto-path")
+    .into_iter()
+    .map(PathBuf::from)
+    .chain(config.proto_paths.iter().cloned())
+    .collect::<Vec<PathBuf>>();
+  let mut inputs: Vec<Input> = Vec::new();
+
+  if args.inputs.is_empty() {
+    return Err(Usage("Missing input files".to_string()).into());
+  }
+
+  for pattern in &args.inputs {
+    let candidates = [PathBuf::from(pattern)]
+      .into_iter()
+      .chain(roots.iter().map(|v| v.join(pattern)));
+    let mut found = Vec::new();
+
+    for candidate in candidates {
+      let candidate = candidate.to_string_lossy();
+
+      found = glob::glob(&candidate)
+        .map_err(|err| {
+          Usage(format!("Invalid pattern {}: {}", candidate, err))
+        })?
+        .collect::<Result<Vec<PathBuf>, _>>()?;
+
+      if !found.is_empty() {
+        break;
+      }
+    }
+
+    if found.is_empty() {
+      return Err(format!("No files match {}", pattern).into());
+    }
+
+    for path in found {
+      let name = roots
+        .iter()
+        .find_map(|v| path.strip_prefix(v).ok())
+        .unwrap_or(&path)
+        .to_string_lossy()
+        .replace('\\', "/");
+
+      if !inputs.iter().any(|v| v.path == path) {
+        inputs.push(Input {
+          path,
+          name
+        });
+      }
+    }
+  }
+
+  Ok(inputs)
+}
+
+// Path of a file relative to an output directory, which keeps the directories
+// of its name unless they'd lead outside of it
+fn output_path(dir: &Path, name: &str, extension: &str) -> PathBuf {
+  let name = Path::new(name);
+  let relative = name
+    .components()
+    .all(|v| matches!(v, Component::Normal(_)));
+  let path = match relative {
+    true => dir.join(name),
+    false => dir.join(name.file_name().unwrap_or_default())
+  };
+
+  path.with_extension(extension)
+}
+
+// Descriptor of the message type named by "--type" from the ".proto" file
+// given by "--proto", with or without its leading dot as in "pkg.Msg", along
+// with a registry of every message in the file so Any fields are expanded
+fn load_type(
+  args: &Args
+) -> CliResult<(&'static MessageDescriptor, TypeRegistry)> {
+  let proto = args
+    .value("--proto")
+    .ok_or_else(|| Usage("Missing --proto argument".to_string()))?;
+  let name = args
+    .value("--type")
+    .ok_or_else(|| Usage("Missing --type argument".to_string()))?;
+  let schema = Args {
+    inputs: vec![proto.to_string()],
+    flags: args.flags.clone()
+  };
+  let config = args.config()?;
+  let input = inputs(&schema, &config)?.remove(0);
+  let file = read_to_string(&input.path)?;
+  let tokens = tokeniser::translate(&file)?;
+  let blocks = lexer::translate(&tokens)?;
+  let described = parser::describe(&input.name, &blocks, config.options)?;
+  let registry = leak_descriptors(&[described])
+    .into_values()
+    .fold(TypeRegistry::default(), TypeRegistry::add_descriptor);
+  let descriptor = registry
+    .get(name)
+    .ok_or_else(|| format!("Unknown message type {}", name))?;
+
+  Ok((descriptor, registry))
+}
+
+fn dispatch(args: &[String]) -> CliResult<()> {
+  // Files given without a command are generated, as they were before there
+  // were commands
+  let (command, args) = match args.first().map(String::as_str) {
+    Some(
+      command @ ("generate" | "check" | "fmt" | "lint" | "decode" | "encode")
+    ) => (command, Args::parse(&args[1..])?),
+    _ => ("generate", Args::parse(args)?)
+  };
+
+  if args.switch("--help") {
+    println!("{}", USAGE);
+
+    return Ok(());
+  }
+
+  match command {
+    "check" => check(&args),
+    "fmt" => fmt(&args),
+    "lint" => lint(&args),
+    "decode" => {
+      let mut input = Vec::new();
+
+      stdin().read_to_end(&mut input)?;
+      print!("{}", decode(&args, &input)?);
+
+      Ok(())
+    }
+    "encode" => {
+      let mut input = String::new();
+
+      stdin().read_to_string(&mut input)?;
+      stdout().write_all(&encode(&args, &input)?)?;
+
+      Ok(())
+    }
+    _ => generate(&args)
+  }
+}
+
+// Run pbrs with the arguments it was given, exiting with 2 when it was called
+// incorrectly and 1 when the command itself failed
+pub fn run(args: &[String]) -> ExitCode {
+  match dispatch(args) {
+    Ok(()) => ExitCode::SUCCESS,
+    Err(err) if err.is::<Usage>() => {
+      eprintln!("error: {}\n\n{}", err, USAGE);
+
+      ExitCode::from(2)
+    }
+    Err(err) => {
+      eprintln!("error: {}", err);
+
+      ExitCode::FAILURE
+    }
+  }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+  use super::*;
+
+  pub fn args(args: &[&str]) -> Args {
+    let args = args
+      .iter()
+      .map(|v| v.to_string())
+      .collect::<Vec<String>>();
+
+    Args::parse(&args).unwrap_or_default()
+  }
+
+  #[test]
+  fn parse_flags() {
+    let args = args(&[
+      "a.proto",
+      "-Iprotos",
+      "--proto-path",
+      "more",
+      "-o=out",
+      "--serde",
+      "--descriptor-set-out",
+      "set.pb",
+      "-q"
+    ]);
+
+    assert_eq!(args.inputs, vec!["a.proto"]);
+    assert_eq!(args.value("--descriptor-set-out"), Some("set.pb"));
+    assert_eq!(args.values("--proto-path"), vec!["protos", "more"]);
+    assert_eq!(args.value("--out-dir"), Some("out"));
+    assert!(args.switch("--quiet") && args.switch("--serde"));
+    assert!(args
+      .expect_flags(&["--out-dir"])
+      .is_err());
+  }
+
+  #[test]
+  fn find_inputs() -> CliResult<()> {
+    let args = args(&["-I", "tests", "fixtures/*.proto"]);
+    let inputs = inputs(&args, &Config::default())?;
+
+    assert_eq!(inputs.len(), 1);
+    assert_eq!(inputs[0].name, "fixtures/counter.proto");
+    assert_eq!(
+      output_path(Path::new("out"), &inputs[0].name, "rs"),
+      Path::new("out/fixtures/counter.rs")
+    );
+    assert_eq!(
+      output_path(Path::new("out"), "../a.proto", "rs"),
+      Path::new("out/a.rs")
+    );
+
+    Ok(())
+  }
+}
@@ -0,0 +1,40 @@
+use super::{inputs, Args, CliResult};
+use crate::{lexer, parser, tokeniser};
+use std::fs::read_to_string;
+
+// Report problems in each file given that would stop protoc compiling it,
+// failing when any are found
+pub fn check(args: &Args) -> CliResult<()> {
+  args.expect_flags(&[])?;
+
+  let config = args.config()?;
+  let inputs = inputs(args, &config)?;
+  let mut problems = 0;
+
+  for input in &inputs {
+    args.debug(format_args!("Checking {}", input.path.display()));
+
+    let source = read_to_string(&input.path)?;
+    let tokens = tokeniser::translate(&source)?;
+    // Files that can't be read into blocks are a problem of their own,
+    // rather than a reason to stop checking the rest
+    let found = match lexer::translate(&tokens) {
+      Ok(blocks) => parser::check(&blocks, config.options.clone()),
+      Err(err) => vec![err]
+    };
+
+    for problem in found {
+      println!("{}: {}", input.path.display(), problem);
+      problems += 1;
+    }
+  }
+
+  match problems {
+    0 => {
+      args.info(format_args!("Checked {} files", inputs.len()));
+
+      Ok(())
+    }
+    _ => Err(format!("Found {} problems", problems).into())
+  }
+}
@@ -0,0 +1,247 @@
+use super::CliResult;
+use crate::parser::Options;
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+use toml::{Table, Value};
+
+// Name of the file that's found in the current directory or any above it
+// when no other is given
+pub const FILE_NAME: &str = "pbrs.toml";
+
+// Settings read from a TOML file, where keys other than those for paths are
+// codegen options named as they are on the command line, as in
+// "serde = true" or "derives = \"Clone, Debug\"". Options for particular
+// packages and messages are set in "[package.NAME]" and "[message.NAME]"
+// sections, named by their fully-qualified names
+#[derive(Debug, Default)]
+pub struct Config {
+  pub options: Options,
+  pub out_dir: Option<PathBuf>,
+  pub proto_paths: Vec<PathBuf>
+}
+
+// Options as they're written on the command line, given their value in the
+// file. Attributes are written as tables of paths to attributes, and any
+// option may be given a list of values
+fn to_options(name: &str, value: &Value) -> CliResult<Vec<String>> {
+  match value {
+    Value::Boolean(true) => Ok(vec![name.to_string()]),
+    Value::Boolean(false) => Ok(vec![format!("{}=false", name)]),
+    Value::String(v) => Ok(vec![format!("{}={}", name, v)]),
+    Value::Array(values) => values
+      .iter()
+      .map(|v| to_options(name, v))
+      .collect::<CliResult<Vec<Vec<String>>>>()
+      .map(|v| v.concat()),
+    Value::Table(table) => table
+      .iter()
+      .map(|(path, attribute)| {
+        let attribute = attribute.as_str().ok_or_else(|| {
+          format!("Invalid attribute for {} in {}", path, name)
+        })?;
+
+        Ok(format!("{}={}={}", name, path, attribute))
+      })
+      .collect(),
+    _ => Err(format!("Invalid value for {}", name).into())
+  }
+}
+
+// Options of a "[package.NAME]" or "[message.NAME]" section, which only apply
+// to what matches the name it's keyed by
+fn apply_sections(
+  options: Options,
+  section: &str,
+  value: &Value
+) -> CliResult<Options> {
+  let sections = value
+    .as_table()
+    .ok_or_else(|| format!("Invalid {} section", section))?;
+
+  sections
+    .iter()
+    .try_fold(options, |options, (name, table)| {
+      let table = table
+        .as_table()
+        .ok_or_else(|| format!("Invalid {} section {}", section, name))?;
+      let path = format!(".{}", name.trim_start_matches('.'));
+
+      table
+        .iter()
+        .map(|(key, value)| to_options(key, value))
+        .collect::<CliResult<Vec<Vec<String>>>>()?
+        .concat()
+        .iter()
+        .try_fold(options, |options, option| {
+          Ok(options.override_option(&path, option)?)
+        })
+    })
+}
+
+fn strings(name: &str, value: &Value) -> CliResult<Vec<String>> {
+  match value {
+    Value::String(v) => Ok(vec![v.clone()]),
+    Value::Array(values) => values
+      .iter()
+      .map(|v| {
+        v.as_str()
+          .map(String::from)
+          .ok_or_else(|| format!("Invalid value for {}", name).into())
+      })
+      .collect(),
+    _ => Err(format!("Invalid value for {}", name).into())
+  }
+}
+
+// Read settings from a file, where paths are relative to the directory the
+// file is in
+pub fn parse(input: &str, dir: &Path) -> CliResult<Config> {
+  let table = input.parse::<Table>()?;
+  let mut config = Config::default();
+
+  for (name, value) in &table {
+    match name.as_str() {
+      "out-dir" => {
+        config.out_dir = strings(name, value)?
+          .pop()
+          .map(|v| dir.join(v))
+      }
+      "proto-path" => {
+        config.proto_paths = strings(name, value)?
+          .iter()
+          .map(|v| dir.join(v))
+          .collect()
+      }
+      "package" | "message" => {
+        config.options = apply_sections(config.options, name, value)?
+      }
+      _ => {
+        for option in to_options(name, value)? {
+          config.options = config.options.option(&option)?;
+        }
+      }
+    }
+  }
+
+  Ok(config)
+}
+
+// Nearest config file in the given directory or any above it
+pub fn discover(dir: &Path) -> Option<PathBuf> {
+  dir
+    .ancestors()
+    .map(|v| v.join(FILE_NAME))
+    .find(|v| v.is_file())
+}
+
+pub fn load(path: &Path) -> CliResult<Config> {
+  let input = read_to_string(path)
+    .map_err(|err| format!("Failed to read {}: {}", path.display(), err))?;
+  let dir = path.parent().unwrap_or(Path::new(""));
+
+  parse(&input, dir)
+    .map_err(|err| format!("{}: {}", path.display(), err).into())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parse_options() -> CliResult<()> {
+    let input = r##"
+      proto-path = ["protos", "vendor"]
+      out-dir = "src/gen"
+      serde = true
+      box-messages = false
+      derives = "Clone, Debug"
+      type-attribute = { ".pkg.Msg" = "#[derive(Hash)]" }
+    "##;
+    let config = parse(input, Path::new("root"))?;
+
+    assert_eq!(
+      config.proto_paths,
+      vec![Path::new("root/protos"), Path::new("root/vendor")]
+    );
+    assert_eq!(config.out_dir.as_deref(), Some(Path::new("root/src/gen")));
+    assert!(config.options.serde && !config.options.box_messages);
+    assert_eq!(config.options.derives.as_deref(), Some("Clone, Debug"));
+    assert_eq!(
+      config
+        .options
+        .type_attributes_for(".pkg.Msg"),
+      vec!["#[derive(Hash)]"]
+    );
+
+    Ok(())
+  }
+
+  #[test]
+  fn parse_sections() -> CliResult<()> {
+    let input = r#"
+      serde = true
+
+      [package.pkg]
+      derives = "Clone"
+
+      [message.".pkg.Msg"]
+      serde = false
+    "#;
+    let options = parse(input, Path::new(""))?.options;
+
+    assert!(options.scoped(".pkg.Other").serde);
+    assert!(!options.scoped(".pkg.Msg").serde);
+    assert_eq!(
+      options
+        .scoped(".pkg.Msg")
+        .derives
+        .as_deref(),
+      Some("Clone")
+    );
+
+    Ok(())
+  }
+
+  #[test]
+  fn discover_upwards() -> CliResult<()> {
+    // Each run has a directory of its own, removed before anything's checked
+    // so it's cleaned up even when the test fails
+    let root = std::env::temp_dir()
+      .join(format!("pbrs_discover_upwards_{}", std::process::id()));
+    let dir = root.join("protos/pkg");
+
+    std::fs::create_dir_all(&dir)?;
+    std::fs::write(root.join(FILE_NAME), "serde = true")?;
+
+    let found = discover(&dir);
+
+    std::fs::remove_dir_all(&root)?;
+    assert_eq!(found, Some(root.join(FILE_NAME)));
+
+    Ok(())
+  }
+
+  #[test]
+  fn reject_unknown_options() {
+    let result = parse("colour = true", Path::new(""));
+
+    assert_eq!(
+      result.map_err(|v| v.to_string()).err(),
+      Some("Unknown option colour".to_string())
+    );
+
+    // Map types and output layouts aren't configurable until map fields are
+    // read and files can be written other than one to one
+    for (input, option) in [
+      ("map-type = \"btree\"", "map-type"),
+      ("layout = \"package\"", "layout")
+    ] {
+      assert_eq!(
+        parse(input, Path::new(""))
+          .map_err(|v| v.to_string())
+          .err(),
+        Some(format!("Unknown option {}", option))
+      );
+    }
+  }
+}
@@ -0,0 +1,66 @@
+use super::{load_type, Args, CliResult};
+use crate::runtime::{json, text, DynamicMessage};
+
+// Decode a message read from stdin, printing it in text format or as JSON
+// given its schema, or with the numbers and wire types of its fields when
+// it's decoded raw, as in "decode --proto a.proto --type pkg.Msg"
+pub fn decode(args: &Args, input: &[u8]) -> CliResult<String> {
+  args.expect_flags(&["--json", "--proto", "--raw", "--type"])?;
+
+  if args.switch("--raw") {
+    return Ok(text::decode_raw(input)?);
+  }
+
+  let (descriptor, registry) = load_type(args)?;
+  let message = DynamicMessage::decode(descriptor, input)?;
+
+  match args.switch("--json") {
+    true => Ok(json::print_with(&message, &registry)),
+    false => Ok(text::print_with(&message, &registry))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::cli::tests::args;
+  use crate::runtime::encoding;
+
+  fn request() -> Vec<u8> {
+    let mut buf = Vec::new();
+
+    encoding::int32::encode(1, &3, &mut buf);
+
+    buf
+  }
+
+  #[test]
+  fn decode_with_schema() -> CliResult<()> {
+    let proto = "tests/fixtures/counter.proto";
+    let text = args(&["--proto", proto, "--type", "counter.Count"]);
+    let json = args(&["--proto", proto, "--type=.counter.Count", "--json"]);
+
+    assert_eq!(decode(&text, &request())?, "value: 3\n");
+    assert_eq!(decode(&json, &request())?, "{\n  \"value\": 3\n}");
+
+    Ok(())
+  }
+
+  #[test]
+  fn decode_without_schema() -> CliResult<()> {
+    assert_eq!(decode(&args(&["--raw"]), &request())?, "1: 3  # varint\n");
+
+    Ok(())
+  }
+
+  #[test]
+  fn reject_unknown_types() {
+    let proto = "tests/fixtures/counter.proto";
+    let result = decode(&args(&["--proto", proto, "--type", "Missing"]), &[]);
+
+    assert_eq!(
+      result.map_err(|v| v.to_string()),
+      Err("Unknown message type Missing".to_string())
+    );
+  }
+}
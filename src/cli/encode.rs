@@ -0,0 +1,48 @@
+use super::{load_type, Args, CliResult};
+use crate::runtime::{json, text, Message};
+
+// Encode a message read from stdin in text format, or as JSON with "--json",
+// checking it against its schema as in "encode --proto a.proto --type pkg.Msg"
+pub fn encode(args: &Args, input: &str) -> CliResult<Vec<u8>> {
+  args.expect_flags(&["--json", "--proto", "--type"])?;
+
+  let (descriptor, registry) = load_type(args)?;
+  let message = match args.switch("--json") {
+    true => json::parse_with(descriptor, input, &registry)?,
+    false => text::parse_with(descriptor, input, &registry)?
+  };
+
+  Ok(message.encode_to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::cli::decode;
+  use crate::cli::tests::args;
+
+  #[test]
+  fn encode_with_schema() -> CliResult<()> {
+    let proto = "tests/fixtures/counter.proto";
+    let text = args(&["--proto", proto, "--type", "counter.Count"]);
+    let json = args(&["--proto", proto, "--type", "counter.Count", "--json"]);
+    let bytes = encode(&text, "value: 5 label: \"hi\"")?;
+
+    assert_eq!(bytes, b"\x08\x05\x12\x02hi");
+    assert_eq!(encode(&json, r#"{"value": 5, "label": "hi"}"#)?, bytes);
+    assert_eq!(decode(&text, &bytes)?, "value: 5\nlabel: \"hi\"\n");
+
+    Ok(())
+  }
+
+  #[test]
+  fn reject_invalid_fields() {
+    let proto = "tests/fixtures/counter.proto";
+    let text = args(&["--proto", proto, "--type", "counter.Count"]);
+
+    assert_eq!(
+      encode(&text, "count: 5").map_err(|v| v.to_string()),
+      Err("Unknown field count".to_string())
+    );
+  }
+}
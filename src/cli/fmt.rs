@@ -0,0 +1,140 @@
+use super::{inputs, Args, CliResult};
+use crate::parser::indent;
+use std::fs::{read_to_string, write};
+
+// Braces opened and closed by a line, ignoring any in strings or comments,
+// and whether it ends within a block comment
+fn count_braces(line: &str, mut comment: bool) -> (usize, usize, bool) {
+  let mut chars = line.chars().peekable();
+  let mut quote = None;
+  let (mut opened, mut closed) = (0, 0);
+
+  while let Some(v) = chars.next() {
+    match (comment, quote, v) {
+      (true, _, '*') if chars.next_if_eq(&'/').is_some() => comment = false,
+      (true, ..) => {}
+      (_, Some(_), '\\') => {
+        chars.next();
+      }
+      (_, Some(q), v) if q == v => quote = None,
+      (_, Some(_), _) => {}
+      (_, None, '"' | '\'') => quote = Some(v),
+      (_, None, '/') if chars.next_if_eq(&'/').is_some() => break,
+      (_, None, '/') if chars.next_if_eq(&'*').is_some() => comment = true,
+      (_, None, '{') => opened += 1,
+      (_, None, '}') => match opened {
+        0 => closed += 1,
+        _ => opened -= 1
+      },
+      _ => {}
+    }
+  }
+
+  (opened, closed, comment)
+}
+
+// Reformat a ".proto" file, indenting each line by two spaces for every block
+// it's within, trimming trailing whitespace and collapsing runs of blank lines
+pub fn format_source(source: &str) -> String {
+  let mut lines: Vec<String> = Vec::new();
+  let mut depth: usize = 0;
+  let mut comment = false;
+
+  for line in source.lines() {
+    let line = line.trim();
+
+    if line.is_empty() {
+      if lines
+        .last()
+        .is_some_and(|v| !v.is_empty())
+      {
+        lines.push(String::new());
+      }
+
+      continue;
+    }
+
+    let (opened, closed, ends_in_comment) = count_braces(line, comment);
+    // Lines that close a block sit at the depth of the line that opened it,
+    // while those continuing a block comment line up with its first "*"
+    let (level, prefix) = match comment {
+      true if line.starts_with('*') => (depth, " "),
+      true => (depth, ""),
+      false => (depth.saturating_sub(closed), "")
+    };
+
+    lines.push(format!("{}{}{}", indent(level as u8), prefix, line));
+    depth = depth.saturating_sub(closed) + opened;
+    comment = ends_in_comment;
+  }
+
+  while lines
+    .last()
+    .is_some_and(|v| v.is_empty())
+  {
+    lines.pop();
+  }
+
+  format!("{}\n", lines.join("\n"))
+}
+
+// Format each file given in place, or list those that need formatting when
+// "--check" is given, failing if there are any
+pub fn fmt(args: &Args) -> CliResult<()> {
+  args.expect_flags(&["--check"])?;
+
+  let mut unformatted = 0;
+
+  for input in inputs(args, &args.config()?)? {
+    let source = read_to_string(&input.path)?;
+    let formatted = format_source(&source);
+
+    if formatted == source {
+      args.debug(format_args!("Unchanged {}", input.path.display()));
+
+      continue;
+    }
+
+    match args.switch("--check") {
+      true => {
+        println!("{}", input.path.display());
+        unformatted += 1;
+      }
+      false => {
+        write(&input.path, formatted)?;
+        args.info(format_args!("Formatted {}", input.path.display()));
+      }
+    }
+  }
+
+  match unformatted {
+    0 => Ok(()),
+    _ => Err(format!("{} files need formatting", unformatted).into())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn reindent_blocks() {
+    let input = "syntax = \"proto3\";   \n\n\n\nmessage Foo {\nstring a = 1; \
+                 // {\n    message Bar { int32 b = 1; }\n/* {\n* }\n*/\n}\n\n";
+
+    assert_eq!(
+      format_source(input),
+      "syntax = \"proto3\";\n\nmessage Foo {\n  string a = 1; // {\n  message \
+       Bar { int32 b = 1; }\n  /* {\n   * }\n   */\n}\n"
+    );
+  }
+
+  #[test]
+  fn keep_formatted_files() -> Result<(), std::io::Error> {
+    let source = read_to_string("tests/fixtures/counter.proto")?;
+
+    assert_eq!(format_source(&source), source);
+
+    Ok(())
+  }
+}
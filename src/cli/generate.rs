@@ -0,0 +1,113 @@
+use super::{inputs, output_path, Args, CliResult};
+use crate::runtime::descriptor::FileDescriptorSet;
+use crate::runtime::Message;
+use crate::{lexer, parser, tokeniser};
+use std::fs::{create_dir_all, read, read_to_string, write};
+use std::path::Path;
+
+const FLAGS: [&str; 3] =
+  ["--descriptor-set-in", "--descriptor-set-out", "--out-dir"];
+
+// Write generated code for the named file into the output directory, or
+// print it when there isn't one
+fn output(
+  args: &Args,
+  dir: Option<&Path>,
+  name: &str,
+  code: &str
+) -> CliResult<()> {
+  let Some(dir) = dir else {
+    println!("{}", code);
+
+    return Ok(());
+  };
+  let path = output_path(dir, name, "rs");
+
+  if let Some(parent) = path.parent() {
+    create_dir_all(parent)?;
+  }
+
+  write(&path, format!("{}\n", code))?;
+  args.info(format_args!("Wrote {}", path.display()));
+
+  Ok(())
+}
+
+// Generate Rust for each file given, or for the files of a descriptor set
+// read with "--descriptor-set-in"
+pub fn generate(args: &Args) -> CliResult<()> {
+  let config = args.config()?;
+  let options = args.options(&FLAGS, config.options.clone())?;
+  let out_dir = args
+    .value("--out-dir")
+    .map(Path::new)
+    .or(config.out_dir.as_deref());
+
+  // Schemas that were compiled elsewhere are read from their descriptor set
+  // in place of any source
+  if let Some(input) = args.value("--descriptor-set-in") {
+    let set = FileDescriptorSet::decode(&read(input)?)?;
+    let code = parser::translate_set(&set, options)?;
+
+    return output(args, out_dir, input, &code);
+  }
+
+  let inputs = inputs(args, &config)?;
+  let mut sources = Vec::new();
+
+  for input in &inputs {
+    args.debug(format_args!("Reading {}", input.path.display()));
+    sources.push(tokeniser::translate(&read_to_string(&input.path)?)?);
+  }
+
+  let mut schemas = Vec::new();
+  let mut problems = 0;
+
+  // Every file is checked before any code is written, so a broken schema
+  // doesn't leave code behind that refers to types which don't exist
+  for (input, tokens) in inputs.iter().zip(&sources) {
+    let blocks = lexer::translate(tokens)?;
+
+    for problem in parser::check(&blocks, options.clone()) {
+      eprintln!("{}: {}", input.path.display(), problem);
+      problems += 1;
+    }
+
+    schemas.push((input, blocks));
+  }
+
+  if problems > 0 {
+    return Err(format!("Found {} problems", problems).into());
+  }
+
+  let mut files = Vec::new();
+
+  for (input, blocks) in schemas {
+    // Descriptor sets are written alongside the generated code rather than
+    // in place of it
+    if args.switch("--descriptor-set-out") {
+      files.push(parser::describe(&input.name, &blocks, options.clone())?);
+    }
+
+    let code = parser::translate(blocks, options.clone());
+
+    // Files in extern paths are generated elsewhere
+    match code.is_empty() {
+      true => args.debug(format_args!("Skipping {}", input.path.display())),
+      false => output(args, out_dir, &input.name, &code)?
+    }
+  }
+
+  if let Some(out) = args.value("--descriptor-set-out") {
+    write(
+      out,
+      FileDescriptorSet {
+        file: files
+      }
+      .encode_to_vec()
+    )?;
+    args.debug(format_args!("Wrote {}", out));
+  }
+
+  Ok(())
+}
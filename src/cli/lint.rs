@@ -0,0 +1,157 @@
+use super::{inputs, Args, CliResult};
+use crate::lexer::{self, Block, Field, Kind};
+use crate::tokeniser;
+use heck::{ToSnakeCase, ToUpperCamelCase};
+use std::fs::read_to_string;
+
+fn check_name(
+  problems: &mut Vec<String>,
+  element: &str,
+  kind: &str,
+  name: &str
+) {
+  let (style, expected) = match kind {
+    "Field" | "Package" => ("lower_snake_case", name.to_snake_case()),
+    _ => ("UpperCamelCase", name.to_upper_camel_case())
+  };
+
+  if name != expected {
+    problems.push(format!(
+      "{}: {} name {} should be {}, as in {}",
+      element, kind, name, style, expected
+    ));
+  }
+}
+
+fn lint_fields(problems: &mut Vec<String>, scope: &str, fields: &[Field]) {
+  for field in fields {
+    match field {
+      Field::Block(block) => lint_block(problems, scope, block),
+      Field::Property(prop) => {
+        let element = format!("{}.{}", scope, prop.name);
+
+        check_name(problems, &element, "Field", prop.name);
+      }
+      Field::Rpc(rpc) => {
+        let element = format!("{}.{}", scope, rpc.name);
+
+        check_name(problems, &element, "Rpc", rpc.name);
+      }
+    }
+  }
+}
+
+fn lint_block(problems: &mut Vec<String>, scope: &str, block: &Block) {
+  let (kind, fields) = match &block.kind {
+    Kind::Message(fields) => ("Message", fields),
+    Kind::Service(fields) => ("Service", fields),
+    _ => return
+  };
+  let name = block.identifier.unwrap_or_default();
+  let element = format!("{}.{}", scope, name)
+    .trim_start_matches('.')
+    .to_string();
+
+  check_name(problems, &element, kind, name);
+  lint_fields(problems, &element, fields);
+}
+
+// Names within a file that don't follow the Protobuf style guide, along with
+// any syntax or package statement it's missing
+pub fn lint_blocks(blocks: &[Block]) -> Vec<String> {
+  let mut problems = Vec::new();
+  let package = blocks
+    .iter()
+    .find_map(|v| match v.kind {
+      Kind::Package(name) => Some(name),
+      _ => None
+    });
+
+  if !blocks
+    .iter()
+    .any(|v| matches!(v.kind, Kind::Syntax(_)))
+  {
+    problems.push("Missing syntax statement".to_string());
+  }
+
+  match package {
+    Some(package) => {
+      for segment in package.split('.') {
+        check_name(&mut problems, package, "Package", segment);
+      }
+    }
+    None => problems.push("Missing package statement".to_string())
+  }
+
+  for block in blocks {
+    lint_block(&mut problems, package.unwrap_or_default(), block);
+  }
+
+  problems
+}
+
+// Report style problems in each file given, failing when any are found
+pub fn lint(args: &Args) -> CliResult<()> {
+  args.expect_flags(&[])?;
+
+  let inputs = inputs(args, &args.config()?)?;
+  let mut problems = 0;
+
+  for input in &inputs {
+    args.debug(format_args!("Linting {}", input.path.display()));
+
+    let source = read_to_string(&input.path)?;
+    let tokens = tokeniser::translate(&source)?;
+
+    for problem in lint_blocks(&lexer::translate(&tokens)?) {
+      println!("{}: {}", input.path.display(), problem);
+      problems += 1;
+    }
+  }
+
+  match problems {
+    0 => {
+      args.info(format_args!("Linted {} files", inputs.len()));
+
+      Ok(())
+    }
+    _ => Err(format!("Found {} style problems", problems).into())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn lint(input: &str) -> Vec<String> {
+    let tokens = tokeniser::translate(input).unwrap_or_default();
+
+    lint_blocks(&lexer::translate(&tokens).unwrap_or_default())
+  }
+
+  #[test]
+  fn accept_styled_names() {
+    let input = "syntax = \"proto3\"; package my_pkg.v1; message Foo { \
+                 message Bar {} int32 foo_bar = 1; } service Baz { rpc \
+                 GetFoo(Foo) returns (Foo); }";
+
+    assert_eq!(lint(input), Vec::<String>::new());
+  }
+
+  #[test]
+  fn report_names() {
+    let input = "package Pkg; message foo_bar { int32 fooBar = 1; }";
+
+    assert_eq!(
+      lint(input),
+      vec![
+        "Missing syntax statement",
+        "Pkg: Package name Pkg should be lower_snake_case, as in pkg",
+        "Pkg.foo_bar: Message name foo_bar should be UpperCamelCase, as in \
+         FooBar",
+        "Pkg.foo_bar.fooBar: Field name fooBar should be lower_snake_case, as \
+         in foo_bar"
+      ]
+    );
+  }
+}
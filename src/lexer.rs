@@ -1,5 +1,7 @@
+mod descriptor;
 mod identifier;
 
+pub use descriptor::translate_descriptor;
 pub use identifier::{Block, Identifier, *};
 use std::cell::RefCell;
 use std::rc::Rc;
@@ -55,22 +57,24 @@ where
   Some(node.groups)
 }
 
-fn into_blocks(group: Vec<TokenGroup>) -> Vec<Block> {
+fn into_blocks(group: Vec<TokenGroup>) -> Result<Vec<Block>, String> {
   group
     .iter()
     .cloned()
     .map(|TokenGroup(tokens, children)| {
-      let (identifier, kind) = Identifier::identify(tokens, children);
+      let (identifier, kind) = Identifier::identify(tokens, children)?;
 
-      Block {
+      Ok(Block {
         identifier,
         kind
-      }
+      })
     })
     .collect()
 }
 
-pub fn translate(input: &Vec<String>) -> Vec<Block> {
+// Blocks of a file's tokens, failing when a block is missing the tokens its
+// kind needs, as with fields that have no number
+pub fn translate(input: &[String]) -> Result<Vec<Block<'_>>, String> {
   let mut tokens = input
     .iter()
     .map(|v| Rc::new(v.as_str()));
@@ -85,11 +89,8 @@ mod tests {
 
   #[test]
   fn test_group_tokens() {
-    let tokens = vec!["message", "Foo", "{", "}"];
-    let mut input = tokens
-      .iter()
-      .cloned()
-      .map(|v| Rc::new(v));
+    let tokens = ["message", "Foo", "{", "}"];
+    let mut input = tokens.iter().cloned().map(Rc::new);
     let result = group_tokens(&mut input).unwrap_or_default();
 
     assert_eq!(
@@ -99,9 +100,9 @@ mod tests {
   }
 
   #[test]
-  fn test_into_blocks() {
+  fn test_into_blocks() -> Result<(), String> {
     let input = vec![TokenGroup(vec!["message", "Foo"], Some(Vec::new()))];
-    let result = into_blocks(input);
+    let result = into_blocks(input)?;
 
     assert_eq!(
       result,
@@ -110,5 +111,7 @@ mod tests {
         kind: Kind::Message(Vec::new())
       }]
     );
+
+    Ok(())
   }
 }
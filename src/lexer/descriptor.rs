@@ -0,0 +1,170 @@
+use super::identifier::{Block, Field, Kind, Property, Rpc, Scalar, Type};
+use crate::runtime::descriptor::{
+  DescriptorProto,
+  FieldDescriptorProto,
+  FileDescriptorProto,
+  ServiceDescriptorProto
+};
+
+type DescriptorResult<T> = Result<T, String>;
+
+fn property(field: &FieldDescriptorProto) -> DescriptorResult<Field<'_>> {
+  let r#type = match field.r#type {
+    FieldDescriptorProto::TYPE_INT32 => Type::Scalar(Scalar::Int32),
+    FieldDescriptorProto::TYPE_BOOL => Type::Scalar(Scalar::Bool),
+    FieldDescriptorProto::TYPE_STRING => Type::Scalar(Scalar::r#String),
+    FieldDescriptorProto::TYPE_MESSAGE => Type::Message(&field.type_name),
+    v => return Err(format!("Unsupported type {} of field {}", v, field.name))
+  };
+
+  if field.label != FieldDescriptorProto::LABEL_OPTIONAL {
+    return Err(format!("Unsupported label of field {}", field.name));
+  }
+
+  Ok(Field::Property(Property {
+    r#type,
+    name: &field.name,
+    value: field.number
+  }))
+}
+
+// Nested messages are listed after the fields of a message, since a
+// descriptor doesn't keep the order they were declared in
+fn message(message: &DescriptorProto) -> DescriptorResult<Block<'_>> {
+  let fields = message
+    .field
+    .iter()
+    .map(property)
+    .chain(
+      message
+        .nested_type
+        .iter()
+        .map(|v| self::message(v).map(Field::Block))
+    )
+    .collect::<DescriptorResult<Vec<Field>>>()?;
+
+  Ok(Block {
+    identifier: Some(&message.name),
+    kind: Kind::Message(fields)
+  })
+}
+
+fn service(service: &ServiceDescriptorProto) -> Block<'_> {
+  let fields = service
+    .method
+    .iter()
+    .map(|v| {
+      Field::Rpc(Rpc {
+        name: &v.name,
+        params: (&v.input_type, &v.output_type),
+        streaming: (v.client_streaming, v.server_streaming)
+      })
+    })
+    .collect();
+
+  Block {
+    identifier: Some(&service.name),
+    kind: Kind::Service(fields)
+  }
+}
+
+// Blocks of a file that was compiled into a descriptor, matching those that
+// "translate" gives for its source. Every type is referred to by its
+// fully-qualified name
+pub fn translate_descriptor(
+  file: &FileDescriptorProto
+) -> DescriptorResult<Vec<Block<'_>>> {
+  // Descriptors leave the syntax unset for proto2 files
+  let syntax = match file.syntax.as_str() {
+    "" => "proto2",
+    v => v
+  };
+  let mut blocks = vec![Block {
+    identifier: None,
+    kind: Kind::Syntax(syntax)
+  }];
+
+  if !file.package.is_empty() {
+    blocks.push(Block {
+      identifier: None,
+      kind: Kind::Package(&file.package)
+    });
+  }
+
+  for v in &file.message_type {
+    blocks.push(message(v)?);
+  }
+
+  blocks.extend(file.service.iter().map(service));
+
+  Ok(blocks)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::lexer::translate;
+  use crate::parser::describe;
+  use crate::tokeniser;
+
+  #[test]
+  fn translate_described_file() -> Result<(), Box<dyn std::error::Error>> {
+    let input = "syntax = \"proto3\";\npackage foo;\nmessage Bar {\n  int32 \
+                 baz = 1;\n  Bar bar = 2;\n}\nservice Baz {\n  rpc Get(Bar) \
+                 returns (stream Bar);\n}";
+    let tokens = tokeniser::translate(input)?;
+    let file = describe("foo.proto", &translate(&tokens)?, Default::default())?;
+    let result = translate_descriptor(&file)?;
+
+    assert_eq!(result[0].kind, Kind::Syntax("proto3"));
+    assert_eq!(result[1].kind, Kind::Package("foo"));
+    assert_eq!(
+      result[2].kind,
+      Kind::Message(vec![
+        Field::Property(Property {
+          r#type: Type::Scalar(Scalar::Int32),
+          name: "baz",
+          value: 1
+        }),
+        Field::Property(Property {
+          r#type: Type::Message(".foo.Bar"),
+          name: "bar",
+          value: 2
+        }),
+      ])
+    );
+    assert_eq!(
+      result[3].kind,
+      Kind::Service(vec![Field::Rpc(Rpc {
+        name: "Get",
+        params: (".foo.Bar", ".foo.Bar"),
+        streaming: (false, true)
+      })])
+    );
+
+    Ok(())
+  }
+
+  #[test]
+  fn reject_unsupported_types() {
+    let file = FileDescriptorProto {
+      message_type: vec![DescriptorProto {
+        name: "Foo".to_string(),
+        field: vec![FieldDescriptorProto {
+          name: "bar".to_string(),
+          number: 1,
+          label: FieldDescriptorProto::LABEL_OPTIONAL,
+          r#type: 3,
+          ..Default::default()
+        }],
+        ..Default::default()
+      }],
+      ..Default::default()
+    };
+
+    assert_eq!(
+      translate_descriptor(&file).map(|_| ()),
+      Err("Unsupported type 3 of field bar".to_string())
+    );
+  }
+}
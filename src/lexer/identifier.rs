@@ -28,9 +28,17 @@ pub enum Scalar {
   r#String
 }
 
+// Type of a property, either a scalar or a reference to another message by
+// name, which may be qualified with its package as in "foo.bar.Baz"
+#[derive(Clone, PartialEq, Debug)]
+pub enum Type<'a> {
+  Scalar(Scalar),
+  Message(&'a str)
+}
+
 #[derive(Clone, PartialEq, Debug)]
 pub struct Property<'a> {
-  pub r#type: Scalar,
+  pub r#type: Type<'a>,
   pub name: &'a str,
   pub value: i32
 }
@@ -38,7 +46,9 @@ pub struct Property<'a> {
 #[derive(Clone, PartialEq, Debug)]
 pub struct Rpc<'a> {
   pub name: &'a str,
-  pub params: (&'a str, &'a str)
+  pub params: (&'a str, &'a str),
+  // Whether the request and response are each a stream of messages
+  pub streaming: (bool, bool)
 }
 
 // Any "block" of code, which can be either a simple expression or a scoped
@@ -54,85 +64,127 @@ pub struct Identifier<'a> {
   pub children: TokenChildren<'a>
 }
 
-impl<'a> From<Identifier<'a>> for (Option<&'a str>, Kind<'a>) {
-  fn from(value: Identifier<'a>) -> Self {
+impl<'a> TryFrom<Identifier<'a>> for (Option<&'a str>, Kind<'a>) {
+  type Error = String;
+
+  fn try_from(value: Identifier<'a>) -> Result<Self, Self::Error> {
     value.kind()
   }
 }
 
-impl<'a> From<Identifier<'a>> for Field<'a> {
-  fn from(value: Identifier<'a>) -> Self {
+impl<'a> TryFrom<Identifier<'a>> for Field<'a> {
+  type Error = String;
+
+  fn try_from(value: Identifier<'a>) -> Result<Self, Self::Error> {
     value.field()
   }
 }
 
 impl<'a> Identifier<'a> {
-  pub fn identify<T>(tokens: Vec<&'a str>, children: TokenChildren<'a>) -> T
+  // Identify a group of tokens, failing when it's missing any of the tokens
+  // its kind needs, such as the name of a message or the number of a field
+  pub fn identify<T>(
+    tokens: Vec<&'a str>,
+    children: TokenChildren<'a>
+  ) -> Result<T, String>
   where
-    T: From<Identifier<'a>>
+    T: TryFrom<Identifier<'a>, Error = String>
   {
     let this = Self {
       tokens,
       children
     };
 
-    this.into()
+    this.try_into()
+  }
+
+  fn token(&self, index: usize, name: &str) -> Result<&'a str, String> {
+    self
+      .tokens
+      .get(index)
+      .copied()
+      .ok_or_else(|| {
+        format!("Missing {} in \"{}\"", name, self.tokens.join(" "))
+      })
   }
 
-  fn scalar(&self, token: &str) -> Scalar {
+  fn r#type(&self, token: &'a str) -> Type<'a> {
     match token {
-      "int32" => Scalar::Int32,
-      "string" => Scalar::r#String,
-      "bool" => Scalar::Bool,
-      _ => panic!("Unidentified scalar")
+      "int32" => Type::Scalar(Scalar::Int32),
+      "string" => Type::Scalar(Scalar::r#String),
+      "bool" => Type::Scalar(Scalar::Bool),
+      _ => Type::Message(token)
+    }
+  }
+
+  fn param(tokens: Option<&&[&'a str]>) -> Result<(&'a str, bool), String> {
+    match tokens.copied().unwrap_or_default() {
+      ["stream", name] => Ok((name, true)),
+      [name, ..] => Ok((name, false)),
+      [] => Err("Missing rpc parameter".to_string())
     }
   }
 
-  fn kind(self) -> (Option<&'a str>, Kind<'a>) {
-    match self.tokens[0] {
-      id @ ("service" | "message") => {
-        let name = Some(self.tokens[1]);
+  fn kind(self) -> Result<(Option<&'a str>, Kind<'a>), String> {
+    match self.tokens.first().copied() {
+      Some(id @ ("service" | "message")) => {
+        let name = Some(self.token(1, "name")?);
         let fields = self
           .children
           .unwrap_or_default()
           .iter()
           .cloned()
           .map(|v| Identifier::identify(v.0, v.1))
-          .collect();
+          .collect::<Result<Vec<Field>, String>>()?;
 
         match id {
-          "service" => (name, Kind::Service(fields)),
-          "message" => (name, Kind::Message(fields)),
-          _ => unreachable!()
+          "service" => Ok((name, Kind::Service(fields))),
+          _ => Ok((name, Kind::Message(fields)))
         }
       }
-      "syntax" => (None, Kind::Syntax(self.tokens[3])),
-      "package" => (None, Kind::Package(self.tokens[1])),
-      _ => (None, Kind::Unknown)
+      Some("syntax") => Ok((None, Kind::Syntax(self.token(3, "syntax")?))),
+      Some("package") => Ok((None, Kind::Package(self.token(1, "package")?))),
+      _ => Ok((None, Kind::Unknown))
     }
   }
 
-  fn field(self) -> Field<'a> {
-    match self.tokens[0] {
+  fn field(self) -> Result<Field<'a>, String> {
+    match self.token(0, "field")? {
       "message" | "service" => {
-        let (identifier, kind) = self.kind();
+        let (identifier, kind) = self.kind()?;
 
-        Field::Block(Block {
+        Ok(Field::Block(Block {
           identifier,
           kind
-        })
+        }))
+      }
+      "rpc" => {
+        // Both parameters are wrapped in parentheses, separated by "returns"
+        let groups = self
+          .tokens
+          .split(|v| matches!(*v, "(" | ")"))
+          .collect::<Vec<&[&str]>>();
+        let (req, req_stream) = Self::param(groups.get(1))?;
+        let (res, res_stream) = Self::param(groups.get(3))?;
+
+        Ok(Field::Rpc(Rpc {
+          name: self.token(1, "name")?,
+          params: (req, res),
+          streaming: (req_stream, res_stream)
+        }))
+      }
+      val => {
+        let name = self.token(1, "name")?;
+        let value = self.token(3, "number")?;
+
+        Ok(Field::Property(Property {
+          r#type: self.r#type(val),
+          name,
+          value: value.parse().map_err(|_| {
+            format!("Invalid number {} for field {}", value, name)
+          })?
+        }))
       }
-      "rpc" => Field::Rpc(Rpc {
-        name: self.tokens[1],
-        params: (self.tokens[3], self.tokens[7])
-      }),
-      val => Field::Property(Property {
-        r#type: self.scalar(val),
-        name: self.tokens[1],
-        value: self.tokens[3]
-          .parse()
-          .expect("Invalid value for field")
-      })
     }
   }
 }
@@ -142,25 +194,81 @@ mod tests {
   use super::*;
 
   #[test]
-  fn identify_kind() {
+  fn identify_kind() -> Result<(), String> {
     let tokens = vec!["message", "Foo", "{", "}"];
-    let result = Identifier::identify::<(Option<&str>, Kind)>(tokens, None);
+    let result = Identifier::identify::<(Option<&str>, Kind)>(tokens, None)?;
 
     assert_eq!(result, (Some("Foo"), Kind::Message(Vec::new())));
+
+    Ok(())
   }
 
   #[test]
-  fn identify_field() {
+  fn identify_field() -> Result<(), String> {
     let tokens = vec!["int32", "foo", "=", "1", ";"];
-    let result = Identifier::identify::<Field>(tokens, None);
+    let result = Identifier::identify::<Field>(tokens, None)?;
 
     assert_eq!(
       result,
       Field::Property(Property {
-        r#type: Scalar::Int32,
+        r#type: Type::Scalar(Scalar::Int32),
         name: "foo",
         value: 1
       })
     );
+
+    Ok(())
+  }
+
+  #[test]
+  fn reject_invalid_fields() {
+    let field = |tokens| Identifier::identify::<Field>(tokens, None);
+
+    assert_eq!(
+      field(vec!["int32", "a", "=", "x"]),
+      Err("Invalid number x for field a".to_string())
+    );
+    assert_eq!(
+      field(vec!["int32", "a"]),
+      Err("Missing number in \"int32 a\"".to_string())
+    );
+    assert!(field(vec!["rpc", "Foo", "(", ")"]).is_err());
+    assert!(field(Vec::new()).is_err());
+  }
+
+  #[test]
+  fn identify_message_field() -> Result<(), String> {
+    let tokens = vec!["foo.Bar", "bar", "=", "2", ";"];
+    let result = Identifier::identify::<Field>(tokens, None)?;
+
+    assert_eq!(
+      result,
+      Field::Property(Property {
+        r#type: Type::Message("foo.Bar"),
+        name: "bar",
+        value: 2
+      })
+    );
+
+    Ok(())
+  }
+
+  #[test]
+  fn identify_streaming_rpc() -> Result<(), String> {
+    let tokens = vec![
+      "rpc", "Chat", "(", "stream", "Note", ")", "returns", "(", "Reply", ")",
+    ];
+    let result = Identifier::identify::<Field>(tokens, Some(Vec::new()))?;
+
+    assert_eq!(
+      result,
+      Field::Rpc(Rpc {
+        name: "Chat",
+        params: ("Note", "Reply"),
+        streaming: (true, false)
+      })
+    );
+
+    Ok(())
   }
 }
@@ -0,0 +1,6 @@
+pub mod cli;
+pub mod lexer;
+pub mod parser;
+pub mod plugin;
+pub mod runtime;
+pub mod tokeniser;
@@ -1,12 +1,45 @@
-use super::lexer::{Block, Field, Kind, Scalar};
-use heck::ToSnakeCase;
+mod check;
+mod descriptor;
+mod mask;
+mod message;
+mod naming;
+mod options;
+mod service;
+
+use super::lexer::{translate_descriptor, Block, Field, Kind, Scalar, Type};
+use crate::runtime::descriptor::{FileDescriptorProto, FileDescriptorSet};
+pub use options::{Options, TimeTypes};
 use regex::RegexBuilder;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::iter::repeat_n;
+
+// Paths to the runtime types that generated services are built on
+const RUNTIME_REQUEST: &str = "::pbrs::runtime::Request";
+const RUNTIME_RESPONSE: &str = "::pbrs::runtime::Response";
+const RUNTIME_STATUS: &str = "::pbrs::runtime::Status";
+const RUNTIME_ENCODING: &str = "::pbrs::runtime::encoding";
+const RUNTIME_STREAMING: &str = "::pbrs::runtime::Streaming";
+const RUNTIME_REGISTRY: &str = "::pbrs::runtime::TypeRegistry";
 
 pub fn indent(depth: u8) -> String {
   (0..depth).map(|_| "  ").collect()
 }
 
+pub fn wrap_mod(name: &str, input: &str) -> String {
+  // Replacer for adding indentation to each line, other than blank lines
+  // which would be left with trailing whitespace
+  let re = RegexBuilder::new(r"^(.)")
+    .multi_line(true)
+    .build()
+    .unwrap();
+
+  format!(
+    "pub mod {} {{\n{}\n}}",
+    name,
+    re.replace_all(input, format!("{}${{1}}", indent(1)))
+  )
+}
+
 impl From<Scalar> for String {
   fn from(value: Scalar) -> String {
     let result = match value {
@@ -19,22 +52,79 @@ impl From<Scalar> for String {
   }
 }
 
+// Names a relative name may refer to from within a scope, from the innermost
+// scope outwards, as in ".pkg.Outer.Inner.Foo", then ".pkg.Outer.Foo", then
+// ".pkg.Foo"
+fn scoped_names<'s>(
+  scope: &'s str,
+  name: &'s str
+) -> impl Iterator<Item = String> + 's {
+  scope
+    .match_indices('.')
+    .map(|(i, _)| &scope[..i])
+    .chain([scope])
+    .rev()
+    .map(move |v| format!("{}.{}", v, name))
+}
+
 #[derive(Default)]
 struct Parser<'a> {
-  config: HashMap<&'a str, &'a str>,
-  root: Vec<String>
+  edges: HashMap<String, HashSet<String>>,
+  nested: Vec<Vec<String>>,
+  options: Options,
+  package: Option<&'a str>,
+  root: Vec<String>,
+  scope: Vec<&'a str>,
+  symbols: HashSet<String>
 }
 
 impl<'a> Parser<'a> {
+  fn prepare(&mut self, blocks: &[Block<'a>]) {
+    // The package is needed to qualify the path of every message, regardless
+    // of where it was declared within the source
+    for block in blocks {
+      if let Kind::Package(name) = block.kind {
+        self.package = Some(name);
+      }
+    }
+
+    // Every message in the file must be known up front so references can be
+    // resolved to messages declared further down or nested in others
+    let package = self.path("");
+
+    self.collect_symbols(package.trim_end_matches('.'), blocks);
+    self.collect_edges(package.trim_end_matches('.'), blocks);
+  }
+
   pub fn parse(&mut self, blocks: Vec<Block<'a>>) -> String {
+    match self.parse_items(blocks) {
+      Some(items) => self.result(items),
+      None => String::new()
+    }
+  }
+
+  // Code for the blocks of a package without the modules of its name around
+  // it, or nothing for packages that are generated elsewhere
+  fn parse_items(&mut self, blocks: Vec<Block<'a>>) -> Option<String> {
+    self.prepare(&blocks);
+
+    // Packages that are generated elsewhere have no code of their own
+    if self.is_extern("") {
+      return None;
+    }
+
     // Collect and parse all blocks of code into an array of String
-    let items = blocks
+    let mut items = blocks
       .iter()
       .cloned()
       .filter_map(|v| self.parse_block(v))
       .collect::<Vec<String>>();
-    // Join the blocks onto the root collection of structs so all nested
-    // structs are placed at the top of the rendered output
+
+    items.extend(self.format_registry());
+
+    // Join the blocks onto the root collection of structs so any structs
+    // generated alongside services are placed at the top of the rendered
+    // output
     let total = self
       .root
       .iter()
@@ -43,25 +133,96 @@ impl<'a> Parser<'a> {
       .collect::<Vec<String>>()
       .join("\n\n");
 
-    self.result(total)
+    Some(total)
   }
 
-  fn result(&mut self, input: String) -> String {
-    // Replacer for adding indentation to each line
-    let re = RegexBuilder::new(r"^")
-      .multi_line(true)
-      .build()
-      .unwrap();
-
-    // If the package has been defined, wrap the result in a mod block
-    match self.config.get("package") {
-      Some(name) => {
-        format!(
-          "pub mod {} {{\n{}\n}}",
-          name,
-          re.replace_all(&input, indent(1))
-        )
+  // Function adding every message of the package to a type registry, so Any
+  // fields holding them can be expanded
+  fn format_registry(&self) -> Option<String> {
+    let mut names = self
+      .symbols
+      .iter()
+      .filter(|v| self.options.extern_type(v).is_none())
+      .collect::<Vec<&String>>();
+
+    if names.is_empty() {
+      return None;
+    }
+
+    names.sort();
+
+    let types = names
+      .iter()
+      .map(|v| format!("{}.add::<{}>()", indent(2), self.resolve(v)))
+      .collect::<Vec<String>>();
+
+    Some(format!(
+      "pub fn register_types(\n{}registry: {}\n) -> {} {{\n{}registry\n{}\n}}",
+      indent(1),
+      RUNTIME_REGISTRY,
+      RUNTIME_REGISTRY,
+      indent(1),
+      types.join("\n")
+    ))
+  }
+
+  fn collect_symbols(&mut self, scope: &str, blocks: &[Block<'a>]) {
+    for block in blocks {
+      if let (Kind::Message(fields), Some(id)) = (&block.kind, block.identifier)
+      {
+        let name = format!("{}.{}", scope, id);
+        let nested = fields
+          .iter()
+          .filter_map(|v| match v {
+            Field::Block(block) => Some(block.clone()),
+            _ => None
+          })
+          .collect::<Vec<Block>>();
+
+        self.collect_symbols(&name, &nested);
+        self.symbols.insert(name);
       }
+    }
+  }
+
+  fn collect_edges(&mut self, scope: &str, blocks: &[Block<'a>]) {
+    // Edges from each message to the messages its fields refer to, which
+    // reveal any messages that can contain themselves
+    for block in blocks {
+      if let (Kind::Message(fields), Some(id)) = (&block.kind, block.identifier)
+      {
+        let name = format!("{}.{}", scope, id);
+
+        for field in fields {
+          match field {
+            Field::Block(block) => {
+              self.collect_edges(&name, std::slice::from_ref(block))
+            }
+            Field::Property(prop) => {
+              if let Type::Message(r#type) = prop.r#type {
+                if let Some(target) = self.lookup_in(&name, r#type) {
+                  self
+                    .edges
+                    .entry(name.clone())
+                    .or_default()
+                    .insert(target);
+                }
+              }
+            }
+            _ => ()
+          }
+        }
+      }
+    }
+  }
+
+  fn result(&mut self, input: String) -> String {
+    // If the package has been defined, wrap the result in a mod block for
+    // each segment of its name, starting from the innermost
+    match self.package {
+      Some(name) => name
+        .rsplit('.')
+        .fold(input, |acc, v| wrap_mod(&naming::snake(v), &acc)),
       None => input
     }
   }
@@ -70,24 +231,24 @@ impl<'a> Parser<'a> {
     let id = block.identifier.unwrap_or_default();
 
     match block.kind {
+      _ if self.is_extern(id) => None,
       Kind::Message(fields) => Some(self.format_block("struct", id, fields)),
       Kind::Service(fields) => {
-        // While Protobuf supports nested message structures, Rust isn't so
-        // forgiving - nested messages need to be moved to the root depth
-        // where they can be referred to as types in child messages
-        self
-          .root
-          .push(format!("pub struct {}Client {{}}", id));
-
-        Some(self.format_block("trait", id, fields))
-      }
-      Kind::Package(name) => {
-        self.config.insert("package", name);
+        let client = self.format_client(id, &fields);
+        let server = self.format_server(id, &fields);
+        let mock = self.format_mock(id, &fields);
 
-        None
+        self.root.push(client);
+
+        Some(format!(
+          "{}\n\n{}\n\n{}",
+          self.format_block("trait", id, fields),
+          server,
+          mock
+        ))
       }
-      Kind::Syntax(syn) => {
-        self.config.insert("syntax", syn);
+      Kind::Package(name) => {
+        self.package = Some(name);
 
         None
       }
@@ -95,74 +256,467 @@ impl<'a> Parser<'a> {
     }
   }
 
-  fn format_field(&mut self, field: Field<'a>) -> String {
+  fn format_field(&mut self, field: Field<'a>) -> Option<String> {
     match field {
       Field::Block(block) => {
-        let id = block.identifier.unwrap_or_default();
         let struct_block = self
           .parse_block(block)
           .unwrap_or_default();
 
-        // Push the generated struct into the root collection so it can be
-        // rendered at the top of the output
-        self.root.push(struct_block);
+        // Nested messages are rendered in a module named after the parent
+        // once all of the parent's fields have been formatted, and only
+        // declare a type rather than a field of the parent
+        if let Some(nested) = self.nested.last_mut() {
+          nested.push(struct_block);
+        }
 
-        self.format_property(id.to_snake_case(), id.to_string())
+        None
       }
       Field::Property(prop) => {
-        let r#type: String = prop.r#type.clone().into();
+        let r#type = self.format_type(&prop.r#type);
+
+        Some(self.format_property(prop.name, naming::snake(prop.name), r#type))
+      }
+      Field::Rpc(rpc) => Some(self.format_method(&rpc))
+    }
+  }
+
+  fn path(&self, name: &str) -> String {
+    // Fully-qualified Protobuf path of a name within the current scope, as
+    // in ".pkg.Msg.field"
+    self
+      .package
+      .iter()
+      .chain(self.scope.iter())
+      .chain([&name])
+      .fold(String::new(), |acc, v| format!("{}.{}", acc, v))
+  }
+
+  fn format_type(&self, r#type: &Type) -> String {
+    match r#type {
+      Type::Scalar(scalar) => scalar.clone().into(),
+      Type::Message(name) => {
+        // Times are never boxed, as they can't contain the message
+        if let Some(time) = self.time_type(name) {
+          return format!("Option<{}>", time);
+        }
+
+        let current = self.path("");
+        let current = current.trim_end_matches('.');
+        // Proto3 message fields may always be unset, and need to be boxed
+        // when the message can contain itself so it has a known size
+        let boxed = self
+          .options
+          .scoped(current)
+          .box_messages
+          || self
+            .lookup(name)
+            .is_some_and(|v| self.reaches(&v, current));
+
+        match boxed {
+          true => format!("Option<Box<{}>>", self.resolve(name)),
+          false => format!("Option<{}>", self.resolve(name))
+        }
+      }
+    }
+  }
+
+  // Rust type that a Timestamp or Duration field of the current message is
+  // generated as, when "time-types" is set for it
+  fn time_type(&self, name: &str) -> Option<&'static str> {
+    let scope = self.path("");
+    let types = self
+      .options
+      .scoped(scope.trim_end_matches('.'))
+      .time_types?;
+
+    match self.lookup(name) {
+      Some(_) => None,
+      None => types.rust_type(name)
+    }
+  }
+
+  fn reaches(&self, from: &str, to: &str) -> bool {
+    let mut visited = HashSet::new();
+    let mut pending = vec![from.to_string()];
 
-        self.format_property(prop.name.to_string(), r#type)
+    while let Some(name) = pending.pop() {
+      if name == to {
+        return true;
       }
-      Field::Rpc(rpc) => {
-        format!(
-          "{}fn {}(req: {}) -> {} {{\n{}\n{}}}",
-          indent(1),
-          rpc.name.to_snake_case(),
-          rpc.params.0,
-          rpc.params.1,
-          format!("{}{}::default()", indent(2), rpc.params.1),
-          indent(1)
-        )
+
+      if visited.insert(name.clone()) {
+        pending.extend(
+          self
+            .edges
+            .get(&name)
+            .into_iter()
+            .flatten()
+            .cloned()
+        );
       }
     }
+
+    false
   }
 
-  fn format_property(&self, name: String, id: String) -> String {
-    format!("{}pub {}: {}", indent(1), name, id)
+  fn lookup(&self, name: &str) -> Option<String> {
+    let scope = self.path("");
+
+    self.lookup_in(scope.trim_end_matches('.'), name)
+  }
+
+  fn lookup_in(&self, scope: &str, name: &str) -> Option<String> {
+    if name.starts_with('.') {
+      return self.symbols.get(name).cloned();
+    }
+
+    scoped_names(scope, name).find(|v| self.symbols.contains(v))
+  }
+
+  // Whether a name in the current scope is within an extern path
+  fn is_extern(&self, name: &str) -> bool {
+    let path = self.path(name);
+
+    self
+      .options
+      .extern_type(path.trim_end_matches('.'))
+      .is_some()
+  }
+
+  // Rust path of a type that's generated elsewhere, looked up from the
+  // innermost scope outwards just as messages in the file are
+  fn lookup_extern_in(&self, scope: &str, name: &str) -> Option<String> {
+    if name.starts_with('.') {
+      return self.options.extern_type(name);
+    }
+
+    scoped_names(scope, name).find_map(|v| self.options.extern_type(&v))
+  }
+
+  fn resolve(&self, name: &str) -> String {
+    let scope = self.path("");
+
+    if self.lookup(name).is_none() {
+      if let Some(path) =
+        self.lookup_extern_in(scope.trim_end_matches('.'), name)
+      {
+        return path;
+      }
+    }
+
+    let package = self
+      .package
+      .map(|v| v.split('.').collect::<Vec<&str>>())
+      .unwrap_or_default();
+    let found = self.lookup(name);
+    let segments = found
+      .as_deref()
+      .unwrap_or(name)
+      .trim_start_matches('.')
+      .split('.')
+      .collect::<Vec<&str>>();
+    // Messages declared within the file are in its package, while packages
+    // elsewhere are told apart from messages by convention, as packages are
+    // lowercase and messages are capitalised
+    let split = match found {
+      Some(_) => package.len(),
+      None => segments
+        .iter()
+        .position(|v| v.starts_with(char::is_uppercase))
+        .unwrap_or(segments.len() - 1)
+    };
+
+    if found.is_none() && split == 0 && !name.starts_with('.') {
+      return naming::upper_camel(name);
+    }
+
+    // Messages nested in other messages are found in a module named after
+    // each of their parents, beneath the modules of their package
+    let (id, parents) = segments
+      .split_last()
+      .unwrap_or((&"", &[]));
+    let target = parents
+      .iter()
+      .map(|v| naming::snake(v))
+      .collect::<Vec<String>>();
+    // References are made from the module holding the message that's being
+    // rendered, which excludes the message itself
+    let current = package
+      .iter()
+      .chain(&self.scope[..self.scope.len().saturating_sub(1)])
+      .map(|v| naming::snake(v))
+      .collect::<Vec<String>>();
+    let common = current
+      .iter()
+      .zip(&target)
+      .take_while(|(a, b)| a == b)
+      .count();
+
+    repeat_n("super".to_string(), current.len() - common)
+      .chain(target[common..].iter().cloned())
+      .chain([naming::upper_camel(id)])
+      .collect::<Vec<String>>()
+      .join("::")
+  }
+
+  fn format_property(&self, proto: &str, name: String, id: String) -> String {
+    let scope = self.path("");
+    let options = self
+      .options
+      .scoped(scope.trim_end_matches('.'));
+    let mut lines = options
+      .field_attributes_for(&self.path(proto))
+      .iter()
+      .map(|v| format!("{}{}", indent(1), v))
+      .collect::<Vec<String>>();
+
+    // Serde should read and write the name used in the Protobuf source, which
+    // only needs spelling out when the Rust name differs from it
+    if options.serde && proto != name.trim_start_matches("r#") {
+      lines.push(format!("{}#[serde(rename = \"{}\")]", indent(1), proto));
+    }
+
+    lines.push(format!("{}pub {}: {}", indent(1), name, id));
+    lines.join("\n")
+  }
+
+  fn format_attributes(&self, desc: &str, id: &str) -> String {
+    let mut lines = Vec::new();
+    let options = self.options.scoped(&self.path(id));
+
+    if desc == "struct" {
+      if let Some(derives) = &options.derives {
+        lines.push(format!("#[derive({})]", derives));
+      }
+
+      // Proto3 treats every field as optional, so missing fields fall back to
+      // their defaults rather than failing to deserialise
+      if options.serde {
+        lines.push(
+          "#[derive(::serde::Serialize, \
+           ::serde::Deserialize)]\n#[serde(default)]"
+            .to_string()
+        );
+      }
+    }
+
+    lines.extend(
+      options
+        .type_attributes_for(&self.path(id))
+        .iter()
+        .map(|v| v.to_string())
+    );
+
+    lines
+      .iter()
+      .map(|v| format!("{}\n", v))
+      .collect()
   }
 
   fn format_block(
     &mut self,
     desc: &str,
-    id: &str,
+    id: &'a str,
     fields: Vec<Field<'a>>
   ) -> String {
+    let attributes = self.format_attributes(desc, id);
+    // Struct fields are separated by commas, while trait methods are items of
+    // their own, and services are shared across the threads serving them
+    let (separator, bounds) = match desc {
+      "trait" => ("\n\n", ": Send + Sync + 'static"),
+      _ => (",\n", "")
+    };
+
+    self.scope.push(id);
+    self.nested.push(Vec::new());
+
     let result = fields
       .iter()
       .cloned()
-      .map(|v| self.format_field(v))
+      .filter_map(|v| self.format_field(v))
       .collect::<Vec<String>>()
-      .join(",\n");
+      .join(separator);
+    let nested = self.nested.pop().unwrap_or_default();
+    let implementation = match desc {
+      "struct" => format!(
+        "\n\n{}\n\n{}",
+        self.format_message(id, &fields),
+        self.format_mask(id, &fields)
+      ),
+      _ => String::new()
+    };
+
+    self.scope.pop();
+
+    let output = format!(
+      "{}pub {} {}{} {{\n{}\n}}{}",
+      attributes,
+      desc,
+      naming::upper_camel(id),
+      bounds,
+      result,
+      implementation
+    );
+
+    match nested.is_empty() {
+      true => output,
+      false => {
+        let module = wrap_mod(&naming::snake(id), &nested.join("\n\n"));
+
+        format!("{}\n\n{}", output, module)
+      }
+    }
+  }
+}
+
+// Module of the code generated for a package, along with the modules of any
+// packages within it, so packages sharing a prefix such as "foo.bar" and
+// "foo.baz" are rendered into the same "foo" module
+#[derive(Default)]
+struct Module {
+  items: Vec<String>,
+  children: Vec<(String, Module)>
+}
+
+impl Module {
+  fn insert(&mut self, package: &str, items: String) {
+    let module = package
+      .split('.')
+      .filter(|v| !v.is_empty())
+      .fold(self, |module, segment| {
+        let index = match module
+          .children
+          .iter()
+          .position(|(v, _)| v == segment)
+        {
+          Some(v) => v,
+          None => {
+            module
+              .children
+              .push((segment.to_string(), Module::default()));
+            module.children.len() - 1
+          }
+        };
 
-    format!("pub {} {} {{\n{}\n}}", desc, id, result)
+        &mut module.children[index].1
+      });
+
+    module.items.push(items);
+  }
+
+  fn render(&self) -> String {
+    let children = self
+      .children
+      .iter()
+      .map(|(name, module)| wrap_mod(&naming::snake(name), &module.render()));
+
+    self
+      .items
+      .iter()
+      .filter(|v| !v.is_empty())
+      .cloned()
+      .chain(children)
+      .collect::<Vec<String>>()
+      .join("\n\n")
   }
 }
 
-pub fn translate(blocks: Vec<Block>) -> String {
-  let mut parser = Parser::default();
+pub fn translate(blocks: Vec<Block>, options: Options) -> String {
+  let mut parser = Parser {
+    options,
+    ..Default::default()
+  };
 
   parser.parse(blocks)
 }
 
+// Code for every file of a descriptor set, where files that share a package
+// are rendered together into the same module
+pub fn translate_set(
+  set: &FileDescriptorSet,
+  options: Options
+) -> Result<String, String> {
+  let mut packages: Vec<(&str, Vec<Block>)> = Vec::new();
+
+  for file in &set.file {
+    // Files in extern paths, such as the well-known types that protoc adds
+    // to descriptor sets, are skipped before they're translated, as they
+    // may use types that can't be
+    if options
+      .extern_type(&format!(".{}", file.package))
+      .is_some()
+    {
+      continue;
+    }
+
+    let blocks = translate_descriptor(file)?;
+
+    match packages
+      .iter_mut()
+      .find(|(v, _)| *v == file.package)
+    {
+      Some((_, v)) => v.extend(blocks),
+      None => packages.push((&file.package, blocks))
+    }
+  }
+
+  let mut root = Module::default();
+
+  for (package, blocks) in packages {
+    let mut parser = Parser {
+      options: options.clone(),
+      ..Default::default()
+    };
+
+    if let Some(items) = parser.parse_items(blocks) {
+      root.insert(package, items);
+    }
+  }
+
+  Ok(root.render())
+}
+
+// Descriptor of a parsed file as protoc would write it to a descriptor set,
+// given the name of the file it was read from, which fails for references to
+// messages that are neither in the file nor in an extern path
+pub fn describe(
+  name: &str,
+  blocks: &[Block],
+  options: Options
+) -> Result<FileDescriptorProto, String> {
+  let mut parser = Parser {
+    options,
+    ..Default::default()
+  };
+
+  parser.describe_file(name, blocks)
+}
+
+// Problems with a parsed file that would stop protoc compiling it, such as
+// references to messages that don't exist or field numbers used twice
+pub fn check(blocks: &[Block], options: Options) -> Vec<String> {
+  let mut parser = Parser {
+    options,
+    ..Default::default()
+  };
+
+  parser.prepare(blocks);
+
+  let package = parser.path("");
+
+  parser.check_blocks(package.trim_end_matches('.'), blocks)
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
-  use crate::lexer::{Property, Rpc};
+  use crate::lexer::{Property, Rpc, Type};
+  use crate::runtime::Message;
 
   fn create_message<'a>() -> Vec<Block<'a>> {
     let fields = vec![Field::Property(Property {
-      r#type: Scalar::Int32,
+      r#type: Type::Scalar(Scalar::Int32),
       name: "bar",
       value: 1
     })];
@@ -176,30 +730,379 @@ mod tests {
   #[test]
   fn translate_struct() {
     let input = create_message();
-    let result = translate(input);
+    let result = translate(input, Options::default());
 
-    assert_eq!(result, "pub struct Foo {\n  pub bar: i32\n}");
+    assert_eq!(
+      result,
+      "#[derive(Clone, Debug, PartialEq, Default)]\npub struct Foo {\n  pub \
+       bar: i32\n}\n\nimpl ::pbrs::runtime::Message for Foo {\n  fn \
+       encode(&self, buf: &mut Vec<u8>) {\n    \
+       ::pbrs::runtime::encoding::int32::encode(1, &self.bar, buf);\n  }\n\n  \
+       fn merge_field(\n    &mut self,\n    number: u32,\n    wire: \
+       ::pbrs::runtime::WireType,\n    buf: &mut &[u8]\n  ) -> Result<(), \
+       ::pbrs::runtime::DecodeError> {\n    match number {\n      1 => \
+       ::pbrs::runtime::encoding::int32::merge(wire, &mut self.bar, buf),\n      \
+       _ => ::pbrs::runtime::encoding::skip_field(wire, buf)\n    }\n  }\n\n  \
+       fn descriptor() -> &'static ::pbrs::runtime::MessageDescriptor {\n    \
+       static DESCRIPTOR: ::pbrs::runtime::MessageDescriptor =\n      \
+       ::pbrs::runtime::MessageDescriptor {\n        name: \"Foo\",\n        \
+       full_name: \"Foo\",\n        fields: &[\n          \
+       ::pbrs::runtime::FieldDescriptor {\n            name: \"bar\",\n            \
+       json_name: \"bar\",\n            number: 1,\n            label: \
+       ::pbrs::runtime::Label::Optional,\n            r#type: \
+       ::pbrs::runtime::FieldType::Int32\n          }\n        ]\n      };\n\n    \
+       &DESCRIPTOR\n  }\n}\n\nimpl ::pbrs::runtime::Masked for Foo {\n  fn \
+       is_path(path: &[&str]) -> bool {\n    \
+       ::pbrs::runtime::mask::is_field(path, \"bar\")\n  }\n\n  fn \
+       merge_path(&mut self, source: &mut Self, path: &[&str]) {\n    \
+       ::pbrs::runtime::mask::merge_field(&mut self.bar, &mut source.bar, \
+       path, \"bar\");\n  }\n\n  fn trim_paths(&mut self, paths: \
+       &[&[&str]]) {\n    ::pbrs::runtime::mask::trim_field(&mut self.bar, \
+       paths, \"bar\");\n  }\n}\n\npub fn register_types(\n  registry: \
+       ::pbrs::runtime::TypeRegistry\n) -> ::pbrs::runtime::TypeRegistry {\n  \
+       registry\n    .add::<Foo>()\n}"
+    );
   }
 
   #[test]
   fn translate_trait() {
     let fields = vec![Field::Rpc(Rpc {
       name: "Foo",
-      params: ("Request", "Response")
+      params: ("Request", "Response"),
+      streaming: (false, false)
     })];
     let input = vec![Block {
       identifier: Some("Bar"),
       kind: Kind::Service(fields)
     }];
-    let result = translate(input);
+    let result = translate(input, Options::default());
 
     assert_eq!(
       result,
-      "pub struct BarClient {}\n\npub trait Bar {\n  fn foo(req: Request) -> \
-       Response {\n    Response::default()\n  }\n}"
+      "#[derive(Clone, Debug)]\npub struct BarClient<T> {\n  transport: \
+       T\n}\n\nimpl<T: ::pbrs::runtime::Transport> BarClient<T> {\n  pub fn \
+       new(transport: T) -> Self {\n    Self { transport }\n  }\n\n  pub \
+       async fn foo(&self, req: &Request) -> Result<Response, \
+       ::pbrs::runtime::Status> {\n    self\n      .foo_with_options(req, \
+       Default::default())\n      .await\n      .map(|v| v.message)\n  }\n\n  \
+       pub async fn foo_with_options(\n    &self,\n    req: &Request,\n    \
+       options: ::pbrs::runtime::CallOptions\n  ) -> \
+       Result<::pbrs::runtime::Response<Response>, ::pbrs::runtime::Status> \
+       {\n    ::pbrs::runtime::unary(&self.transport, \"/Bar/Foo\", req, \
+       options).await\n  }\n}\n\npub trait Bar: Send + Sync + 'static {\n  fn \
+       foo(\n    &self,\n    req: ::pbrs::runtime::Request<Request>\n  ) -> \
+       impl ::std::future::Future<Output = \
+       Result<::pbrs::runtime::Response<Response>, ::pbrs::runtime::Status>> \
+       + Send;\n}\n\npub struct BarServer<S> {\n  inner: \
+       ::std::sync::Arc<S>\n}\n\nimpl<S: Bar> BarServer<S> {\n  pub fn \
+       new(inner: S) -> Self {\n    \
+       Self::from_arc(::std::sync::Arc::new(inner))\n  }\n\n  pub fn \
+       from_arc(inner: ::std::sync::Arc<S>) -> Self {\n    Self { inner }\n  \
+       }\n}\n\nimpl<S: Bar> ::pbrs::runtime::Service for BarServer<S> {\n  fn \
+       name(&self) -> &'static str {\n    \"Bar\"\n  }\n\n  fn call<'a>(\n    \
+       &'a self,\n    path: &'a str,\n    req: \
+       ::pbrs::runtime::Request<::pbrs::runtime::Streaming<Vec<u8>>>\n  ) -> \
+       ::pbrs::runtime::BoxFuture<'a, \
+       Result<::pbrs::runtime::Response<::pbrs::runtime::Streaming<Vec<u8>>>, \
+       ::pbrs::runtime::Status>> {\n    match path {\n      \"/Bar/Foo\" => \
+       Box::pin(::pbrs::runtime::handle_unary(req, |req| {\n        \
+       self.inner.foo(req)\n      })),\n      _ => Box::pin(async move { \
+       Err(::pbrs::runtime::Status::unimplemented(path)) })\n    }\n  \
+       }\n}\n\npub struct MockBar {\n  pub foo: \
+       ::pbrs::runtime::MockMethod<Request, Response>\n}\n\nimpl MockBar {\n  \
+       pub fn new() -> Self {\n    Self {\n      foo: \
+       ::pbrs::runtime::MockMethod::new(\"/Bar/Foo\")\n    }\n  }\n\n  // \
+       Panic unless every method was called as expected\n  pub fn \
+       verify(&self) {\n    self.foo.verify();\n  }\n}\n\nimpl Default for \
+       MockBar {\n  fn default() -> Self {\n    Self::new()\n  }\n}\n\nimpl \
+       Bar for MockBar {\n  fn foo(\n    &self,\n    req: \
+       ::pbrs::runtime::Request<Request>\n  ) -> impl \
+       ::std::future::Future<Output = \
+       Result<::pbrs::runtime::Response<Response>, ::pbrs::runtime::Status>> \
+       + Send {\n    self.foo.call(req)\n  }\n}"
     );
   }
 
+  #[test]
+  fn sanitise_rpc_names() {
+    let rpc = |name| {
+      Field::Rpc(Rpc {
+        name,
+        params: ("Request", "Response"),
+        streaming: (false, false)
+      })
+    };
+    let input = vec![Block {
+      identifier: Some("Bar"),
+      kind: Kind::Service(vec![rpc("Self"), rpc("Type")])
+    }];
+    let result = translate(input, Options::default());
+
+    assert_eq!(
+      result,
+      "#[derive(Clone, Debug)]\npub struct BarClient<T> {\n  transport: \
+       T\n}\n\nimpl<T: ::pbrs::runtime::Transport> BarClient<T> {\n  pub fn \
+       new(transport: T) -> Self {\n    Self { transport }\n  }\n\n  pub async \
+       fn self_(&self, req: &Request) -> Result<Response, \
+       ::pbrs::runtime::Status> {\n    self\n      .self_with_options(req, \
+       Default::default())\n      .await\n      .map(|v| v.message)\n  }\n\n  \
+       pub async fn self_with_options(\n    &self,\n    req: &Request,\n    \
+       options: ::pbrs::runtime::CallOptions\n  ) -> \
+       Result<::pbrs::runtime::Response<Response>, ::pbrs::runtime::Status> \
+       {\n    ::pbrs::runtime::unary(&self.transport, \"/Bar/Self\", req, \
+       options).await\n  }\n\n  pub async fn r#type(&self, req: &Request) -> \
+       Result<Response, ::pbrs::runtime::Status> {\n    self\n      \
+       .type_with_options(req, Default::default())\n      .await\n      \
+       .map(|v| v.message)\n  }\n\n  pub async fn type_with_options(\n    \
+       &self,\n    req: &Request,\n    options: \
+       ::pbrs::runtime::CallOptions\n  ) -> \
+       Result<::pbrs::runtime::Response<Response>, ::pbrs::runtime::Status> \
+       {\n    ::pbrs::runtime::unary(&self.transport, \"/Bar/Type\", req, \
+       options).await\n  }\n}\n\npub trait Bar: Send + Sync + 'static {\n  fn \
+       self_(\n    &self,\n    req: ::pbrs::runtime::Request<Request>\n  ) -> \
+       impl ::std::future::Future<Output = \
+       Result<::pbrs::runtime::Response<Response>, ::pbrs::runtime::Status>> + \
+       Send;\n\n  fn r#type(\n    &self,\n    req: \
+       ::pbrs::runtime::Request<Request>\n  ) -> impl \
+       ::std::future::Future<Output = \
+       Result<::pbrs::runtime::Response<Response>, ::pbrs::runtime::Status>> + \
+       Send;\n}\n\npub struct BarServer<S> {\n  inner: \
+       ::std::sync::Arc<S>\n}\n\nimpl<S: Bar> BarServer<S> {\n  pub fn \
+       new(inner: S) -> Self {\n    \
+       Self::from_arc(::std::sync::Arc::new(inner))\n  }\n\n  pub fn \
+       from_arc(inner: ::std::sync::Arc<S>) -> Self {\n    Self { inner }\n  \
+       }\n}\n\nimpl<S: Bar> ::pbrs::runtime::Service for BarServer<S> {\n  fn \
+       name(&self) -> &'static str {\n    \"Bar\"\n  }\n\n  fn call<'a>(\n    \
+       &'a self,\n    path: &'a str,\n    req: \
+       ::pbrs::runtime::Request<::pbrs::runtime::Streaming<Vec<u8>>>\n  ) -> \
+       ::pbrs::runtime::BoxFuture<'a, \
+       Result<::pbrs::runtime::Response<::pbrs::runtime::Streaming<Vec<u8>>>, \
+       ::pbrs::runtime::Status>> {\n    match path {\n      \"/Bar/Self\" => \
+       Box::pin(::pbrs::runtime::handle_unary(req, |req| {\n        \
+       self.inner.self_(req)\n      })),\n      \"/Bar/Type\" => \
+       Box::pin(::pbrs::runtime::handle_unary(req, |req| {\n        \
+       self.inner.r#type(req)\n      })),\n      _ => Box::pin(async move { \
+       Err(::pbrs::runtime::Status::unimplemented(path)) })\n    }\n  \
+       }\n}\n\npub struct MockBar {\n  pub self_: \
+       ::pbrs::runtime::MockMethod<Request, Response>,\n  pub r#type: \
+       ::pbrs::runtime::MockMethod<Request, Response>\n}\n\nimpl MockBar {\n  \
+       pub fn new() -> Self {\n    Self {\n      self_: \
+       ::pbrs::runtime::MockMethod::new(\"/Bar/Self\"),\n      r#type: \
+       ::pbrs::runtime::MockMethod::new(\"/Bar/Type\")\n    }\n  }\n\n  // \
+       Panic unless every method was called as expected\n  pub fn \
+       verify(&self) {\n    self.self_.verify();\n    self.r#type.verify();\n  \
+       }\n}\n\nimpl Default for MockBar {\n  fn default() -> Self {\n    \
+       Self::new()\n  }\n}\n\nimpl Bar for MockBar {\n  fn self_(\n    \
+       &self,\n    req: ::pbrs::runtime::Request<Request>\n  ) -> impl \
+       ::std::future::Future<Output = \
+       Result<::pbrs::runtime::Response<Response>, ::pbrs::runtime::Status>> + \
+       Send {\n    self.self_.call(req)\n  }\n\n  fn r#type(\n    &self,\n    \
+       req: ::pbrs::runtime::Request<Request>\n  ) -> impl \
+       ::std::future::Future<Output = \
+       Result<::pbrs::runtime::Response<Response>, ::pbrs::runtime::Status>> + \
+       Send {\n    self.r#type.call(req)\n  }\n}"
+    );
+  }
+
+  #[test]
+  fn translate_server() {
+    let fields = vec![Field::Rpc(Rpc {
+      name: "Foo",
+      params: ("Request", "Response"),
+      streaming: (false, false)
+    })];
+    let input = vec![
+      Block {
+        identifier: None,
+        kind: Kind::Package("baz")
+      },
+      Block {
+        identifier: Some("Bar"),
+        kind: Kind::Service(fields)
+      },
+    ];
+    let result = translate(input, Options::default());
+
+    assert!(result.contains(
+      "  impl<S: Bar> ::pbrs::runtime::Service for BarServer<S> {\n    fn \
+       name(&self) -> &'static str {\n      \"baz.Bar\"\n    }"
+    ));
+    assert!(result.contains(
+      "\"/baz.Bar/Foo\" => Box::pin(::pbrs::runtime::handle_unary(req, |req| \
+       {\n          self.inner.foo(req)\n        })),"
+    ));
+  }
+
+  #[test]
+  fn translate_streaming() {
+    let fields = vec![Field::Rpc(Rpc {
+      name: "Foo",
+      params: ("Request", "Response"),
+      streaming: (true, true)
+    })];
+    let input = vec![Block {
+      identifier: Some("Bar"),
+      kind: Kind::Service(fields)
+    }];
+    let result = translate(input, Options::default());
+
+    assert!(result.contains(
+      "pub async fn foo(&self, req: ::pbrs::runtime::Streaming<Request>) -> \
+       Result<::pbrs::runtime::Streaming<Response>, ::pbrs::runtime::Status>"
+    ));
+    assert!(result.contains(
+      "req: ::pbrs::runtime::Request<::pbrs::runtime::Streaming<Request>>\n  \
+       ) -> impl ::std::future::Future<Output = \
+       Result<::pbrs::runtime::Response<::pbrs::runtime::Streaming<Response>>, \
+       ::pbrs::runtime::Status>> + Send;"
+    ));
+    assert!(result.contains("::pbrs::runtime::handle_streaming(req, |req| {"));
+  }
+
+  #[test]
+  fn translate_mock() {
+    let fields = vec![Field::Rpc(Rpc {
+      name: "Foo",
+      params: ("Request", "Response"),
+      streaming: (true, false)
+    })];
+    let input = vec![Block {
+      identifier: Some("Bar"),
+      kind: Kind::Service(fields)
+    }];
+    let result = translate(input, Options::default());
+
+    assert!(result.contains(
+      "pub struct MockBar {\n  pub foo: \
+       ::pbrs::runtime::MockMethod<Vec<Request>, Response>\n}"
+    ));
+    assert!(
+      result.contains("+ Send {\n    self.foo.call_streaming(req)\n  }\n}")
+    );
+  }
+
+  #[test]
+  fn describe_file() -> Result<(), String> {
+    let fields = vec![
+      Field::Block(Block {
+        identifier: Some("Baz"),
+        kind: Kind::Message(Vec::new())
+      }),
+      Field::Property(Property {
+        r#type: Type::Message("Baz"),
+        name: "foo_baz",
+        value: 2
+      }),
+    ];
+    let rpc = Rpc {
+      name: "Get",
+      params: ("Bar", "Bar.Baz"),
+      streaming: (false, true)
+    };
+    let input = vec![
+      Block {
+        identifier: None,
+        kind: Kind::Syntax("proto3")
+      },
+      Block {
+        identifier: None,
+        kind: Kind::Package("foo")
+      },
+      Block {
+        identifier: Some("Bar"),
+        kind: Kind::Message(fields)
+      },
+      Block {
+        identifier: Some("Service"),
+        kind: Kind::Service(vec![Field::Rpc(rpc)])
+      },
+    ];
+    let result = describe("foo.proto", &input, Options::default())?;
+    let field = &result.message_type[0].field[0];
+    let method = &result.service[0].method[0];
+
+    assert_eq!(result.package, "foo");
+    assert_eq!(result.syntax, "proto3");
+    assert_eq!(result.message_type[0].nested_type[0].name, "Baz");
+    assert_eq!(field.type_name, ".foo.Bar.Baz");
+    assert_eq!(field.json_name, "fooBaz");
+    assert_eq!(method.output_type, ".foo.Bar.Baz");
+    assert!(method.server_streaming);
+    assert_eq!(
+      result.encode_to_vec()[..16],
+      *b"\x0a\x09foo.proto\x12\x03foo"
+    );
+
+    Ok(())
+  }
+
+  #[test]
+  fn translate_descriptor_set() -> Result<(), String> {
+    let mut input = create_message();
+
+    input.push(Block {
+      identifier: None,
+      kind: Kind::Package("foo.bar")
+    });
+
+    let file = describe("foo.proto", &input, Options::default())?;
+    let mut other = file.clone();
+
+    other.message_type[0].name = "Baz".to_string();
+
+    let set = FileDescriptorSet {
+      file: vec![file.clone()]
+    };
+    let result = translate_set(&set, Options::default())?;
+
+    assert_eq!(result, translate(input, Options::default()));
+
+    let set = FileDescriptorSet {
+      file: vec![file, other]
+    };
+    let result = translate_set(&set, Options::default())?;
+
+    assert_eq!(result.matches("pub mod foo").count(), 1);
+    assert!(result.contains("pub struct Baz"));
+
+    Ok(())
+  }
+
+  #[test]
+  fn translate_sibling_packages() -> Result<(), String> {
+    let file = |package| {
+      let mut input = create_message();
+
+      input.push(Block {
+        identifier: None,
+        kind: Kind::Package(package)
+      });
+
+      describe("foo.proto", &input, Options::default())
+    };
+    let set = FileDescriptorSet {
+      file: vec![file("foo.bar")?, file("foo.baz")?, file("qux")?]
+    };
+    let result = translate_set(&set, Options::default().derives(None))?;
+    let modules = result
+      .lines()
+      .filter(|v| v.trim_start().starts_with("pub mod"))
+      .collect::<Vec<&str>>();
+
+    assert_eq!(
+      modules,
+      [
+        "pub mod foo {",
+        "  pub mod bar {",
+        "  pub mod baz {",
+        "pub mod qux {"
+      ]
+    );
+    assert_eq!(result.matches("pub struct Foo").count(), 3);
+
+    Ok(())
+  }
+
   #[test]
   fn wrap_package() {
     let mut input = create_message();
@@ -209,11 +1112,680 @@ mod tests {
       kind: Kind::Package("foobar")
     });
 
-    let result = translate(input);
+    let result = translate(input, Options::default());
+
+    assert_eq!(
+      result,
+      "pub mod foobar {\n  #[derive(Clone, Debug, PartialEq, Default)]\n  pub \
+       struct Foo {\n    pub bar: i32\n  }\n\n  impl ::pbrs::runtime::Message \
+       for Foo {\n    fn encode(&self, buf: &mut Vec<u8>) {\n      \
+       ::pbrs::runtime::encoding::int32::encode(1, &self.bar, buf);\n    \
+       }\n\n    fn merge_field(\n      &mut self,\n      number: u32,\n      \
+       wire: ::pbrs::runtime::WireType,\n      buf: &mut &[u8]\n    ) -> \
+       Result<(), ::pbrs::runtime::DecodeError> {\n      match number \
+       {\n        1 => ::pbrs::runtime::encoding::int32::merge(wire, &mut \
+       self.bar, buf),\n        _ => \
+       ::pbrs::runtime::encoding::skip_field(wire, buf)\n      }\n    }\n\n    \
+       fn descriptor() -> &'static ::pbrs::runtime::MessageDescriptor {\n      \
+       static DESCRIPTOR: ::pbrs::runtime::MessageDescriptor =\n        \
+       ::pbrs::runtime::MessageDescriptor {\n          name: \
+       \"Foo\",\n          full_name: \"foobar.Foo\",\n          fields: \
+       &[\n            ::pbrs::runtime::FieldDescriptor {\n              name: \
+       \"bar\",\n              json_name: \"bar\",\n              number: \
+       1,\n              label: \
+       ::pbrs::runtime::Label::Optional,\n              r#type: \
+       ::pbrs::runtime::FieldType::Int32\n            }\n          ]\n        \
+       };\n\n      &DESCRIPTOR\n    }\n  }\n\n  impl ::pbrs::runtime::Masked \
+       for Foo {\n    fn is_path(path: &[&str]) -> bool {\n      \
+       ::pbrs::runtime::mask::is_field(path, \"bar\")\n    }\n\n    fn \
+       merge_path(&mut self, source: &mut Self, path: &[&str]) {\n      \
+       ::pbrs::runtime::mask::merge_field(&mut self.bar, &mut source.bar, \
+       path, \"bar\");\n    }\n\n    fn trim_paths(&mut self, paths: \
+       &[&[&str]]) {\n      ::pbrs::runtime::mask::trim_field(&mut self.bar, \
+       paths, \"bar\");\n    }\n  }\n\n  pub fn register_types(\n    registry: \
+       ::pbrs::runtime::TypeRegistry\n  ) -> ::pbrs::runtime::TypeRegistry \
+       {\n    registry\n      .add::<Foo>()\n  }\n}"
+    );
+  }
+
+  #[test]
+  fn derive_serde() {
+    let fields = vec![
+      Field::Block(Block {
+        identifier: Some("Baz"),
+        kind: Kind::Message(Vec::new())
+      }),
+      Field::Property(Property {
+        r#type: Type::Message("Baz"),
+        name: "baz",
+        value: 1
+      }),
+    ];
+    let input = vec![Block {
+      identifier: Some("Foo"),
+      kind: Kind::Message(fields)
+    }];
+    let result = translate(
+      input,
+      Options::default()
+        .derives(None)
+        .serde(true)
+    );
+
+    assert_eq!(
+      result,
+      "#[derive(::serde::Serialize, \
+       ::serde::Deserialize)]\n#[serde(default)]\npub struct Foo {\n  pub baz: \
+       Option<foo::Baz>\n}\n\nimpl ::pbrs::runtime::Message for Foo {\n  fn \
+       encode(&self, buf: &mut Vec<u8>) {\n    if let Some(value) = &self.baz \
+       {\n      ::pbrs::runtime::encoding::message::encode(1, value, \
+       buf);\n    }\n  }\n\n  fn merge_field(\n    &mut self,\n    number: \
+       u32,\n    wire: ::pbrs::runtime::WireType,\n    buf: &mut &[u8]\n  ) -> \
+       Result<(), ::pbrs::runtime::DecodeError> {\n    match number {\n      1 \
+       => ::pbrs::runtime::encoding::message::merge(wire, \
+       self.baz.get_or_insert_with(Default::default), buf),\n      _ => \
+       ::pbrs::runtime::encoding::skip_field(wire, buf)\n    }\n  }\n\n  fn \
+       descriptor() -> &'static ::pbrs::runtime::MessageDescriptor {\n    \
+       static DESCRIPTOR: ::pbrs::runtime::MessageDescriptor =\n      \
+       ::pbrs::runtime::MessageDescriptor {\n        name: \"Foo\",\n        \
+       full_name: \"Foo\",\n        fields: &[\n          \
+       ::pbrs::runtime::FieldDescriptor {\n            name: \
+       \"baz\",\n            json_name: \"baz\",\n            number: \
+       1,\n            label: ::pbrs::runtime::Label::Optional,\n            \
+       r#type: \
+       ::pbrs::runtime::FieldType::Message(::pbrs::runtime::MessageType::Static(<foo::Baz \
+       as ::pbrs::runtime::Message>::descriptor))\n          }\n        \
+       ]\n      };\n\n    &DESCRIPTOR\n  }\n}\n\nimpl ::pbrs::runtime::Masked \
+       for Foo {\n  fn is_path(path: &[&str]) -> bool {\n    \
+       ::pbrs::runtime::mask::is_message_field::<foo::Baz>(path, \"baz\")\n  \
+       }\n\n  fn merge_path(&mut self, source: &mut Self, path: &[&str]) \
+       {\n    ::pbrs::runtime::mask::merge_message_field(&mut self.baz, &mut \
+       source.baz, path, \"baz\");\n  }\n\n  fn trim_paths(&mut self, paths: \
+       &[&[&str]]) {\n    ::pbrs::runtime::mask::trim_message_field(&mut \
+       self.baz, paths, \"baz\");\n  }\n}\n\npub mod foo {\n  \
+       #[derive(::serde::Serialize, ::serde::Deserialize)]\n  \
+       #[serde(default)]\n  pub struct Baz {\n\n  }\n\n  impl \
+       ::pbrs::runtime::Message for Baz {\n    fn encode(&self, buf: &mut \
+       Vec<u8>) {\n      let _ = buf;\n    }\n\n    fn merge_field(\n      \
+       &mut self,\n      number: u32,\n      wire: \
+       ::pbrs::runtime::WireType,\n      buf: &mut &[u8]\n    ) -> Result<(), \
+       ::pbrs::runtime::DecodeError> {\n      match number {\n        _ => \
+       ::pbrs::runtime::encoding::skip_field(wire, buf)\n      }\n    }\n\n    \
+       fn descriptor() -> &'static ::pbrs::runtime::MessageDescriptor {\n      \
+       static DESCRIPTOR: ::pbrs::runtime::MessageDescriptor =\n        \
+       ::pbrs::runtime::MessageDescriptor {\n          name: \
+       \"Baz\",\n          full_name: \"Foo.Baz\",\n          fields: \
+       &[]\n        };\n\n      &DESCRIPTOR\n    }\n  }\n\n  impl \
+       ::pbrs::runtime::Masked for Baz {\n    fn is_path(path: &[&str]) -> \
+       bool {\n      let _ = path;\n\n      false\n    }\n\n    fn \
+       merge_path(&mut self, source: &mut Self, path: &[&str]) {\n      let _ \
+       = (source, path);\n    }\n\n    fn trim_paths(&mut self, paths: \
+       &[&[&str]]) {\n      let _ = paths;\n    }\n  }\n}\n\npub fn \
+       register_types(\n  registry: ::pbrs::runtime::TypeRegistry\n) -> \
+       ::pbrs::runtime::TypeRegistry {\n  registry\n    .add::<Foo>()\n    \
+       .add::<foo::Baz>()\n}"
+    );
+  }
+
+  #[test]
+  fn scoped_options() -> Result<(), String> {
+    let fields = vec![Field::Block(Block {
+      identifier: Some("Baz"),
+      kind: Kind::Message(Vec::new())
+    })];
+    let input = vec![Block {
+      identifier: Some("Foo"),
+      kind: Kind::Message(fields)
+    }];
+    let options = Options::default()
+      .derives(None)
+      .override_option(".Foo.Baz", "derives=Clone")?;
+    let result = translate(input, options);
+
+    assert!(result.starts_with("pub struct Foo {"));
+    assert!(result.contains("  #[derive(Clone)]\n  pub struct Baz {"));
+
+    Ok(())
+  }
+
+  #[test]
+  fn extern_paths() {
+    let fields = vec![Field::Property(Property {
+      r#type: Type::Message("common.Money"),
+      name: "price",
+      value: 1
+    })];
+    let input = vec![
+      Block {
+        identifier: None,
+        kind: Kind::Package("shop")
+      },
+      Block {
+        identifier: Some("Item"),
+        kind: Kind::Message(fields)
+      },
+    ];
+    let common = vec![
+      Block {
+        identifier: None,
+        kind: Kind::Package("common")
+      },
+      Block {
+        identifier: Some("Money"),
+        kind: Kind::Message(Vec::new())
+      },
+    ];
+    let options = Options::default().extern_path(".common", "::common_types");
+
+    assert!(translate(input, options.clone())
+      .contains("pub price: Option<::common_types::Money>"));
+    assert_eq!(translate(common, options), "");
+  }
+
+  #[test]
+  fn time_types() -> Result<(), String> {
+    let fields = vec![
+      Field::Property(Property {
+        r#type: Type::Message("google.protobuf.Timestamp"),
+        name: "at",
+        value: 1
+      }),
+      Field::Property(Property {
+        r#type: Type::Message(".google.protobuf.Duration"),
+        name: "took",
+        value: 2
+      }),
+    ];
+    let input = vec![Block {
+      identifier: Some("Event"),
+      kind: Kind::Message(fields)
+    }];
+    let result = translate(
+      input.clone(),
+      Options::default().option("time-types=chrono")?
+    );
+
+    assert!(
+      result.contains("pub at: Option<::chrono::DateTime<::chrono::Utc>>")
+    );
+    assert!(result.contains("pub took: Option<::std::time::Duration>"));
+    assert!(result.contains(
+      "2 => ::pbrs::runtime::encoding::converted::merge::<\
+       ::pbrs::runtime::well_known::Duration, _>(wire, &mut self.took, buf)"
+    ));
+    assert!(translate(input, Options::default())
+      .contains("pub at: Option<::pbrs::runtime::well_known::Timestamp>"));
+
+    Ok(())
+  }
+
+  #[test]
+  fn custom_attributes() {
+    let mut input = create_message();
+
+    input.push(Block {
+      identifier: None,
+      kind: Kind::Package("foobar")
+    });
+
+    let options = Options::default()
+      .derives(Some("Clone"))
+      .type_attribute(".foobar.Foo", "#[derive(Hash)]")
+      .type_attribute(".other", "#[derive(Eq)]")
+      .field_attribute("Foo.bar", "#[doc(hidden)]");
+    let result = translate(input, options);
+
+    assert_eq!(
+      result,
+      "pub mod foobar {\n  #[derive(Clone)]\n  #[derive(Hash)]\n  pub struct \
+       Foo {\n    #[doc(hidden)]\n    pub bar: i32\n  }\n\n  impl \
+       ::pbrs::runtime::Message for Foo {\n    fn encode(&self, buf: &mut \
+       Vec<u8>) {\n      ::pbrs::runtime::encoding::int32::encode(1, \
+       &self.bar, buf);\n    }\n\n    fn merge_field(\n      &mut self,\n      \
+       number: u32,\n      wire: ::pbrs::runtime::WireType,\n      buf: &mut \
+       &[u8]\n    ) -> Result<(), ::pbrs::runtime::DecodeError> {\n      match \
+       number {\n        1 => ::pbrs::runtime::encoding::int32::merge(wire, \
+       &mut self.bar, buf),\n        _ => \
+       ::pbrs::runtime::encoding::skip_field(wire, buf)\n      }\n    }\n\n    \
+       fn descriptor() -> &'static ::pbrs::runtime::MessageDescriptor {\n      \
+       static DESCRIPTOR: ::pbrs::runtime::MessageDescriptor =\n        \
+       ::pbrs::runtime::MessageDescriptor {\n          name: \
+       \"Foo\",\n          full_name: \"foobar.Foo\",\n          fields: \
+       &[\n            ::pbrs::runtime::FieldDescriptor {\n              name: \
+       \"bar\",\n              json_name: \"bar\",\n              number: \
+       1,\n              label: \
+       ::pbrs::runtime::Label::Optional,\n              r#type: \
+       ::pbrs::runtime::FieldType::Int32\n            }\n          ]\n        \
+       };\n\n      &DESCRIPTOR\n    }\n  }\n\n  impl ::pbrs::runtime::Masked \
+       for Foo {\n    fn is_path(path: &[&str]) -> bool {\n      \
+       ::pbrs::runtime::mask::is_field(path, \"bar\")\n    }\n\n    fn \
+       merge_path(&mut self, source: &mut Self, path: &[&str]) {\n      \
+       ::pbrs::runtime::mask::merge_field(&mut self.bar, &mut source.bar, \
+       path, \"bar\");\n    }\n\n    fn trim_paths(&mut self, paths: \
+       &[&[&str]]) {\n      ::pbrs::runtime::mask::trim_field(&mut self.bar, \
+       paths, \"bar\");\n    }\n  }\n\n  pub fn register_types(\n    registry: \
+       ::pbrs::runtime::TypeRegistry\n  ) -> ::pbrs::runtime::TypeRegistry \
+       {\n    registry\n      .add::<Foo>()\n  }\n}"
+    );
+  }
+
+  #[test]
+  fn nested_packages() {
+    let fields = vec![
+      Field::Property(Property {
+        r#type: Type::Message("Bar"),
+        name: "bar",
+        value: 1
+      }),
+      Field::Property(Property {
+        r#type: Type::Message("foo.baz.Baz"),
+        name: "baz",
+        value: 2
+      }),
+      Field::Property(Property {
+        r#type: Type::Message(".Qux"),
+        name: "qux",
+        value: 3
+      }),
+    ];
+    let input = vec![
+      Block {
+        identifier: None,
+        kind: Kind::Package("foo.bar.v1")
+      },
+      Block {
+        identifier: Some("Foo"),
+        kind: Kind::Message(fields)
+      },
+    ];
+    let result = translate(input, Options::default().derives(None));
+
+    assert_eq!(
+      result,
+      "pub mod foo {\n  pub mod bar {\n    pub mod v1 {\n      pub struct Foo \
+       {\n        pub bar: Option<Bar>,\n        pub baz: \
+       Option<super::super::baz::Baz>,\n        pub qux: \
+       Option<super::super::super::Qux>\n      }\n\n      impl \
+       ::pbrs::runtime::Message for Foo {\n        fn encode(&self, buf: &mut \
+       Vec<u8>) {\n          if let Some(value) = &self.bar {\n            \
+       ::pbrs::runtime::encoding::message::encode(1, value, buf);\n          \
+       }\n          if let Some(value) = &self.baz {\n            \
+       ::pbrs::runtime::encoding::message::encode(2, value, buf);\n          \
+       }\n          if let Some(value) = &self.qux {\n            \
+       ::pbrs::runtime::encoding::message::encode(3, value, buf);\n          \
+       }\n        }\n\n        fn merge_field(\n          &mut \
+       self,\n          number: u32,\n          wire: \
+       ::pbrs::runtime::WireType,\n          buf: &mut &[u8]\n        ) -> \
+       Result<(), ::pbrs::runtime::DecodeError> {\n          match number \
+       {\n            1 => ::pbrs::runtime::encoding::message::merge(wire, \
+       self.bar.get_or_insert_with(Default::default), buf),\n            2 => \
+       ::pbrs::runtime::encoding::message::merge(wire, \
+       self.baz.get_or_insert_with(Default::default), buf),\n            3 => \
+       ::pbrs::runtime::encoding::message::merge(wire, \
+       self.qux.get_or_insert_with(Default::default), buf),\n            _ => \
+       ::pbrs::runtime::encoding::skip_field(wire, buf)\n          }\n        \
+       }\n\n        fn descriptor() -> &'static \
+       ::pbrs::runtime::MessageDescriptor {\n          static DESCRIPTOR: \
+       ::pbrs::runtime::MessageDescriptor =\n            \
+       ::pbrs::runtime::MessageDescriptor {\n              name: \
+       \"Foo\",\n              full_name: \"foo.bar.v1.Foo\",\n              \
+       fields: &[\n                ::pbrs::runtime::FieldDescriptor \
+       {\n                  name: \"bar\",\n                  json_name: \
+       \"bar\",\n                  number: 1,\n                  label: \
+       ::pbrs::runtime::Label::Optional,\n                  r#type: \
+       ::pbrs::runtime::FieldType::Message(::pbrs::runtime::MessageType::Static(<Bar \
+       as ::pbrs::runtime::Message>::descriptor))\n                \
+       },\n                ::pbrs::runtime::FieldDescriptor \
+       {\n                  name: \"baz\",\n                  json_name: \
+       \"baz\",\n                  number: 2,\n                  label: \
+       ::pbrs::runtime::Label::Optional,\n                  r#type: \
+       ::pbrs::runtime::FieldType::Message(::pbrs::runtime::MessageType::Static(<super::super::baz::Baz \
+       as ::pbrs::runtime::Message>::descriptor))\n                \
+       },\n                ::pbrs::runtime::FieldDescriptor \
+       {\n                  name: \"qux\",\n                  json_name: \
+       \"qux\",\n                  number: 3,\n                  label: \
+       ::pbrs::runtime::Label::Optional,\n                  r#type: \
+       ::pbrs::runtime::FieldType::Message(::pbrs::runtime::MessageType::Static(<super::super::super::Qux \
+       as ::pbrs::runtime::Message>::descriptor))\n                \
+       }\n              ]\n            };\n\n          &DESCRIPTOR\n        \
+       }\n      }\n\n      impl ::pbrs::runtime::Masked for Foo {\n        fn \
+       is_path(path: &[&str]) -> bool {\n          \
+       ::pbrs::runtime::mask::is_field(path, \"bar\")\n            || \
+       ::pbrs::runtime::mask::is_field(path, \"baz\")\n            || \
+       ::pbrs::runtime::mask::is_field(path, \"qux\")\n        }\n\n        fn \
+       merge_path(&mut self, source: &mut Self, path: &[&str]) {\n          \
+       ::pbrs::runtime::mask::merge_field(&mut self.bar, &mut source.bar, \
+       path, \"bar\");\n          ::pbrs::runtime::mask::merge_field(&mut \
+       self.baz, &mut source.baz, path, \"baz\");\n          \
+       ::pbrs::runtime::mask::merge_field(&mut self.qux, &mut source.qux, \
+       path, \"qux\");\n        }\n\n        fn trim_paths(&mut self, paths: \
+       &[&[&str]]) {\n          ::pbrs::runtime::mask::trim_field(&mut \
+       self.bar, paths, \"bar\");\n          \
+       ::pbrs::runtime::mask::trim_field(&mut self.baz, paths, \
+       \"baz\");\n          ::pbrs::runtime::mask::trim_field(&mut self.qux, \
+       paths, \"qux\");\n        }\n      }\n\n      pub fn \
+       register_types(\n        registry: ::pbrs::runtime::TypeRegistry\n      \
+       ) -> ::pbrs::runtime::TypeRegistry {\n        registry\n          \
+       .add::<Foo>()\n      }\n    }\n  }\n}"
+    );
+  }
+
+  #[test]
+  fn nested_messages() {
+    let nested = |fields| {
+      Field::Block(Block {
+        identifier: Some("Foo"),
+        kind: Kind::Message(fields)
+      })
+    };
+    let property = |r#type, name, value| {
+      Field::Property(Property {
+        r#type: Type::Message(r#type),
+        name,
+        value
+      })
+    };
+    let input = vec![
+      Block {
+        identifier: Some("A"),
+        kind: Kind::Message(vec![property("Foo", "one", 1)])
+      },
+      Block {
+        identifier: Some("B"),
+        kind: Kind::Message(vec![
+          nested(vec![property("A", "a", 1), property("B", "b", 2)]),
+          property("Foo", "foo", 1),
+        ])
+      },
+    ];
+    let result = translate(input, Options::default().derives(None));
+
+    assert_eq!(
+      result,
+      "pub struct A {\n  pub one: Option<Foo>\n}\n\nimpl \
+       ::pbrs::runtime::Message for A {\n  fn encode(&self, buf: &mut Vec<u8>) \
+       {\n    if let Some(value) = &self.one {\n      \
+       ::pbrs::runtime::encoding::message::encode(1, value, buf);\n    }\n  \
+       }\n\n  fn merge_field(\n    &mut self,\n    number: u32,\n    wire: \
+       ::pbrs::runtime::WireType,\n    buf: &mut &[u8]\n  ) -> Result<(), \
+       ::pbrs::runtime::DecodeError> {\n    match number {\n      1 => \
+       ::pbrs::runtime::encoding::message::merge(wire, \
+       self.one.get_or_insert_with(Default::default), buf),\n      _ => \
+       ::pbrs::runtime::encoding::skip_field(wire, buf)\n    }\n  }\n\n  fn \
+       descriptor() -> &'static ::pbrs::runtime::MessageDescriptor {\n    \
+       static DESCRIPTOR: ::pbrs::runtime::MessageDescriptor =\n      \
+       ::pbrs::runtime::MessageDescriptor {\n        name: \"A\",\n        \
+       full_name: \"A\",\n        fields: &[\n          \
+       ::pbrs::runtime::FieldDescriptor {\n            name: \
+       \"one\",\n            json_name: \"one\",\n            number: \
+       1,\n            label: ::pbrs::runtime::Label::Optional,\n            \
+       r#type: \
+       ::pbrs::runtime::FieldType::Message(::pbrs::runtime::MessageType::Static(<Foo \
+       as ::pbrs::runtime::Message>::descriptor))\n          }\n        \
+       ]\n      };\n\n    &DESCRIPTOR\n  }\n}\n\nimpl ::pbrs::runtime::Masked \
+       for A {\n  fn is_path(path: &[&str]) -> bool {\n    \
+       ::pbrs::runtime::mask::is_field(path, \"one\")\n  }\n\n  fn \
+       merge_path(&mut self, source: &mut Self, path: &[&str]) {\n    \
+       ::pbrs::runtime::mask::merge_field(&mut self.one, &mut source.one, \
+       path, \"one\");\n  }\n\n  fn trim_paths(&mut self, paths: &[&[&str]]) \
+       {\n    ::pbrs::runtime::mask::trim_field(&mut self.one, paths, \
+       \"one\");\n  }\n}\n\npub struct B {\n  pub foo: \
+       Option<Box<b::Foo>>\n}\n\nimpl ::pbrs::runtime::Message for B {\n  fn \
+       encode(&self, buf: &mut Vec<u8>) {\n    if let Some(value) = &self.foo \
+       {\n      ::pbrs::runtime::encoding::message::encode(1, value, \
+       buf);\n    }\n  }\n\n  fn merge_field(\n    &mut self,\n    number: \
+       u32,\n    wire: ::pbrs::runtime::WireType,\n    buf: &mut &[u8]\n  ) -> \
+       Result<(), ::pbrs::runtime::DecodeError> {\n    match number {\n      1 \
+       => ::pbrs::runtime::encoding::message::merge(wire, \
+       self.foo.get_or_insert_with(Default::default), buf),\n      _ => \
+       ::pbrs::runtime::encoding::skip_field(wire, buf)\n    }\n  }\n\n  fn \
+       descriptor() -> &'static ::pbrs::runtime::MessageDescriptor {\n    \
+       static DESCRIPTOR: ::pbrs::runtime::MessageDescriptor =\n      \
+       ::pbrs::runtime::MessageDescriptor {\n        name: \"B\",\n        \
+       full_name: \"B\",\n        fields: &[\n          \
+       ::pbrs::runtime::FieldDescriptor {\n            name: \
+       \"foo\",\n            json_name: \"foo\",\n            number: \
+       1,\n            label: ::pbrs::runtime::Label::Optional,\n            \
+       r#type: \
+       ::pbrs::runtime::FieldType::Message(::pbrs::runtime::MessageType::Static(<b::Foo \
+       as ::pbrs::runtime::Message>::descriptor))\n          }\n        \
+       ]\n      };\n\n    &DESCRIPTOR\n  }\n}\n\nimpl ::pbrs::runtime::Masked \
+       for B {\n  fn is_path(path: &[&str]) -> bool {\n    \
+       ::pbrs::runtime::mask::is_message_field::<b::Foo>(path, \"foo\")\n  \
+       }\n\n  fn merge_path(&mut self, source: &mut Self, path: &[&str]) \
+       {\n    ::pbrs::runtime::mask::merge_message_field(&mut self.foo, &mut \
+       source.foo, path, \"foo\");\n  }\n\n  fn trim_paths(&mut self, paths: \
+       &[&[&str]]) {\n    ::pbrs::runtime::mask::trim_message_field(&mut \
+       self.foo, paths, \"foo\");\n  }\n}\n\npub mod b {\n  pub struct Foo \
+       {\n    pub a: Option<super::A>,\n    pub b: Option<Box<super::B>>\n  \
+       }\n\n  impl ::pbrs::runtime::Message for Foo {\n    fn encode(&self, \
+       buf: &mut Vec<u8>) {\n      if let Some(value) = &self.a {\n        \
+       ::pbrs::runtime::encoding::message::encode(1, value, buf);\n      \
+       }\n      if let Some(value) = &self.b {\n        \
+       ::pbrs::runtime::encoding::message::encode(2, value, buf);\n      \
+       }\n    }\n\n    fn merge_field(\n      &mut self,\n      number: \
+       u32,\n      wire: ::pbrs::runtime::WireType,\n      buf: &mut \
+       &[u8]\n    ) -> Result<(), ::pbrs::runtime::DecodeError> {\n      match \
+       number {\n        1 => ::pbrs::runtime::encoding::message::merge(wire, \
+       self.a.get_or_insert_with(Default::default), buf),\n        2 => \
+       ::pbrs::runtime::encoding::message::merge(wire, \
+       self.b.get_or_insert_with(Default::default), buf),\n        _ => \
+       ::pbrs::runtime::encoding::skip_field(wire, buf)\n      }\n    }\n\n    \
+       fn descriptor() -> &'static ::pbrs::runtime::MessageDescriptor {\n      \
+       static DESCRIPTOR: ::pbrs::runtime::MessageDescriptor =\n        \
+       ::pbrs::runtime::MessageDescriptor {\n          name: \
+       \"Foo\",\n          full_name: \"B.Foo\",\n          fields: \
+       &[\n            ::pbrs::runtime::FieldDescriptor {\n              name: \
+       \"a\",\n              json_name: \"a\",\n              number: \
+       1,\n              label: \
+       ::pbrs::runtime::Label::Optional,\n              r#type: \
+       ::pbrs::runtime::FieldType::Message(::pbrs::runtime::MessageType::Static(<super::A \
+       as ::pbrs::runtime::Message>::descriptor))\n            },\n            \
+       ::pbrs::runtime::FieldDescriptor {\n              name: \
+       \"b\",\n              json_name: \"b\",\n              number: \
+       2,\n              label: \
+       ::pbrs::runtime::Label::Optional,\n              r#type: \
+       ::pbrs::runtime::FieldType::Message(::pbrs::runtime::MessageType::Static(<super::B \
+       as ::pbrs::runtime::Message>::descriptor))\n            }\n          \
+       ]\n        };\n\n      &DESCRIPTOR\n    }\n  }\n\n  impl \
+       ::pbrs::runtime::Masked for Foo {\n    fn is_path(path: &[&str]) -> \
+       bool {\n      ::pbrs::runtime::mask::is_message_field::<super::A>(path, \
+       \"a\")\n        || \
+       ::pbrs::runtime::mask::is_message_field::<super::B>(path, \"b\")\n    \
+       }\n\n    fn merge_path(&mut self, source: &mut Self, path: &[&str]) \
+       {\n      ::pbrs::runtime::mask::merge_message_field(&mut self.a, &mut \
+       source.a, path, \"a\");\n      \
+       ::pbrs::runtime::mask::merge_message_field(&mut self.b, &mut source.b, \
+       path, \"b\");\n    }\n\n    fn trim_paths(&mut self, paths: &[&[&str]]) \
+       {\n      ::pbrs::runtime::mask::trim_message_field(&mut self.a, paths, \
+       \"a\");\n      ::pbrs::runtime::mask::trim_message_field(&mut self.b, \
+       paths, \"b\");\n    }\n  }\n}\n\npub fn register_types(\n  registry: \
+       ::pbrs::runtime::TypeRegistry\n) -> ::pbrs::runtime::TypeRegistry {\n  \
+       registry\n    .add::<A>()\n    .add::<B>()\n    .add::<b::Foo>()\n}"
+    );
+  }
+
+  #[test]
+  fn box_messages() {
+    let property = |r#type, name| {
+      Field::Property(Property {
+        r#type: Type::Message(r#type),
+        name,
+        value: 1
+      })
+    };
+    let input = vec![
+      Block {
+        identifier: Some("Leaf"),
+        kind: Kind::Message(Vec::new())
+      },
+      Block {
+        identifier: Some("Node"),
+        kind: Kind::Message(vec![
+          property("Node", "child"),
+          property("Leaf", "leaf"),
+        ])
+      },
+    ];
+    let options = Options::default().derives(None);
+    let result = translate(input.clone(), options.clone());
+
+    assert!(result.contains("pub child: Option<Box<Node>>"));
+    assert!(result.contains("pub leaf: Option<Leaf>"));
+
+    let result = translate(input, options.box_messages(true));
+
+    assert!(result.contains("pub leaf: Option<Box<Leaf>>"));
+  }
+
+  #[test]
+  fn mask_fields() {
+    let property = |r#type, name| {
+      Field::Property(Property {
+        r#type,
+        name,
+        value: 1
+      })
+    };
+    let input = vec![
+      Block {
+        identifier: Some("Leaf"),
+        kind: Kind::Message(Vec::new())
+      },
+      Block {
+        identifier: Some("Node"),
+        kind: Kind::Message(vec![
+          property(Type::Message("Leaf"), "leaf"),
+          property(Type::Message("google.protobuf.Timestamp"), "at"),
+          property(Type::Scalar(Scalar::r#String), "type"),
+          property(Type::Message("Node"), "next"),
+        ])
+      },
+    ];
+    let result = translate(input.clone(), Options::default().derives(None));
+
+    assert!(result.contains(
+      "  fn is_path(path: &[&str]) -> bool {\n    let _ = path;\n\n    \
+       false\n  }"
+    ));
+    assert!(result.contains(
+      "::pbrs::runtime::mask::is_message_field::<Leaf>(path, \"leaf\")\n      \
+       || ::pbrs::runtime::mask::is_field(path, \"at\")\n      || \
+       ::pbrs::runtime::mask::is_field(path, \"type\")"
+    ));
+    assert!(result.contains(
+      "::pbrs::runtime::mask::merge_message_field(&mut self.leaf, &mut \
+       source.leaf, path, \"leaf\");"
+    ));
+    assert!(result.contains(
+      "::pbrs::runtime::mask::trim_field(&mut self.r#type, paths, \"type\");"
+    ));
+
+    // Boxed fields are masked as the message they hold, and time types as a
+    // single field
+    let options = Options::default()
+      .derives(None)
+      .time_types(Some(TimeTypes::Chrono));
+    let result = translate(input, options);
+
+    assert!(result.contains("pub next: Option<Box<Node>>"));
+    assert!(result.contains(
+      "::pbrs::runtime::mask::is_message_field::<Leaf>(path, \"leaf\")\n      \
+       || ::pbrs::runtime::mask::is_field(path, \"at\")\n      || \
+       ::pbrs::runtime::mask::is_field(path, \"type\")\n      || \
+       ::pbrs::runtime::mask::is_message_field::<Node>(path, \"next\")"
+    ));
+    assert!(result.contains(
+      "::pbrs::runtime::mask::merge_field(&mut self.at, &mut source.at, path, \
+       \"at\");"
+    ));
+    assert!(result.contains(
+      "::pbrs::runtime::mask::merge_message_field(&mut self.next, &mut \
+       source.next, path, \"next\");"
+    ));
+    assert!(result.contains(
+      "::pbrs::runtime::mask::trim_message_field(&mut self.next, paths, \
+       \"next\");"
+    ));
+  }
+
+  #[test]
+  fn sanitise_names() {
+    let property = |name, value| {
+      Field::Property(Property {
+        r#type: Type::Scalar(Scalar::Bool),
+        name,
+        value
+      })
+    };
+    let input = vec![Block {
+      identifier: Some("match_result"),
+      kind: Kind::Message(vec![
+        property("type", 1),
+        property("self", 2),
+        property("someValue", 3),
+      ])
+    }];
+    let options = Options::default()
+      .derives(None)
+      .serde(true);
+    let result = translate(input, options);
 
     assert_eq!(
       result,
-      "pub mod foobar {\n  pub struct Foo {\n    pub bar: i32\n  }\n}"
+      "#[derive(::serde::Serialize, \
+       ::serde::Deserialize)]\n#[serde(default)]\npub struct MatchResult {\n  \
+       pub r#type: bool,\n  #[serde(rename = \"self\")]\n  pub self_: bool,\n  \
+       #[serde(rename = \"someValue\")]\n  pub some_value: bool\n}\n\nimpl \
+       ::pbrs::runtime::Message for MatchResult {\n  fn encode(&self, buf: \
+       &mut Vec<u8>) {\n    ::pbrs::runtime::encoding::bool::encode(1, \
+       &self.r#type, buf);\n    ::pbrs::runtime::encoding::bool::encode(2, \
+       &self.self_, buf);\n    ::pbrs::runtime::encoding::bool::encode(3, \
+       &self.some_value, buf);\n  }\n\n  fn merge_field(\n    &mut self,\n    \
+       number: u32,\n    wire: ::pbrs::runtime::WireType,\n    buf: &mut \
+       &[u8]\n  ) -> Result<(), ::pbrs::runtime::DecodeError> {\n    match \
+       number {\n      1 => ::pbrs::runtime::encoding::bool::merge(wire, &mut \
+       self.r#type, buf),\n      2 => \
+       ::pbrs::runtime::encoding::bool::merge(wire, &mut self.self_, \
+       buf),\n      3 => ::pbrs::runtime::encoding::bool::merge(wire, &mut \
+       self.some_value, buf),\n      _ => \
+       ::pbrs::runtime::encoding::skip_field(wire, buf)\n    }\n  }\n\n  fn \
+       descriptor() -> &'static ::pbrs::runtime::MessageDescriptor {\n    \
+       static DESCRIPTOR: ::pbrs::runtime::MessageDescriptor =\n      \
+       ::pbrs::runtime::MessageDescriptor {\n        name: \
+       \"match_result\",\n        full_name: \"match_result\",\n        \
+       fields: &[\n          ::pbrs::runtime::FieldDescriptor {\n            \
+       name: \"type\",\n            json_name: \"type\",\n            number: \
+       1,\n            label: ::pbrs::runtime::Label::Optional,\n            \
+       r#type: ::pbrs::runtime::FieldType::Bool\n          },\n          \
+       ::pbrs::runtime::FieldDescriptor {\n            name: \
+       \"self\",\n            json_name: \"self\",\n            number: \
+       2,\n            label: ::pbrs::runtime::Label::Optional,\n            \
+       r#type: ::pbrs::runtime::FieldType::Bool\n          },\n          \
+       ::pbrs::runtime::FieldDescriptor {\n            name: \
+       \"someValue\",\n            json_name: \"someValue\",\n            \
+       number: 3,\n            label: \
+       ::pbrs::runtime::Label::Optional,\n            r#type: \
+       ::pbrs::runtime::FieldType::Bool\n          }\n        ]\n      \
+       };\n\n    &DESCRIPTOR\n  }\n}\n\nimpl ::pbrs::runtime::Masked for \
+       MatchResult {\n  fn is_path(path: &[&str]) -> bool {\n    \
+       ::pbrs::runtime::mask::is_field(path, \"type\")\n      || \
+       ::pbrs::runtime::mask::is_field(path, \"self\")\n      || \
+       ::pbrs::runtime::mask::is_field(path, \"someValue\")\n  }\n\n  fn \
+       merge_path(&mut self, source: &mut Self, path: &[&str]) {\n    \
+       ::pbrs::runtime::mask::merge_field(&mut self.r#type, &mut \
+       source.r#type, path, \"type\");\n    \
+       ::pbrs::runtime::mask::merge_field(&mut self.self_, &mut source.self_, \
+       path, \"self\");\n    ::pbrs::runtime::mask::merge_field(&mut \
+       self.some_value, &mut source.some_value, path, \"someValue\");\n  \
+       }\n\n  fn trim_paths(&mut self, paths: &[&[&str]]) {\n    \
+       ::pbrs::runtime::mask::trim_field(&mut self.r#type, paths, \
+       \"type\");\n    ::pbrs::runtime::mask::trim_field(&mut self.self_, \
+       paths, \"self\");\n    ::pbrs::runtime::mask::trim_field(&mut \
+       self.some_value, paths, \"someValue\");\n  }\n}\n\npub fn \
+       register_types(\n  registry: ::pbrs::runtime::TypeRegistry\n) -> \
+       ::pbrs::runtime::TypeRegistry {\n  registry\n    \
+       .add::<MatchResult>()\n}"
     );
   }
 }
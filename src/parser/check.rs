@@ -0,0 +1,129 @@
+use super::Parser;
+use crate::lexer::{Block, Field, Kind, Type};
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+
+// Field numbers are limited to 29 bits, less those Protobuf keeps for itself
+const MAX_NUMBER: i32 = (1 << 29) - 1;
+const RESERVED_NUMBERS: RangeInclusive<i32> = 19000..=19999;
+
+impl<'a> Parser<'a> {
+  // Whether a type is declared in the file or is generated elsewhere
+  fn resolves(&self, scope: &str, name: &str) -> bool {
+    self.lookup_in(scope, name).is_some()
+      || self
+        .lookup_extern_in(scope, name)
+        .is_some()
+  }
+
+  // Problems within messages and services that protoc would refuse to
+  // compile, each prefixed with the name of the element it was found in
+  pub(super) fn check_blocks(
+    &self,
+    scope: &str,
+    blocks: &[Block<'a>]
+  ) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    for block in blocks {
+      let (Kind::Message(fields) | Kind::Service(fields), Some(id)) =
+        (&block.kind, block.identifier)
+      else {
+        continue;
+      };
+      let name = format!("{}.{}", scope, id);
+      let mut numbers = HashMap::new();
+      let label = |element: &str, problem: String| {
+        format!("{}.{}: {}", name.trim_start_matches('.'), element, problem)
+      };
+
+      for field in fields {
+        match field {
+          Field::Block(block) => {
+            let nested = std::slice::from_ref(block);
+
+            problems.extend(self.check_blocks(&name, nested));
+          }
+          Field::Property(prop) => {
+            if let Type::Message(r#type) = prop.r#type {
+              if !self.resolves(&name, r#type) {
+                problems
+                  .push(label(prop.name, format!("Unknown type {}", r#type)));
+              }
+            }
+
+            if !(1..=MAX_NUMBER).contains(&prop.value) {
+              problems.push(label(
+                prop.name,
+                format!("Invalid field number {}", prop.value)
+              ));
+            } else if RESERVED_NUMBERS.contains(&prop.value) {
+              problems.push(label(
+                prop.name,
+                format!("Reserved field number {}", prop.value)
+              ));
+            }
+
+            if let Some(other) = numbers.insert(prop.value, prop.name) {
+              problems.push(label(
+                prop.name,
+                format!(
+                  "Field number {} is also used by {}",
+                  prop.value, other
+                )
+              ));
+            }
+          }
+          Field::Rpc(rpc) => {
+            for param in [rpc.params.0, rpc.params.1] {
+              if !self.resolves(&name, param) {
+                problems
+                  .push(label(rpc.name, format!("Unknown type {}", param)));
+              }
+            }
+          }
+        }
+      }
+    }
+
+    problems
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::parser::Options;
+  use crate::{lexer, parser, tokeniser};
+
+  fn check(input: &str) -> Vec<String> {
+    let tokens = tokeniser::translate(input).unwrap_or_default();
+    let options = Options::default().extern_path(".common", "::common");
+
+    parser::check(&lexer::translate(&tokens).unwrap_or_default(), options)
+  }
+
+  #[test]
+  fn accept_valid_files() {
+    let input = "package pkg; message A { message B { A a = 1; } B b = 1; \
+                 common.Money c = 2; } service S { rpc Get(A) returns (stream \
+                 A.B); }";
+
+    assert_eq!(check(input), Vec::<String>::new());
+  }
+
+  #[test]
+  fn report_problems() {
+    let input = "package pkg; message A { Missing a = 1; int32 b = 1; int32 c \
+                 = 19000; } service S { rpc Get(A) returns (B); }";
+
+    assert_eq!(
+      check(input),
+      vec![
+        "pkg.A.a: Unknown type Missing",
+        "pkg.A.b: Field number 1 is also used by a",
+        "pkg.A.c: Reserved field number 19000",
+        "pkg.S.Get: Unknown type B"
+      ]
+    );
+  }
+}
@@ -0,0 +1,224 @@
+use super::{scoped_names, Parser};
+use crate::lexer::{Block, Field, Kind, Property, Rpc, Scalar, Type};
+use crate::runtime::descriptor::{
+  DescriptorProto,
+  FieldDescriptorProto,
+  FileDescriptorProto,
+  MethodDescriptorProto,
+  ServiceDescriptorProto
+};
+
+// Name of a field in JSON, which protoc derives by dropping each underscore
+// and capitalising the letter after it
+pub(super) fn json_name(name: &str) -> String {
+  let mut result = String::new();
+  let mut upper = false;
+
+  for c in name.chars() {
+    match (c, upper) {
+      ('_', _) => upper = true,
+      (c, true) => {
+        result.push(c.to_ascii_uppercase());
+        upper = false;
+      }
+      (c, false) => result.push(c)
+    }
+  }
+
+  result
+}
+
+fn scalar_type(scalar: &Scalar) -> i32 {
+  match scalar {
+    Scalar::Int32 => FieldDescriptorProto::TYPE_INT32,
+    Scalar::Bool => FieldDescriptorProto::TYPE_BOOL,
+    Scalar::r#String => FieldDescriptorProto::TYPE_STRING
+  }
+}
+
+impl<'a> Parser<'a> {
+  // Describe a file the way protoc writes it to a descriptor set without
+  // "--include_source_info", with every field and every method referring to
+  // messages by their fully-qualified names. Imports and options aren't read
+  // from files, so neither dependencies nor options are written
+  pub(super) fn describe_file(
+    &mut self,
+    name: &str,
+    blocks: &[Block<'a>]
+  ) -> Result<FileDescriptorProto, String> {
+    self.prepare(blocks);
+
+    let scope = self.path("");
+    let scope = scope.trim_end_matches('.');
+    let mut file = FileDescriptorProto {
+      name: name.to_string(),
+      package: self
+        .package
+        .unwrap_or_default()
+        .to_string(),
+      ..Default::default()
+    };
+
+    for block in blocks {
+      match (&block.kind, block.identifier) {
+        (Kind::Message(fields), Some(id)) => file
+          .message_type
+          .push(self.describe_message(scope, id, fields)?),
+        (Kind::Service(fields), Some(id)) => file
+          .service
+          .push(self.describe_service(scope, id, fields)?),
+        // Files without a syntax are proto2, which protoc leaves unset
+        (Kind::Syntax("proto3"), _) => file.syntax = "proto3".to_string(),
+        _ => ()
+      }
+    }
+
+    Ok(file)
+  }
+
+  // Fully-qualified name of a message referred to from the given scope, which
+  // is either declared in this file or in an extern path, such as the
+  // well-known types
+  fn describe_type(&self, scope: &str, name: &str) -> Result<String, String> {
+    if let Some(path) = self.lookup_in(scope, name) {
+      return Ok(path);
+    }
+
+    let names: Vec<String> = match name.starts_with('.') {
+      true => vec![name.to_string()],
+      false => scoped_names(scope, name).collect()
+    };
+
+    names
+      .into_iter()
+      .find(|v| self.options.extern_type(v).is_some())
+      .ok_or_else(|| format!("Unknown type {}", name))
+  }
+
+  fn describe_message(
+    &self,
+    scope: &str,
+    id: &str,
+    fields: &[Field<'a>]
+  ) -> Result<DescriptorProto, String> {
+    let path = format!("{}.{}", scope, id);
+    let mut message = DescriptorProto {
+      name: id.to_string(),
+      ..Default::default()
+    };
+
+    for field in fields {
+      match field {
+        Field::Property(property) => message
+          .field
+          .push(self.describe_field(&path, property)?),
+        Field::Block(Block {
+          identifier: Some(id),
+          kind: Kind::Message(fields)
+        }) => message
+          .nested_type
+          .push(self.describe_message(&path, id, fields)?),
+        _ => ()
+      }
+    }
+
+    Ok(message)
+  }
+
+  fn describe_field(
+    &self,
+    scope: &str,
+    property: &Property
+  ) -> Result<FieldDescriptorProto, String> {
+    let (r#type, type_name) = match &property.r#type {
+      Type::Scalar(scalar) => (scalar_type(scalar), String::new()),
+      Type::Message(name) => (
+        FieldDescriptorProto::TYPE_MESSAGE,
+        self.describe_type(scope, name)?
+      )
+    };
+
+    Ok(FieldDescriptorProto {
+      name: property.name.to_string(),
+      number: property.value,
+      label: FieldDescriptorProto::LABEL_OPTIONAL,
+      r#type,
+      type_name,
+      json_name: json_name(property.name)
+    })
+  }
+
+  fn describe_service(
+    &self,
+    scope: &str,
+    id: &str,
+    fields: &[Field<'a>]
+  ) -> Result<ServiceDescriptorProto, String> {
+    let method = fields
+      .iter()
+      .filter_map(|v| match v {
+        Field::Rpc(rpc) => Some(self.describe_method(scope, rpc)),
+        _ => None
+      })
+      .collect::<Result<_, _>>()?;
+
+    Ok(ServiceDescriptorProto {
+      name: id.to_string(),
+      method
+    })
+  }
+
+  fn describe_method(
+    &self,
+    scope: &str,
+    rpc: &Rpc
+  ) -> Result<MethodDescriptorProto, String> {
+    Ok(MethodDescriptorProto {
+      name: rpc.name.to_string(),
+      input_type: self.describe_type(scope, rpc.params.0)?,
+      output_type: self.describe_type(scope, rpc.params.1)?,
+      client_streaming: rpc.streaming.0,
+      server_streaming: rpc.streaming.1
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parser::describe;
+  use crate::{lexer, tokeniser};
+
+  #[test]
+  fn reject_unknown_types() -> Result<(), Box<dyn std::error::Error>> {
+    let input = "package foo;\nmessage Bar {\n  Missing baz = 1;\n  \
+                 google.protobuf.Empty empty = 2;\n}";
+    let tokens = tokeniser::translate(input)?;
+    let blocks = lexer::translate(&tokens)?;
+
+    assert_eq!(
+      describe("foo.proto", &blocks, Default::default()),
+      Err("Unknown type Missing".to_string())
+    );
+
+    let input =
+      "package foo;\nmessage Bar {\n  google.protobuf.Empty empty = 1;\n}";
+    let tokens = tokeniser::translate(input)?;
+    let file =
+      describe("foo.proto", &lexer::translate(&tokens)?, Default::default())?;
+
+    assert_eq!(
+      file.message_type[0].field[0].type_name,
+      ".google.protobuf.Empty"
+    );
+
+    Ok(())
+  }
+
+  #[test]
+  fn convert_json_names() {
+    assert_eq!(json_name("foo_bar_baz"), "fooBarBaz");
+    assert_eq!(json_name("Foo"), "Foo");
+    assert_eq!(json_name("foo__bar"), "fooBar");
+  }
+}
@@ -0,0 +1,129 @@
+use super::{indent, naming, Parser};
+use crate::lexer::{Field, Property, Type};
+
+// Mask helpers within the runtime, called once for each field
+const RUNTIME_MASK: &str = "::pbrs::runtime::mask";
+
+impl<'a> Parser<'a> {
+  // Implementation of the runtime's Masked trait, which picks out fields by
+  // the names the lexer read for them, and follows paths into the message
+  // fields generated alongside this one
+  pub(super) fn format_mask(&self, id: &str, fields: &[Field<'a>]) -> String {
+    let properties = fields
+      .iter()
+      .filter_map(|v| match v {
+        Field::Property(prop) => Some(prop),
+        _ => None
+      })
+      .collect::<Vec<&Property>>();
+    let (is_path, merge, trim) = match properties.is_empty() {
+      // Messages without fields have no paths at all
+      true => (
+        format!("{}let _ = path;\n\n{}false", indent(2), indent(2)),
+        format!("{}let _ = (source, path);", indent(2)),
+        format!("{}let _ = paths;", indent(2))
+      ),
+      false => (
+        properties
+          .iter()
+          .map(|v| self.format_is_path(v))
+          .collect::<Vec<String>>()
+          .join(&format!("\n{}|| ", indent(3))),
+        properties
+          .iter()
+          .map(|v| self.format_merge_path(v))
+          .collect::<Vec<String>>()
+          .join("\n"),
+        properties
+          .iter()
+          .map(|v| self.format_trim_path(v))
+          .collect::<Vec<String>>()
+          .join("\n")
+      )
+    };
+    let is_path = match properties.is_empty() {
+      true => is_path,
+      false => format!("{}{}", indent(2), is_path)
+    };
+
+    [
+      format!(
+        "impl ::pbrs::runtime::Masked for {} {{",
+        naming::upper_camel(id)
+      ),
+      format!("{}fn is_path(path: &[&str]) -> bool {{", indent(1)),
+      is_path,
+      format!("{}}}\n", indent(1)),
+      format!(
+        "{}fn merge_path(&mut self, source: &mut Self, path: &[&str]) {{",
+        indent(1)
+      ),
+      merge,
+      format!("{}}}\n", indent(1)),
+      format!(
+        "{}fn trim_paths(&mut self, paths: &[&[&str]]) {{",
+        indent(1)
+      ),
+      trim,
+      format!("{}}}", indent(1)),
+      "}".to_string()
+    ]
+    .join("\n")
+  }
+
+  // Message type of a field that's generated alongside this one, and so can
+  // be masked within, rather than being generated elsewhere or as a time type
+  fn masked_type(&self, prop: &Property) -> Option<String> {
+    match prop.r#type {
+      Type::Message(name) => self
+        .lookup(name)
+        .filter(|v| self.options.extern_type(v).is_none())
+        .map(|_| self.resolve(name)),
+      Type::Scalar(_) => None
+    }
+  }
+
+  fn format_is_path(&self, prop: &Property) -> String {
+    match self.masked_type(prop) {
+      Some(r#type) => format!(
+        "{}::is_message_field::<{}>(path, \"{}\")",
+        RUNTIME_MASK, r#type, prop.name
+      ),
+      None => format!("{}::is_field(path, \"{}\")", RUNTIME_MASK, prop.name)
+    }
+  }
+
+  fn format_merge_path(&self, prop: &Property) -> String {
+    let name = naming::snake(prop.name);
+    let call = match self.masked_type(prop) {
+      Some(_) => "merge_message_field",
+      None => "merge_field"
+    };
+
+    format!(
+      "{}{}::{}(&mut self.{}, &mut source.{}, path, \"{}\");",
+      indent(2),
+      RUNTIME_MASK,
+      call,
+      name,
+      name,
+      prop.name
+    )
+  }
+
+  fn format_trim_path(&self, prop: &Property) -> String {
+    let call = match self.masked_type(prop) {
+      Some(_) => "trim_message_field",
+      None => "trim_field"
+    };
+
+    format!(
+      "{}{}::{}(&mut self.{}, paths, \"{}\");",
+      indent(2),
+      RUNTIME_MASK,
+      call,
+      naming::snake(prop.name),
+      prop.name
+    )
+  }
+}
@@ -0,0 +1,195 @@
+use super::descriptor::json_name;
+use super::{indent, naming, Parser, RUNTIME_ENCODING};
+use crate::lexer::{Field, Property, Scalar, Type};
+
+// Module of encoding helpers within the runtime for each scalar
+fn scalar_module(scalar: &Scalar) -> &'static str {
+  match scalar {
+    Scalar::Int32 => "int32",
+    Scalar::Bool => "bool",
+    Scalar::r#String => "string"
+  }
+}
+
+impl<'a> Parser<'a> {
+  // Implementation of the runtime's Message trait, which encodes and decodes
+  // each field with the helpers for its type
+  pub(super) fn format_message(
+    &self,
+    id: &str,
+    fields: &[Field<'a>]
+  ) -> String {
+    let properties = fields
+      .iter()
+      .filter_map(|v| match v {
+        Field::Property(prop) => Some(prop),
+        _ => None
+      })
+      .collect::<Vec<&Property>>();
+    let encode = properties
+      .iter()
+      .map(|v| self.format_encode(v))
+      .collect::<Vec<String>>();
+    let merge = properties
+      .iter()
+      .map(|v| self.format_merge(v))
+      .collect::<Vec<String>>();
+    // Messages without fields never write to the buffer
+    let encode = match encode.is_empty() {
+      true => format!("{}let _ = buf;", indent(2)),
+      false => encode.join("\n")
+    };
+
+    format!(
+      "impl ::pbrs::runtime::Message for {} {{\n{}fn encode(&self, buf: &mut \
+       Vec<u8>) {{\n{}\n{}}}\n\n{}fn merge_field(\n{}&mut self,\n{}number: \
+       u32,\n{}wire: ::pbrs::runtime::WireType,\n{}buf: &mut &[u8]\n{}) -> \
+       Result<(), ::pbrs::runtime::DecodeError> {{\n{}match number {{\n{}{}_ \
+       => {}::skip_field(wire, buf)\n{}}}\n{}}}\n\n{}\n}}",
+      naming::upper_camel(id),
+      indent(1),
+      encode,
+      indent(1),
+      indent(1),
+      indent(2),
+      indent(2),
+      indent(2),
+      indent(2),
+      indent(1),
+      indent(2),
+      merge.join(""),
+      indent(3),
+      RUNTIME_ENCODING,
+      indent(2),
+      indent(1),
+      self.format_descriptor(id, &properties)
+    )
+  }
+
+  // Descriptor of a message as a static, which message fields refer to
+  // through the "descriptor" function of their type
+  fn format_descriptor(&self, id: &str, properties: &[&Property]) -> String {
+    let fields = properties
+      .iter()
+      .map(|v| {
+        let r#type = match &v.r#type {
+          Type::Scalar(Scalar::Int32) => "Int32".to_string(),
+          Type::Scalar(Scalar::Bool) => "Bool".to_string(),
+          Type::Scalar(Scalar::r#String) => "String".to_string(),
+          Type::Message(name) => format!(
+            "Message(::pbrs::runtime::MessageType::Static(<{} as \
+             ::pbrs::runtime::Message>::descriptor))",
+            self.resolve(name)
+          )
+        };
+
+        [
+          format!("{}::pbrs::runtime::FieldDescriptor {{", indent(5)),
+          format!("{}name: \"{}\",", indent(6), v.name),
+          format!("{}json_name: \"{}\",", indent(6), json_name(v.name)),
+          format!("{}number: {},", indent(6), v.value),
+          format!("{}label: ::pbrs::runtime::Label::Optional,", indent(6)),
+          format!(
+            "{}r#type: ::pbrs::runtime::FieldType::{}",
+            indent(6),
+            r#type
+          ),
+          format!("{}}}", indent(5))
+        ]
+        .join("\n")
+      })
+      .collect::<Vec<String>>();
+    let fields = match fields.is_empty() {
+      true => "&[]".to_string(),
+      false => format!("&[\n{}\n{}]", fields.join(",\n"), indent(4))
+    };
+    let full_name = self.path("");
+
+    [
+      format!(
+        "{}fn descriptor() -> &'static ::pbrs::runtime::MessageDescriptor {{",
+        indent(1)
+      ),
+      format!(
+        "{}static DESCRIPTOR: ::pbrs::runtime::MessageDescriptor =",
+        indent(2)
+      ),
+      format!("{}::pbrs::runtime::MessageDescriptor {{", indent(3)),
+      format!("{}name: \"{}\",", indent(4), id),
+      format!(
+        "{}full_name: \"{}\",",
+        indent(4),
+        full_name.trim_matches('.')
+      ),
+      format!("{}fields: {}", indent(4), fields),
+      format!("{}}};", indent(3)),
+      String::new(),
+      format!("{}&DESCRIPTOR", indent(2)),
+      format!("{}}}", indent(1))
+    ]
+    .join("\n")
+  }
+
+  fn format_encode(&self, prop: &Property) -> String {
+    let name = naming::snake(prop.name);
+
+    match prop.r#type {
+      Type::Scalar(ref scalar) => format!(
+        "{}{}::{}::encode({}, &self.{}, buf);",
+        indent(2),
+        RUNTIME_ENCODING,
+        scalar_module(scalar),
+        prop.value,
+        name
+      ),
+      // Unset message fields are left out entirely, while those generated as
+      // time types are converted to the message they're encoded as
+      Type::Message(r#type) => {
+        let call = match self.time_type(r#type) {
+          Some(_) => {
+            format!("converted::encode::<{}, _>", self.resolve(r#type))
+          }
+          None => "message::encode".to_string()
+        };
+
+        format!(
+          "{}if let Some(value) = &self.{} {{\n{}{}::{}({}, value, buf);\n{}}}",
+          indent(2),
+          name,
+          indent(3),
+          RUNTIME_ENCODING,
+          call,
+          prop.value,
+          indent(2)
+        )
+      }
+    }
+  }
+
+  fn format_merge(&self, prop: &Property) -> String {
+    let name = naming::snake(prop.name);
+    let (call, target) = match prop.r#type {
+      Type::Scalar(ref scalar) => (
+        format!("{}::merge", scalar_module(scalar)),
+        format!("&mut self.{}", name)
+      ),
+      Type::Message(r#type) if self.time_type(r#type).is_some() => (
+        format!("converted::merge::<{}, _>", self.resolve(r#type)),
+        format!("&mut self.{}", name)
+      ),
+      Type::Message(_) => (
+        "message::merge".to_string(),
+        format!("self.{}.get_or_insert_with(Default::default)", name)
+      )
+    };
+
+    format!(
+      "{}{} => {}::{}(wire, {}, buf),\n",
+      indent(3),
+      prop.value,
+      RUNTIME_ENCODING,
+      call,
+      target
+    )
+  }
+}
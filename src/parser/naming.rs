@@ -0,0 +1,55 @@
+use heck::{ToSnakeCase, ToUpperCamelCase};
+
+// Keywords that can only be used as identifiers in their raw form, including
+// those reserved for future use
+const KEYWORDS: &[&str] = &[
+  "abstract", "as", "async", "await", "become", "box", "break", "const",
+  "continue", "do", "dyn", "else", "enum", "extern", "false", "final", "fn",
+  "for", "gen", "if", "impl", "in", "let", "loop", "macro", "match", "mod",
+  "move", "mut", "override", "priv", "pub", "ref", "return", "static",
+  "struct", "trait", "true", "try", "type", "typeof", "unsafe", "unsized",
+  "use", "virtual", "where", "while", "yield"
+];
+
+// Keywords that can't be raw identifiers either, so are suffixed instead
+const RESERVED: &[&str] = &["crate", "self", "Self", "super", "_"];
+
+pub fn escape(name: String) -> String {
+  if RESERVED.contains(&name.as_str()) {
+    format!("{}_", name)
+  } else if KEYWORDS.contains(&name.as_str()) {
+    format!("r#{}", name)
+  } else {
+    name
+  }
+}
+
+// Name of a field, module or function
+pub fn snake(name: &str) -> String {
+  escape(name.to_snake_case())
+}
+
+// Name of a struct or trait
+pub fn upper_camel(name: &str) -> String {
+  escape(name.to_upper_camel_case())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn escape_keywords() {
+    assert_eq!(snake("type"), "r#type");
+    assert_eq!(snake("async"), "r#async");
+    assert_eq!(snake("self"), "self_");
+    assert_eq!(upper_camel("self"), "Self_");
+  }
+
+  #[test]
+  fn convert_case() {
+    assert_eq!(snake("someField"), "some_field");
+    assert_eq!(snake("SayHello"), "say_hello");
+    assert_eq!(upper_camel("hello_request"), "HelloRequest");
+  }
+}
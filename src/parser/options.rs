@@ -0,0 +1,388 @@
+use super::naming;
+
+// Derives added to every generated struct unless they're switched off
+const DEFAULT_DERIVES: &str = "Clone, Debug, PartialEq, Default";
+
+// Well-known types of "google/protobuf", which are built into the runtime
+// rather than generated
+const WELL_KNOWN_TYPES: (&str, &str) =
+  (".google.protobuf", "::pbrs::runtime::well_known");
+
+// Crates whose types Timestamp and Duration fields are generated as, in
+// place of the well-known types they're encoded as
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimeTypes {
+  Chrono,
+  Std,
+  Time
+}
+
+impl TimeTypes {
+  // Rust type of a field given the full name of its message type, when it's
+  // a Timestamp or Duration
+  pub fn rust_type(self, name: &str) -> Option<&'static str> {
+    match (self, name.trim_start_matches('.')) {
+      (Self::Chrono, "google.protobuf.Timestamp") => {
+        Some("::chrono::DateTime<::chrono::Utc>")
+      }
+      (Self::Std, "google.protobuf.Timestamp") => {
+        Some("::std::time::SystemTime")
+      }
+      (Self::Time, "google.protobuf.Timestamp") => {
+        Some("::time::OffsetDateTime")
+      }
+      (Self::Time, "google.protobuf.Duration") => Some("::time::Duration"),
+      (_, "google.protobuf.Duration") => Some("::std::time::Duration"),
+      _ => None
+    }
+  }
+}
+
+// Codegen options that toggle optional output from the parser, set from the
+// command line before any blocks are parsed
+#[derive(Clone, Debug)]
+pub struct Options {
+  pub box_messages: bool,
+  pub derives: Option<String>,
+  // Packages and messages that are generated elsewhere, along with the Rust
+  // paths they're found at, as in (".common", "::common_types")
+  pub extern_paths: Vec<(String, String)>,
+  pub field_attributes: Vec<(String, String)>,
+  // Options that only apply to the packages and messages matching a path,
+  // written as they are on the command line
+  pub overrides: Vec<(String, String)>,
+  pub serde: bool,
+  pub time_types: Option<TimeTypes>,
+  pub type_attributes: Vec<(String, String)>
+}
+
+impl Default for Options {
+  fn default() -> Self {
+    Self {
+      box_messages: false,
+      derives: Some(DEFAULT_DERIVES.to_string()),
+      extern_paths: vec![(
+        WELL_KNOWN_TYPES.0.to_string(),
+        WELL_KNOWN_TYPES.1.to_string()
+      )],
+      field_attributes: Vec::new(),
+      overrides: Vec::new(),
+      serde: false,
+      time_types: None,
+      type_attributes: Vec::new()
+    }
+  }
+}
+
+impl Options {
+  pub fn box_messages(mut self, enabled: bool) -> Self {
+    self.box_messages = enabled;
+
+    self
+  }
+
+  pub fn derives(mut self, derives: Option<&str>) -> Self {
+    self.derives = derives.map(String::from);
+
+    self
+  }
+
+  pub fn extern_path(mut self, proto: &str, rust: &str) -> Self {
+    self
+      .extern_paths
+      .push((proto.to_string(), rust.to_string()));
+
+    self
+  }
+
+  pub fn field_attribute(mut self, path: &str, attribute: &str) -> Self {
+    self
+      .field_attributes
+      .push((path.to_string(), attribute.to_string()));
+
+    self
+  }
+
+  // Set an option for everything matching a path, as in ".pkg" or
+  // ".pkg.Msg", which takes precedence over options set for all paths
+  pub fn override_option(
+    mut self,
+    path: &str,
+    option: &str
+  ) -> Result<Self, String> {
+    Self::default().option(option)?;
+    self
+      .overrides
+      .push((path.to_string(), option.to_string()));
+
+    Ok(self)
+  }
+
+  pub fn serde(mut self, enabled: bool) -> Self {
+    self.serde = enabled;
+
+    self
+  }
+
+  pub fn time_types(mut self, types: Option<TimeTypes>) -> Self {
+    self.time_types = types;
+
+    self
+  }
+
+  pub fn type_attribute(mut self, path: &str, attribute: &str) -> Self {
+    self
+      .type_attributes
+      .push((path.to_string(), attribute.to_string()));
+
+    self
+  }
+
+  // Set an option as it's written on the command line without its leading
+  // dashes, as in "serde" or "type-attribute=.pkg.Msg=#[derive(Hash)]"
+  pub fn option(self, option: &str) -> Result<Self, String> {
+    let (name, value) = option
+      .split_once('=')
+      .unwrap_or((option, ""));
+    // Attributes and extern paths are further split into "path=value"
+    let (path, attribute) = value
+      .split_once('=')
+      .unwrap_or((value, ""));
+
+    // Switches may be turned off again with "=false"
+    let enabled = match value {
+      "" | "true" => Ok(true),
+      "false" => Ok(false),
+      _ => Err(format!("Invalid value {} for {}", value, name))
+    };
+    // Attributes and extern paths need both a path and what it maps to
+    let mapping = match path.is_empty() || attribute.is_empty() {
+      true => Err(format!(
+        "Expected PATH=VALUE for {} but found {}",
+        name, value
+      )),
+      false => Ok((path, attribute))
+    };
+
+    match name {
+      "serde" => Ok(self.serde(enabled?)),
+      "box-messages" => Ok(self.box_messages(enabled?)),
+      "derives" => Ok(self.derives(Some(value).filter(|v| !v.is_empty()))),
+      "time-types" => match value {
+        "chrono" => Ok(self.time_types(Some(TimeTypes::Chrono))),
+        "std" => Ok(self.time_types(Some(TimeTypes::Std))),
+        "time" => Ok(self.time_types(Some(TimeTypes::Time))),
+        "false" => Ok(self.time_types(None)),
+        _ => Err(format!("Invalid value {} for {}", value, name))
+      },
+      "extern-path" => mapping.map(|(path, rust)| self.extern_path(path, rust)),
+      "type-attribute" => {
+        mapping.map(|(path, attribute)| self.type_attribute(path, attribute))
+      }
+      "field-attribute" => {
+        mapping.map(|(path, attribute)| self.field_attribute(path, attribute))
+      }
+      _ => Err(format!("Unknown option {}", name))
+    }
+  }
+
+  // Options for a package or message, with the overrides of any matching
+  // path applied from the least to the most specific
+  pub fn scoped(&self, path: &str) -> Self {
+    let mut overrides = self
+      .overrides
+      .iter()
+      .filter(|(matcher, _)| matches(matcher, path))
+      .collect::<Vec<&(String, String)>>();
+
+    overrides.sort_by_key(|(matcher, _)| matcher.matches('.').count());
+    overrides
+      .into_iter()
+      .fold(self.clone(), |options, (_, option)| {
+        options
+          .clone()
+          .option(option)
+          .unwrap_or(options)
+      })
+  }
+
+  // Rust path of a type that's generated elsewhere, given its fully-qualified
+  // name, which is found beneath the most specific extern path it's within.
+  // Packages within the extern path are modules, as are any messages that
+  // the type is nested in
+  pub fn extern_type(&self, name: &str) -> Option<String> {
+    let (proto, rust) = self
+      .extern_paths
+      .iter()
+      .filter(|(proto, _)| proto.starts_with('.') && matches(proto, name))
+      .max_by_key(|(proto, _)| proto.len())?;
+    let rest = name[proto.len().min(name.len())..]
+      .trim_start_matches('.')
+      .split('.')
+      .filter(|v| !v.is_empty())
+      .collect::<Vec<&str>>();
+    let Some((id, parents)) = rest.split_last() else {
+      return Some(rust.clone());
+    };
+    let path = parents
+      .iter()
+      .map(|v| naming::snake(v))
+      .chain([naming::upper_camel(id)]);
+
+    Some(
+      [rust.clone()]
+        .into_iter()
+        .chain(path)
+        .collect::<Vec<String>>()
+        .join("::")
+    )
+  }
+
+  pub fn field_attributes_for(&self, path: &str) -> Vec<&str> {
+    Self::matching(&self.field_attributes, path)
+  }
+
+  pub fn type_attributes_for(&self, path: &str) -> Vec<&str> {
+    Self::matching(&self.type_attributes, path)
+  }
+
+  fn matching<'a>(
+    attributes: &'a [(String, String)],
+    path: &str
+  ) -> Vec<&'a str> {
+    attributes
+      .iter()
+      .filter(|(matcher, _)| matches(matcher, path))
+      .map(|(_, attribute)| attribute.as_str())
+      .collect()
+  }
+}
+
+// Match a fully-qualified Protobuf path such as ".pkg.Msg.field" against a
+// matcher - matchers starting with "." are anchored to the root of the path
+// and match whole segments, so ".pkg" covers everything within "pkg", while
+// other matchers are compared against the trailing segments, so "Msg.field"
+// covers the field in any package
+pub fn matches(matcher: &str, path: &str) -> bool {
+  if matcher == "." {
+    return true;
+  }
+
+  match matcher.strip_prefix('.') {
+    Some(_) => path == matcher || path.starts_with(&format!("{}.", matcher)),
+    None => path == matcher || path.ends_with(&format!(".{}", matcher))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn match_prefix() {
+    assert!(matches(".", ".pkg.Msg"));
+    assert!(matches(".pkg", ".pkg.Msg.field"));
+    assert!(matches(".pkg.Msg", ".pkg.Msg"));
+    assert!(!matches(".pkg.Msg", ".pkg.MsgTwo"));
+  }
+
+  #[test]
+  fn override_options() -> Result<(), String> {
+    let options = Options::default()
+      .option("serde")?
+      .override_option(".pkg.Msg", "serde=false")?
+      .override_option(".pkg", "derives=Clone")?
+      .override_option(".pkg", "serde")?;
+
+    assert!(options.scoped(".other.Msg").serde);
+    assert!(!options.scoped(".pkg.Msg.Inner").serde);
+    assert_eq!(
+      options
+        .scoped(".pkg.Msg")
+        .derives
+        .as_deref(),
+      Some("Clone")
+    );
+    assert!(options
+      .override_option(".pkg", "serde=maybe")
+      .is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn time_types() -> Result<(), String> {
+    let options = Options::default()
+      .option("time-types=chrono")?
+      .override_option(".pkg", "time-types=time")?;
+    let types = options
+      .scoped(".other")
+      .time_types
+      .ok_or("Missing time types")?;
+
+    assert_eq!(
+      types.rust_type(".google.protobuf.Timestamp"),
+      Some("::chrono::DateTime<::chrono::Utc>")
+    );
+    assert_eq!(
+      types.rust_type("google.protobuf.Duration"),
+      Some("::std::time::Duration")
+    );
+    assert_eq!(types.rust_type(".google.protobuf.Any"), None);
+    assert_eq!(options.scoped(".pkg").time_types, Some(TimeTypes::Time));
+    assert!(Options::default()
+      .option("time-types=jiff")
+      .is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn map_extern_types() -> Result<(), String> {
+    let options = Options::default()
+      .option("extern-path=.common=::common_types")?
+      .extern_path(".common.money", "::money")
+      .extern_path(".google.protobuf.Any", "::pbrs::runtime::Any");
+
+    assert_eq!(
+      options.extern_type(".common.v1.Price.Unit"),
+      Some("::common_types::v1::price::Unit".to_string())
+    );
+    assert_eq!(
+      options.extern_type(".common.money.Money"),
+      Some("::money::Money".to_string())
+    );
+    assert_eq!(
+      options.extern_type(".google.protobuf.Any"),
+      Some("::pbrs::runtime::Any".to_string())
+    );
+    assert_eq!(
+      options.extern_type(".google.protobuf.UInt32Value"),
+      Some("::pbrs::runtime::well_known::UInt32Value".to_string())
+    );
+    assert_eq!(options.extern_type(".commons.Money"), None);
+
+    for option in [
+      "extern-path=.common",
+      "extern-path=.common=",
+      "type-attribute==#[derive(Hash)]",
+      "field-attribute=.pkg.Msg.field"
+    ] {
+      assert!(
+        Options::default()
+          .option(option)
+          .is_err(),
+        "{}",
+        option
+      );
+    }
+
+    Ok(())
+  }
+
+  #[test]
+  fn match_suffix() {
+    assert!(matches("Msg.field", ".pkg.Msg.field"));
+    assert!(!matches("Msg", ".pkg.OtherMsg"));
+  }
+}
@@ -0,0 +1,323 @@
+use super::{
+  indent,
+  naming,
+  Parser,
+  RUNTIME_REQUEST,
+  RUNTIME_RESPONSE,
+  RUNTIME_STATUS,
+  RUNTIME_STREAMING
+};
+use crate::lexer::{Field, Rpc};
+
+// Name of the runtime functions that send and handle each shape of call,
+// depending on which sides of the call are streamed
+fn shape(rpc: &Rpc) -> &'static str {
+  match rpc.streaming {
+    (false, false) => "unary",
+    (true, false) => "client_streaming",
+    (false, true) => "server_streaming",
+    (true, true) => "streaming"
+  }
+}
+
+fn rpcs<'b, 'a>(fields: &'b [Field<'a>]) -> impl Iterator<Item = &'b Rpc<'a>> {
+  fields.iter().filter_map(|v| match v {
+    Field::Rpc(rpc) => Some(rpc),
+    _ => None
+  })
+}
+
+impl<'a> Parser<'a> {
+  // Fully-qualified name of a service, as in "pkg.Service"
+  pub(super) fn service_name(&self, service: &str) -> String {
+    match self.package {
+      Some(package) => format!("{}.{}", package, service),
+      None => service.to_string()
+    }
+  }
+
+  // Full path of a method as it's sent over the wire, as in
+  // "/pkg.Service/Method"
+  pub(super) fn method_path(&self, service: &str, rpc: &str) -> String {
+    format!("/{}/{}", self.service_name(service), rpc)
+  }
+
+  // Request and response types of a method, where streamed messages are
+  // wrapped in the runtime's stream
+  fn method_types(&self, rpc: &Rpc) -> (String, String) {
+    let wrap = |name: &str, streaming: bool| match streaming {
+      true => format!("{}<{}>", RUNTIME_STREAMING, self.resolve(name)),
+      false => self.resolve(name)
+    };
+
+    (
+      wrap(rpc.params.0, rpc.streaming.0),
+      wrap(rpc.params.1, rpc.streaming.1)
+    )
+  }
+
+  // Method of the service trait, which implementations are free to write as
+  // "async fn" while callers can rely on the futures being sent across
+  // threads
+  pub(super) fn format_method(&self, rpc: &Rpc) -> String {
+    format!("{};", self.format_signature(rpc))
+  }
+
+  fn format_signature(&self, rpc: &Rpc) -> String {
+    let (req, res) = self.method_types(rpc);
+
+    [
+      format!("{}fn {}(", indent(1), naming::snake(rpc.name)),
+      format!("{}&self,", indent(2)),
+      format!("{}req: {}<{}>", indent(2), RUNTIME_REQUEST, req),
+      format!(
+        "{}) -> impl ::std::future::Future<Output = Result<{}<{}>, {}>> + Send",
+        indent(1),
+        RUNTIME_RESPONSE,
+        res,
+        RUNTIME_STATUS
+      )
+    ]
+    .join("\n")
+  }
+
+  // Client for a service that encodes each request, sends it over any
+  // transport and decodes the response
+  pub(super) fn format_client(&self, id: &str, fields: &[Field<'a>]) -> String {
+    let name = format!("{}Client", naming::upper_camel(id));
+    let methods = rpcs(fields)
+      .map(|v| format!("\n\n{}", self.format_client_method(id, v)))
+      .collect::<String>();
+
+    [
+      "#[derive(Clone, Debug)]".to_string(),
+      format!("pub struct {}<T> {{", name),
+      format!("{}transport: T", indent(1)),
+      "}".to_string(),
+      String::new(),
+      format!("impl<T: ::pbrs::runtime::Transport> {}<T> {{", name),
+      format!("{}pub fn new(transport: T) -> Self {{", indent(1)),
+      format!("{}Self {{ transport }}", indent(2)),
+      format!("{}}}{}", indent(1), methods),
+      "}".to_string()
+    ]
+    .join("\n")
+  }
+
+  fn format_client_method(&self, service: &str, rpc: &Rpc) -> String {
+    let name = naming::snake(rpc.name);
+    // The suffix is added before escaping, so "Self" and "Type" give
+    // "self_with_options" and "type_with_options" rather than their escaped
+    // names with it
+    let with_options = naming::snake(&format!("{}_with_options", rpc.name));
+    let (req, res) = self.method_types(rpc);
+    // Single requests are borrowed, while streams are handed over
+    let req = match rpc.streaming.0 {
+      true => req,
+      false => format!("&{}", req)
+    };
+
+    [
+      format!(
+        "{}pub async fn {}(&self, req: {}) -> Result<{}, {}> {{",
+        indent(1),
+        name,
+        req,
+        res,
+        RUNTIME_STATUS
+      ),
+      format!("{}self", indent(2)),
+      format!("{}.{}(req, Default::default())", indent(3), with_options),
+      format!("{}.await", indent(3)),
+      format!("{}.map(|v| v.message)", indent(3)),
+      format!("{}}}", indent(1)),
+      String::new(),
+      format!("{}pub async fn {}(", indent(1), with_options),
+      format!("{}&self,", indent(2)),
+      format!("{}req: {},", indent(2), req),
+      format!("{}options: ::pbrs::runtime::CallOptions", indent(2)),
+      format!(
+        "{}) -> Result<{}<{}>, {}> {{",
+        indent(1),
+        RUNTIME_RESPONSE,
+        res,
+        RUNTIME_STATUS
+      ),
+      format!(
+        "{}::pbrs::runtime::{}(&self.transport, \"{}\", req, options).await",
+        indent(2),
+        shape(rpc),
+        self.method_path(service, rpc.name)
+      ),
+      format!("{}}}", indent(1))
+    ]
+    .join("\n")
+  }
+
+  // Dispatcher for a service that decodes each request, calls the matching
+  // method of the trait and encodes the response
+  pub(super) fn format_server(&self, id: &str, fields: &[Field<'a>]) -> String {
+    let name = format!("{}Server", naming::upper_camel(id));
+    let arms = rpcs(fields)
+      .map(|v| {
+        [
+          format!(
+            "{}\"{}\" => Box::pin(::pbrs::runtime::handle_{}(req, |req| {{",
+            indent(3),
+            self.method_path(id, v.name),
+            shape(v)
+          ),
+          format!("{}self.inner.{}(req)", indent(4), naming::snake(v.name)),
+          format!("{}}})),\n", indent(3))
+        ]
+        .join("\n")
+      })
+      .collect::<String>();
+    let bytes = format!("{}<Vec<u8>>", RUNTIME_STREAMING);
+
+    [
+      format!("pub struct {}<S> {{", name),
+      format!("{}inner: ::std::sync::Arc<S>", indent(1)),
+      "}".to_string(),
+      String::new(),
+      format!("impl<S: {}> {}<S> {{", naming::upper_camel(id), name),
+      format!("{}pub fn new(inner: S) -> Self {{", indent(1)),
+      format!("{}Self::from_arc(::std::sync::Arc::new(inner))", indent(2)),
+      format!("{}}}", indent(1)),
+      String::new(),
+      // Shared implementations, such as mocks, stay reachable by the caller
+      format!(
+        "{}pub fn from_arc(inner: ::std::sync::Arc<S>) -> Self {{",
+        indent(1)
+      ),
+      format!("{}Self {{ inner }}", indent(2)),
+      format!("{}}}", indent(1)),
+      "}".to_string(),
+      String::new(),
+      format!(
+        "impl<S: {}> ::pbrs::runtime::Service for {}<S> {{",
+        naming::upper_camel(id),
+        name
+      ),
+      format!("{}fn name(&self) -> &'static str {{", indent(1)),
+      format!("{}\"{}\"", indent(2), self.service_name(id)),
+      format!("{}}}", indent(1)),
+      String::new(),
+      format!("{}fn call<'a>(", indent(1)),
+      format!("{}&'a self,", indent(2)),
+      format!("{}path: &'a str,", indent(2)),
+      format!("{}req: {}<{}>", indent(2), RUNTIME_REQUEST, bytes),
+      format!(
+        "{}) -> ::pbrs::runtime::BoxFuture<'a, Result<{}<{}>, {}>> {{",
+        indent(1),
+        RUNTIME_RESPONSE,
+        bytes,
+        RUNTIME_STATUS
+      ),
+      format!("{}match path {{", indent(2)),
+      format!(
+        "{}{}_ => Box::pin(async move {{ Err({}::unimplemented(path)) }})",
+        arms,
+        indent(3),
+        RUNTIME_STATUS
+      ),
+      format!("{}}}", indent(2)),
+      format!("{}}}", indent(1)),
+      "}".to_string()
+    ]
+    .join("\n")
+  }
+
+  // Mock of a service for testing its callers in memory, with a field per
+  // method for programming responses and checking the calls it was given
+  pub(super) fn format_mock(&self, id: &str, fields: &[Field<'a>]) -> String {
+    let name = format!("Mock{}", naming::upper_camel(id));
+    let rpcs = rpcs(fields).collect::<Vec<&Rpc>>();
+    let fields = rpcs
+      .iter()
+      .map(|v| {
+        let (req, res) = self.method_types(v);
+        // Streamed requests are recorded as the list of their messages
+        let req = match v.streaming.0 {
+          true => format!("Vec<{}>", self.resolve(v.params.0)),
+          false => req
+        };
+
+        format!(
+          "{}pub {}: ::pbrs::runtime::MockMethod<{}, {}>",
+          indent(1),
+          naming::snake(v.name),
+          req,
+          res
+        )
+      })
+      .collect::<Vec<String>>();
+    let constructors = rpcs
+      .iter()
+      .map(|v| {
+        format!(
+          "{}{}: ::pbrs::runtime::MockMethod::new(\"{}\")",
+          indent(3),
+          naming::snake(v.name),
+          self.method_path(id, v.name)
+        )
+      })
+      .collect::<Vec<String>>();
+    let checks = rpcs
+      .iter()
+      .map(|v| {
+        format!("\n{}self.{}.verify();", indent(2), naming::snake(v.name))
+      })
+      .collect::<String>();
+    let methods = rpcs
+      .iter()
+      .map(|v| {
+        let call = match v.streaming.0 {
+          true => "call_streaming",
+          false => "call"
+        };
+
+        format!(
+          "{} {{\n{}self.{}.{}(req)\n{}}}",
+          self.format_signature(v),
+          indent(2),
+          naming::snake(v.name),
+          call,
+          indent(1)
+        )
+      })
+      .collect::<Vec<String>>();
+
+    [
+      format!("pub struct {} {{", name),
+      fields.join(",\n"),
+      "}".to_string(),
+      String::new(),
+      format!("impl {} {{", name),
+      format!("{}pub fn new() -> Self {{", indent(1)),
+      format!("{}Self {{", indent(2)),
+      constructors.join(",\n"),
+      format!("{}}}", indent(2)),
+      format!("{}}}", indent(1)),
+      String::new(),
+      format!(
+        "{}// Panic unless every method was called as expected",
+        indent(1)
+      ),
+      format!("{}pub fn verify(&self) {{{}", indent(1), checks),
+      format!("{}}}", indent(1)),
+      "}".to_string(),
+      String::new(),
+      format!("impl Default for {} {{", name),
+      format!("{}fn default() -> Self {{", indent(1)),
+      format!("{}Self::new()", indent(2)),
+      format!("{}}}", indent(1)),
+      "}".to_string(),
+      String::new(),
+      format!("impl {} for {} {{", naming::upper_camel(id), name),
+      methods.join("\n\n"),
+      "}".to_string()
+    ]
+    .join("\n")
+  }
+}
@@ -0,0 +1,182 @@
+use crate::lexer::translate_descriptor;
+use crate::parser::{self, Options};
+use crate::runtime::descriptor::FileDescriptorProto;
+use crate::runtime::encoding::{self, DecodeError, Message, WireType};
+
+// Request that protoc sends a plugin on stdin, as in
+// "google/protobuf/compiler/plugin.proto"
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CodeGeneratorRequest {
+  pub file_to_generate: Vec<String>,
+  pub parameter: String,
+  pub proto_file: Vec<FileDescriptorProto>
+}
+
+impl Message for CodeGeneratorRequest {
+  fn encode(&self, buf: &mut Vec<u8>) {
+    encoding::string::encode_repeated(1, &self.file_to_generate, buf);
+    encoding::string::encode(2, &self.parameter, buf);
+    encoding::message::encode_repeated(15, &self.proto_file, buf);
+  }
+
+  fn merge_field(
+    &mut self,
+    number: u32,
+    wire: WireType,
+    buf: &mut &[u8]
+  ) -> Result<(), DecodeError> {
+    match number {
+      1 => {
+        encoding::string::merge_repeated(wire, &mut self.file_to_generate, buf)
+      }
+      2 => encoding::string::merge(wire, &mut self.parameter, buf),
+      15 => encoding::message::merge_repeated(wire, &mut self.proto_file, buf),
+      _ => encoding::skip_field(wire, buf)
+    }
+  }
+}
+
+// Response that a plugin writes to stdout, holding either the generated files
+// or the error that stopped it
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CodeGeneratorResponse {
+  pub error: String,
+  pub file: Vec<File>
+}
+
+impl Message for CodeGeneratorResponse {
+  fn encode(&self, buf: &mut Vec<u8>) {
+    encoding::string::encode(1, &self.error, buf);
+    encoding::message::encode_repeated(15, &self.file, buf);
+  }
+
+  fn merge_field(
+    &mut self,
+    number: u32,
+    wire: WireType,
+    buf: &mut &[u8]
+  ) -> Result<(), DecodeError> {
+    match number {
+      1 => encoding::string::merge(wire, &mut self.error, buf),
+      15 => encoding::message::merge_repeated(wire, &mut self.file, buf),
+      _ => encoding::skip_field(wire, buf)
+    }
+  }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct File {
+  pub name: String,
+  pub content: String
+}
+
+impl Message for File {
+  fn encode(&self, buf: &mut Vec<u8>) {
+    encoding::string::encode(1, &self.name, buf);
+    encoding::string::encode(15, &self.content, buf);
+  }
+
+  fn merge_field(
+    &mut self,
+    number: u32,
+    wire: WireType,
+    buf: &mut &[u8]
+  ) -> Result<(), DecodeError> {
+    match number {
+      1 => encoding::string::merge(wire, &mut self.name, buf),
+      15 => encoding::string::merge(wire, &mut self.content, buf),
+      _ => encoding::skip_field(wire, buf)
+    }
+  }
+}
+
+// Options are passed to plugins as "--pbrs_opt=serde,box-messages", so the
+// derives within an option are separated with "+" rather than commas
+fn options(parameter: &str) -> Result<Options, String> {
+  parameter
+    .split(',')
+    .map(str::trim)
+    .filter(|v| !v.is_empty())
+    .try_fold(Options::default(), |options, v| {
+      match v.strip_prefix("derives=") {
+        Some(derives) => {
+          options.option(&format!("derives={}", derives.replace('+', ", ")))
+        }
+        None => options.option(v)
+      }
+    })
+}
+
+fn generate(request: &CodeGeneratorRequest) -> Result<Vec<File>, String> {
+  let options = options(&request.parameter)?;
+  let files = request
+    .file_to_generate
+    .iter()
+    .map(|name| {
+      let file = request
+        .proto_file
+        .iter()
+        .find(|v| &v.name == name)
+        .ok_or_else(|| format!("{}: Missing descriptor", name))?;
+      let blocks = translate_descriptor(file)
+        .map_err(|err| format!("{}: {}", name, err))?;
+      let code = parser::translate(blocks, options.clone());
+
+      // Files in extern paths are generated elsewhere
+      Ok((!code.is_empty()).then(|| File {
+        name: format!("{}.rs", name.trim_end_matches(".proto")),
+        content: format!("{}\n", code)
+      }))
+    })
+    .collect::<Result<Vec<Option<File>>, String>>()?;
+
+  Ok(files.into_iter().flatten().collect())
+}
+
+// Generate a Rust file for each file that protoc asks for, named after the
+// file it was compiled from. Errors are reported through the response, which
+// protoc shows to the user
+pub fn run(request: &CodeGeneratorRequest) -> CodeGeneratorResponse {
+  match generate(request) {
+    Ok(file) => CodeGeneratorResponse {
+      file,
+      ..Default::default()
+    },
+    Err(error) => CodeGeneratorResponse {
+      error,
+      ..Default::default()
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parse_parameter() -> Result<(), String> {
+    let result = options("serde, derives=Clone+Debug,box-messages")?;
+
+    assert!(result.serde);
+    assert!(result.box_messages);
+    assert_eq!(result.derives.as_deref(), Some("Clone, Debug"));
+    assert_eq!(
+      options("serde,unknown").map(|_| ()),
+      Err("Unknown option unknown".to_string())
+    );
+
+    Ok(())
+  }
+
+  #[test]
+  fn report_errors() {
+    let request = CodeGeneratorRequest {
+      file_to_generate: vec!["foo.proto".to_string()],
+      ..Default::default()
+    };
+    let response = run(&request);
+
+    assert_eq!(response.error, "foo.proto: Missing descriptor");
+    assert!(response.file.is_empty());
+  }
+}
@@ -0,0 +1,68 @@
+// Types shared by all generated code, which refers to them through the
+// "::pbrs::runtime" path
+mod client;
+pub mod descriptor;
+pub mod encoding;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod json;
+pub mod mask;
+mod mock;
+mod reflect;
+mod registry;
+mod server;
+mod status;
+mod stream;
+pub mod text;
+pub mod well_known;
+
+pub use client::{
+  client_streaming,
+  server_streaming,
+  streaming,
+  unary,
+  CallOptions,
+  Transport
+};
+pub use encoding::{DecodeError, Message, WireType};
+pub use mask::Masked;
+pub use mock::MockMethod;
+pub use reflect::{
+  leak_descriptors,
+  DynamicMessage,
+  FieldDescriptor,
+  FieldType,
+  Label,
+  MessageDescriptor,
+  MessageType,
+  Value
+};
+pub use registry::TypeRegistry;
+pub use server::{
+  handle_client_streaming,
+  handle_server_streaming,
+  handle_streaming,
+  handle_unary,
+  BoxFuture,
+  Router,
+  Service
+};
+pub use status::{Code, Metadata, Request, Response, Status};
+pub use stream::{channel, Sender, Streaming};
+pub use text::TextFormat;
+
+// Generated code only awaits transports and services, so in-memory ones
+// complete without ever needing to be woken
+#[cfg(test)]
+pub(crate) fn block_on<F: std::future::Future>(future: F) -> F::Output {
+  use std::task::{Context, Poll, Waker};
+
+  let mut future = std::pin::pin!(future);
+  let mut cx = Context::from_waker(Waker::noop());
+
+  loop {
+    if let Poll::Ready(value) = future.as_mut().poll(&mut cx) {
+      return value;
+    }
+  }
+}
@@ -0,0 +1,260 @@
+use super::encoding::Message;
+use super::status::{Metadata, Response, Status};
+use super::stream::Streaming;
+use std::future::Future;
+use std::time::Duration;
+
+// Options for a single call, which the transport applies to the request it
+// sends
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CallOptions {
+  pub metadata: Metadata,
+  pub timeout: Option<Duration>
+}
+
+impl CallOptions {
+  pub fn metadata(mut self, key: &str, value: &str) -> Self {
+    self
+      .metadata
+      .insert(key.to_string(), value.to_string());
+
+    self
+  }
+
+  pub fn timeout(mut self, timeout: Duration) -> Self {
+    self.timeout = Some(timeout);
+
+    self
+  }
+}
+
+// Any byte-level transport that generated clients can send calls over, given
+// the full path of the method such as "/pkg.Service/Method". Every call is a
+// stream of encoded messages each way, which holds a single message for
+// methods that don't stream
+pub trait Transport: Send + Sync {
+  fn call(
+    &self,
+    path: &str,
+    body: Streaming<Vec<u8>>,
+    options: CallOptions
+  ) -> impl Future<Output = Result<Response<Streaming<Vec<u8>>>, Status>> + Send;
+}
+
+fn encode_all<Req>(req: Streaming<Req>) -> Streaming<Vec<u8>>
+where
+  Req: Message + Send + 'static
+{
+  req.map(|v| Ok(v.encode_to_vec()))
+}
+
+fn decode_all<Res>(res: Streaming<Vec<u8>>) -> Streaming<Res>
+where
+  Res: Message + Default + Send + 'static
+{
+  res.map(|v| Res::decode(&v).map_err(|err| Status::internal(err.to_string())))
+}
+
+// Read the only message of a response, which may still be followed by an
+// error ending the stream
+async fn single<Res>(mut stream: Streaming<Res>) -> Result<Res, Status>
+where
+  Res: Send + 'static
+{
+  let message = stream
+    .message()
+    .await
+    .unwrap_or_else(|| Err(Status::internal("Missing response message")))?;
+
+  while let Some(item) = stream.message().await {
+    item?;
+  }
+
+  Ok(message)
+}
+
+// Encode a request, send it over the transport and decode the response, which
+// is all each method of a generated client needs to do
+pub async fn unary<T, Req, Res>(
+  transport: &T,
+  path: &str,
+  req: &Req,
+  options: CallOptions
+) -> Result<Response<Res>, Status>
+where
+  T: Transport,
+  Req: Message,
+  Res: Message + Default + Send + 'static
+{
+  let body = Streaming::once(Ok(req.encode_to_vec()));
+  let response = transport
+    .call(path, body, options)
+    .await?;
+
+  Ok(Response {
+    metadata: response.metadata,
+    message: single(decode_all(response.message)).await?
+  })
+}
+
+pub async fn client_streaming<T, Req, Res>(
+  transport: &T,
+  path: &str,
+  req: Streaming<Req>,
+  options: CallOptions
+) -> Result<Response<Res>, Status>
+where
+  T: Transport,
+  Req: Message + Send + 'static,
+  Res: Message + Default + Send + 'static
+{
+  let response = transport
+    .call(path, encode_all(req), options)
+    .await?;
+
+  Ok(Response {
+    metadata: response.metadata,
+    message: single(decode_all(response.message)).await?
+  })
+}
+
+pub async fn server_streaming<T, Req, Res>(
+  transport: &T,
+  path: &str,
+  req: &Req,
+  options: CallOptions
+) -> Result<Response<Streaming<Res>>, Status>
+where
+  T: Transport,
+  Req: Message,
+  Res: Message + Default + Send + 'static
+{
+  let body = Streaming::once(Ok(req.encode_to_vec()));
+  let response = transport
+    .call(path, body, options)
+    .await?;
+
+  Ok(Response {
+    metadata: response.metadata,
+    message: decode_all(response.message)
+  })
+}
+
+pub async fn streaming<T, Req, Res>(
+  transport: &T,
+  path: &str,
+  req: Streaming<Req>,
+  options: CallOptions
+) -> Result<Response<Streaming<Res>>, Status>
+where
+  T: Transport,
+  Req: Message + Send + 'static,
+  Res: Message + Default + Send + 'static
+{
+  let response = transport
+    .call(path, encode_all(req), options)
+    .await?;
+
+  Ok(Response {
+    metadata: response.metadata,
+    message: decode_all(response.message)
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::runtime::block_on;
+  use crate::runtime::encoding::{self, DecodeError, WireType};
+
+  #[derive(Debug, Default, PartialEq)]
+  struct Greeting {
+    name: String
+  }
+
+  impl Message for Greeting {
+    fn encode(&self, buf: &mut Vec<u8>) {
+      encoding::string::encode(1, &self.name, buf);
+    }
+
+    fn merge_field(
+      &mut self,
+      number: u32,
+      wire: WireType,
+      buf: &mut &[u8]
+    ) -> Result<(), DecodeError> {
+      match number {
+        1 => encoding::string::merge(wire, &mut self.name, buf),
+        _ => encoding::skip_field(wire, buf)
+      }
+    }
+  }
+
+  // Transport that replies with whatever it was sent
+  struct Echo;
+
+  impl Transport for Echo {
+    async fn call(
+      &self,
+      path: &str,
+      body: Streaming<Vec<u8>>,
+      options: CallOptions
+    ) -> Result<Response<Streaming<Vec<u8>>>, Status> {
+      match path {
+        "/Greeter/Echo" => Ok(Response {
+          metadata: options.metadata,
+          message: body
+        }),
+        _ => Err(Status::unimplemented(path))
+      }
+    }
+  }
+
+  #[test]
+  fn unary_round_trip() -> Result<(), Status> {
+    let req = Greeting {
+      name: "foo".to_string()
+    };
+    let options = CallOptions::default().metadata("key", "value");
+    let response: Response<Greeting> =
+      block_on(unary(&Echo, "/Greeter/Echo", &req, options))?;
+
+    assert_eq!(response.message, req);
+    assert_eq!(response.metadata.get("key"), Some(&"value".to_string()));
+
+    Ok(())
+  }
+
+  #[test]
+  fn unary_status() {
+    let result: Result<Response<Greeting>, Status> = block_on(unary(
+      &Echo,
+      "/Greeter/Missing",
+      &Greeting::default(),
+      CallOptions::default()
+    ));
+
+    assert_eq!(result, Err(Status::unimplemented("/Greeter/Missing")));
+  }
+
+  #[test]
+  fn streaming_round_trip() -> Result<(), Status> {
+    let req = Streaming::iter(["foo", "bar"].map(|v| {
+      Ok(Greeting {
+        name: v.to_string()
+      })
+    }));
+    let response: Response<Streaming<Greeting>> = block_on(streaming(
+      &Echo,
+      "/Greeter/Echo",
+      req,
+      CallOptions::default()
+    ))?;
+    let result = block_on(response.message.collect())?;
+
+    assert_eq!(result.len(), 2);
+    assert_eq!(result[1].name, "bar");
+
+    Ok(())
+  }
+}
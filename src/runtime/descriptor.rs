@@ -0,0 +1,233 @@
+use super::encoding::{self, DecodeError, Message, WireType};
+
+// Types of "google/protobuf/descriptor.proto" that describe a parsed file,
+// limited to the fields that pbrs reads or writes. Any other field, such as
+// options and source info, is skipped when decoding
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FileDescriptorSet {
+  pub file: Vec<FileDescriptorProto>
+}
+
+impl Message for FileDescriptorSet {
+  fn encode(&self, buf: &mut Vec<u8>) {
+    encoding::message::encode_repeated(1, &self.file, buf);
+  }
+
+  fn merge_field(
+    &mut self,
+    number: u32,
+    wire: WireType,
+    buf: &mut &[u8]
+  ) -> Result<(), DecodeError> {
+    match number {
+      1 => encoding::message::merge_repeated(wire, &mut self.file, buf),
+      _ => encoding::skip_field(wire, buf)
+    }
+  }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FileDescriptorProto {
+  pub name: String,
+  pub package: String,
+  pub message_type: Vec<DescriptorProto>,
+  pub service: Vec<ServiceDescriptorProto>,
+  pub syntax: String
+}
+
+impl Message for FileDescriptorProto {
+  fn encode(&self, buf: &mut Vec<u8>) {
+    encoding::string::encode(1, &self.name, buf);
+    encoding::string::encode(2, &self.package, buf);
+    encoding::message::encode_repeated(4, &self.message_type, buf);
+    encoding::message::encode_repeated(6, &self.service, buf);
+    encoding::string::encode(12, &self.syntax, buf);
+  }
+
+  fn merge_field(
+    &mut self,
+    number: u32,
+    wire: WireType,
+    buf: &mut &[u8]
+  ) -> Result<(), DecodeError> {
+    match number {
+      1 => encoding::string::merge(wire, &mut self.name, buf),
+      2 => encoding::string::merge(wire, &mut self.package, buf),
+      4 => encoding::message::merge_repeated(wire, &mut self.message_type, buf),
+      6 => encoding::message::merge_repeated(wire, &mut self.service, buf),
+      12 => encoding::string::merge(wire, &mut self.syntax, buf),
+      _ => encoding::skip_field(wire, buf)
+    }
+  }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DescriptorProto {
+  pub name: String,
+  pub field: Vec<FieldDescriptorProto>,
+  pub nested_type: Vec<DescriptorProto>
+}
+
+impl Message for DescriptorProto {
+  fn encode(&self, buf: &mut Vec<u8>) {
+    encoding::string::encode(1, &self.name, buf);
+    encoding::message::encode_repeated(2, &self.field, buf);
+    encoding::message::encode_repeated(3, &self.nested_type, buf);
+  }
+
+  fn merge_field(
+    &mut self,
+    number: u32,
+    wire: WireType,
+    buf: &mut &[u8]
+  ) -> Result<(), DecodeError> {
+    match number {
+      1 => encoding::string::merge(wire, &mut self.name, buf),
+      2 => encoding::message::merge_repeated(wire, &mut self.field, buf),
+      3 => encoding::message::merge_repeated(wire, &mut self.nested_type, buf),
+      _ => encoding::skip_field(wire, buf)
+    }
+  }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FieldDescriptorProto {
+  pub name: String,
+  pub number: i32,
+  pub label: i32,
+  pub r#type: i32,
+  // Fully-qualified name of a message type, as in ".pkg.Msg"
+  pub type_name: String,
+  pub json_name: String
+}
+
+impl FieldDescriptorProto {
+  pub const LABEL_OPTIONAL: i32 = 1;
+  pub const TYPE_BOOL: i32 = 8;
+  pub const TYPE_BYTES: i32 = 12;
+  pub const TYPE_INT32: i32 = 5;
+  pub const TYPE_INT64: i32 = 3;
+  pub const TYPE_MESSAGE: i32 = 11;
+  pub const TYPE_STRING: i32 = 9;
+}
+
+impl Message for FieldDescriptorProto {
+  fn encode(&self, buf: &mut Vec<u8>) {
+    encoding::string::encode(1, &self.name, buf);
+    encoding::int32::encode(3, &self.number, buf);
+    encoding::int32::encode(4, &self.label, buf);
+    encoding::int32::encode(5, &self.r#type, buf);
+    encoding::string::encode(6, &self.type_name, buf);
+    encoding::string::encode(10, &self.json_name, buf);
+  }
+
+  fn merge_field(
+    &mut self,
+    number: u32,
+    wire: WireType,
+    buf: &mut &[u8]
+  ) -> Result<(), DecodeError> {
+    match number {
+      1 => encoding::string::merge(wire, &mut self.name, buf),
+      3 => encoding::int32::merge(wire, &mut self.number, buf),
+      4 => encoding::int32::merge(wire, &mut self.label, buf),
+      5 => encoding::int32::merge(wire, &mut self.r#type, buf),
+      6 => encoding::string::merge(wire, &mut self.type_name, buf),
+      10 => encoding::string::merge(wire, &mut self.json_name, buf),
+      _ => encoding::skip_field(wire, buf)
+    }
+  }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ServiceDescriptorProto {
+  pub name: String,
+  pub method: Vec<MethodDescriptorProto>
+}
+
+impl Message for ServiceDescriptorProto {
+  fn encode(&self, buf: &mut Vec<u8>) {
+    encoding::string::encode(1, &self.name, buf);
+    encoding::message::encode_repeated(2, &self.method, buf);
+  }
+
+  fn merge_field(
+    &mut self,
+    number: u32,
+    wire: WireType,
+    buf: &mut &[u8]
+  ) -> Result<(), DecodeError> {
+    match number {
+      1 => encoding::string::merge(wire, &mut self.name, buf),
+      2 => encoding::message::merge_repeated(wire, &mut self.method, buf),
+      _ => encoding::skip_field(wire, buf)
+    }
+  }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MethodDescriptorProto {
+  pub name: String,
+  pub input_type: String,
+  pub output_type: String,
+  pub client_streaming: bool,
+  pub server_streaming: bool
+}
+
+impl Message for MethodDescriptorProto {
+  fn encode(&self, buf: &mut Vec<u8>) {
+    encoding::string::encode(1, &self.name, buf);
+    encoding::string::encode(2, &self.input_type, buf);
+    encoding::string::encode(3, &self.output_type, buf);
+    encoding::bool::encode(5, &self.client_streaming, buf);
+    encoding::bool::encode(6, &self.server_streaming, buf);
+  }
+
+  fn merge_field(
+    &mut self,
+    number: u32,
+    wire: WireType,
+    buf: &mut &[u8]
+  ) -> Result<(), DecodeError> {
+    match number {
+      1 => encoding::string::merge(wire, &mut self.name, buf),
+      2 => encoding::string::merge(wire, &mut self.input_type, buf),
+      3 => encoding::string::merge(wire, &mut self.output_type, buf),
+      5 => encoding::bool::merge(wire, &mut self.client_streaming, buf),
+      6 => encoding::bool::merge(wire, &mut self.server_streaming, buf),
+      _ => encoding::skip_field(wire, buf)
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn descriptor_round_trip() -> Result<(), DecodeError> {
+    let set = FileDescriptorSet {
+      file: vec![FileDescriptorProto {
+        name: "foo.proto".to_string(),
+        message_type: vec![DescriptorProto {
+          name: "Foo".to_string(),
+          field: vec![FieldDescriptorProto {
+            name: "bar".to_string(),
+            number: 1,
+            label: FieldDescriptorProto::LABEL_OPTIONAL,
+            r#type: FieldDescriptorProto::TYPE_INT32,
+            json_name: "bar".to_string(),
+            ..Default::default()
+          }],
+          ..Default::default()
+        }],
+        syntax: "proto3".to_string(),
+        ..Default::default()
+      }]
+    };
+
+    assert_eq!(FileDescriptorSet::decode(&set.encode_to_vec())?, set);
+
+    Ok(())
+  }
+}
@@ -0,0 +1,784 @@
+use super::reflect::MessageDescriptor;
+use std::cell::Cell;
+use std::error::Error;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+// Wire types that prefix every encoded field along with its number
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WireType {
+  Varint = 0,
+  Fixed64 = 1,
+  LengthDelimited = 2,
+  StartGroup = 3,
+  EndGroup = 4,
+  Fixed32 = 5
+}
+
+impl TryFrom<u64> for WireType {
+  type Error = DecodeError;
+
+  fn try_from(value: u64) -> Result<Self, Self::Error> {
+    match value {
+      0 => Ok(WireType::Varint),
+      1 => Ok(WireType::Fixed64),
+      2 => Ok(WireType::LengthDelimited),
+      3 => Ok(WireType::StartGroup),
+      4 => Ok(WireType::EndGroup),
+      5 => Ok(WireType::Fixed32),
+      _ => Err(DecodeError::new(format!("Invalid wire type {}", value)))
+    }
+  }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecodeError(String);
+
+impl DecodeError {
+  pub fn new(message: impl Into<String>) -> Self {
+    Self(message.into())
+  }
+}
+
+impl Display for DecodeError {
+  fn fmt(&self, f: &mut Formatter) -> FmtResult {
+    write!(f, "Failed to decode message: {}", self.0)
+  }
+}
+
+impl Error for DecodeError {}
+
+// Any message that can be written to and read from the Protobuf wire format,
+// which generated messages implement field by field
+pub trait Message {
+  fn encode(&self, buf: &mut Vec<u8>);
+
+  fn merge_field(
+    &mut self,
+    number: u32,
+    wire: WireType,
+    buf: &mut &[u8]
+  ) -> Result<(), DecodeError>;
+
+  fn encode_to_vec(&self) -> Vec<u8> {
+    let mut buf = Vec::new();
+
+    self.encode(&mut buf);
+
+    buf
+  }
+
+  fn merge(&mut self, mut buf: &[u8]) -> Result<(), DecodeError> {
+    while !buf.is_empty() {
+      let (number, wire) = decode_key(&mut buf)?;
+
+      self.merge_field(number, wire, &mut buf)?;
+    }
+
+    Ok(())
+  }
+
+  fn decode(buf: &[u8]) -> Result<Self, DecodeError>
+  where
+    Self: Default
+  {
+    let mut message = Self::default();
+
+    message.merge(buf)?;
+
+    Ok(message)
+  }
+
+  // Messages written by hand describe themselves as having no fields unless
+  // they say otherwise
+  fn descriptor() -> &'static MessageDescriptor
+  where
+    Self: Sized
+  {
+    &MessageDescriptor::EMPTY
+  }
+}
+
+// Boxed messages encode exactly as the message they contain, which lets
+// recursive fields use the same helpers as any other message field
+impl<M: Message> Message for Box<M> {
+  fn encode(&self, buf: &mut Vec<u8>) {
+    (**self).encode(buf)
+  }
+
+  fn merge_field(
+    &mut self,
+    number: u32,
+    wire: WireType,
+    buf: &mut &[u8]
+  ) -> Result<(), DecodeError> {
+    (**self).merge_field(number, wire, buf)
+  }
+
+  fn descriptor() -> &'static MessageDescriptor {
+    M::descriptor()
+  }
+}
+
+pub fn encode_varint(mut value: u64, buf: &mut Vec<u8>) {
+  while value >= 0x80 {
+    buf.push((value as u8) | 0x80);
+    value >>= 7;
+  }
+
+  buf.push(value as u8);
+}
+
+pub fn decode_varint(buf: &mut &[u8]) -> Result<u64, DecodeError> {
+  let mut value = 0;
+
+  for i in 0..10 {
+    let (byte, rest) = buf
+      .split_first()
+      .ok_or_else(|| DecodeError::new("Unexpected end of varint"))?;
+
+    *buf = rest;
+    value |= ((byte & 0x7f) as u64) << (i * 7);
+
+    if byte & 0x80 == 0 {
+      return Ok(value);
+    }
+  }
+
+  Err(DecodeError::new("Varint is longer than 10 bytes"))
+}
+
+pub fn encode_key(number: u32, wire: WireType, buf: &mut Vec<u8>) {
+  encode_varint(((number as u64) << 3) | wire as u64, buf);
+}
+
+pub fn decode_key(buf: &mut &[u8]) -> Result<(u32, WireType), DecodeError> {
+  let key = decode_varint(buf)?;
+  let number = (key >> 3) as u32;
+
+  if number == 0 {
+    return Err(DecodeError::new("Field number 0 is invalid"));
+  }
+
+  Ok((number, WireType::try_from(key & 0x7)?))
+}
+
+pub fn encode_bytes(number: u32, value: &[u8], buf: &mut Vec<u8>) {
+  encode_key(number, WireType::LengthDelimited, buf);
+  encode_varint(value.len() as u64, buf);
+  buf.extend_from_slice(value);
+}
+
+pub fn decode_bytes<'a>(buf: &mut &'a [u8]) -> Result<&'a [u8], DecodeError> {
+  let len = decode_varint(buf)? as usize;
+
+  if len > buf.len() {
+    return Err(DecodeError::new("Length exceeds remaining bytes"));
+  }
+
+  let (value, rest) = buf.split_at(len);
+
+  *buf = rest;
+
+  Ok(value)
+}
+
+pub fn check_wire(
+  expected: WireType,
+  actual: WireType
+) -> Result<(), DecodeError> {
+  match expected == actual {
+    true => Ok(()),
+    false => Err(DecodeError::new(format!(
+      "Expected wire type {:?} but found {:?}",
+      expected, actual
+    )))
+  }
+}
+
+// Levels that messages and groups may be nested to while decoding, past
+// which they're rejected rather than overflowing the stack
+pub const RECURSION_LIMIT: usize = 100;
+
+thread_local! {
+  static DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+// Levels left as they were once a nested decode returns, even by unwinding
+struct DepthGuard(usize);
+
+impl Drop for DepthGuard {
+  fn drop(&mut self) {
+    DEPTH.set(self.0);
+  }
+}
+
+// Decode whatever is nested one level further in. Messages can't pass a
+// context to their fields' merges, so the depth is kept for each thread
+pub fn nested<T>(
+  decode: impl FnOnce() -> Result<T, DecodeError>
+) -> Result<T, DecodeError> {
+  let depth = DEPTH.get();
+
+  if depth >= RECURSION_LIMIT {
+    return Err(DecodeError::new("Recursion limit exceeded"));
+  }
+
+  let _guard = DepthGuard(depth);
+
+  DEPTH.set(depth + 1);
+  decode()
+}
+
+// Skip over a field that the message doesn't know about, which is how newer
+// fields are tolerated by older readers
+pub fn skip_field(wire: WireType, buf: &mut &[u8]) -> Result<(), DecodeError> {
+  let len = match wire {
+    WireType::Varint => return decode_varint(buf).map(|_| ()),
+    WireType::Fixed64 => 8,
+    WireType::Fixed32 => 4,
+    WireType::LengthDelimited => return decode_bytes(buf).map(|_| ()),
+    WireType::StartGroup => {
+      return nested(|| loop {
+        let (_, wire) = decode_key(buf)?;
+
+        match wire {
+          WireType::EndGroup => return Ok(()),
+          _ => skip_field(wire, buf)?
+        }
+      })
+    }
+    WireType::EndGroup => return Err(DecodeError::new("Unexpected end group"))
+  };
+
+  if len > buf.len() {
+    return Err(DecodeError::new("Unexpected end of field"));
+  }
+
+  *buf = &buf[len..];
+
+  Ok(())
+}
+
+// Proto3 scalars are only written when they differ from their default, so
+// each scalar type has a module of helpers that generated messages call
+pub mod bool {
+  use super::*;
+
+  pub fn encode(number: u32, value: &bool, buf: &mut Vec<u8>) {
+    if *value {
+      encode_key(number, WireType::Varint, buf);
+      encode_varint(1, buf);
+    }
+  }
+
+  pub fn merge(
+    wire: WireType,
+    value: &mut bool,
+    buf: &mut &[u8]
+  ) -> Result<(), DecodeError> {
+    check_wire(WireType::Varint, wire)?;
+
+    *value = decode_varint(buf)? != 0;
+
+    Ok(())
+  }
+}
+
+pub mod int32 {
+  use super::*;
+
+  pub fn encode(number: u32, value: &i32, buf: &mut Vec<u8>) {
+    if *value != 0 {
+      encode_key(number, WireType::Varint, buf);
+      // Negative numbers are sign extended to ten bytes
+      encode_varint(*value as i64 as u64, buf);
+    }
+  }
+
+  pub fn merge(
+    wire: WireType,
+    value: &mut i32,
+    buf: &mut &[u8]
+  ) -> Result<(), DecodeError> {
+    check_wire(WireType::Varint, wire)?;
+
+    *value = decode_varint(buf)? as i32;
+
+    Ok(())
+  }
+}
+
+pub mod int64 {
+  use super::*;
+
+  pub fn encode(number: u32, value: &i64, buf: &mut Vec<u8>) {
+    if *value != 0 {
+      encode_key(number, WireType::Varint, buf);
+      encode_varint(*value as u64, buf);
+    }
+  }
+
+  pub fn merge(
+    wire: WireType,
+    value: &mut i64,
+    buf: &mut &[u8]
+  ) -> Result<(), DecodeError> {
+    check_wire(WireType::Varint, wire)?;
+
+    *value = decode_varint(buf)? as i64;
+
+    Ok(())
+  }
+}
+
+pub mod uint32 {
+  use super::*;
+
+  pub fn encode(number: u32, value: &u32, buf: &mut Vec<u8>) {
+    if *value != 0 {
+      encode_key(number, WireType::Varint, buf);
+      encode_varint(*value as u64, buf);
+    }
+  }
+
+  pub fn merge(
+    wire: WireType,
+    value: &mut u32,
+    buf: &mut &[u8]
+  ) -> Result<(), DecodeError> {
+    check_wire(WireType::Varint, wire)?;
+
+    *value = decode_varint(buf)? as u32;
+
+    Ok(())
+  }
+}
+
+pub mod uint64 {
+  use super::*;
+
+  pub fn encode(number: u32, value: &u64, buf: &mut Vec<u8>) {
+    if *value != 0 {
+      encode_key(number, WireType::Varint, buf);
+      encode_varint(*value, buf);
+    }
+  }
+
+  pub fn merge(
+    wire: WireType,
+    value: &mut u64,
+    buf: &mut &[u8]
+  ) -> Result<(), DecodeError> {
+    check_wire(WireType::Varint, wire)?;
+
+    *value = decode_varint(buf)?;
+
+    Ok(())
+  }
+}
+
+// Floating point numbers are written as their little-endian bits, and are
+// skipped when they're positive zero
+pub mod float {
+  use super::*;
+
+  pub fn encode(number: u32, value: &f32, buf: &mut Vec<u8>) {
+    if value.to_bits() != 0 {
+      encode_key(number, WireType::Fixed32, buf);
+      buf.extend(value.to_le_bytes());
+    }
+  }
+
+  pub fn merge(
+    wire: WireType,
+    value: &mut f32,
+    buf: &mut &[u8]
+  ) -> Result<(), DecodeError> {
+    check_wire(WireType::Fixed32, wire)?;
+
+    let bytes = buf
+      .get(..4)
+      .ok_or_else(|| DecodeError::new("Unexpected end of float"))?;
+    let mut value_bytes = [0; 4];
+
+    value_bytes.copy_from_slice(bytes);
+    *value = f32::from_le_bytes(value_bytes);
+    *buf = &buf[4..];
+
+    Ok(())
+  }
+}
+
+pub mod double {
+  use super::*;
+
+  pub fn encode(number: u32, value: &f64, buf: &mut Vec<u8>) {
+    if value.to_bits() != 0 {
+      encode_key(number, WireType::Fixed64, buf);
+      buf.extend(value.to_le_bytes());
+    }
+  }
+
+  pub fn merge(
+    wire: WireType,
+    value: &mut f64,
+    buf: &mut &[u8]
+  ) -> Result<(), DecodeError> {
+    check_wire(WireType::Fixed64, wire)?;
+
+    let bytes = buf
+      .get(..8)
+      .ok_or_else(|| DecodeError::new("Unexpected end of double"))?;
+    let mut value_bytes = [0; 8];
+
+    value_bytes.copy_from_slice(bytes);
+    *value = f64::from_le_bytes(value_bytes);
+    *buf = &buf[8..];
+
+    Ok(())
+  }
+}
+
+pub mod string {
+  use super::*;
+
+  pub fn encode(number: u32, value: &str, buf: &mut Vec<u8>) {
+    if !value.is_empty() {
+      encode_bytes(number, value.as_bytes(), buf);
+    }
+  }
+
+  pub fn merge(
+    wire: WireType,
+    value: &mut String,
+    buf: &mut &[u8]
+  ) -> Result<(), DecodeError> {
+    check_wire(WireType::LengthDelimited, wire)?;
+
+    *value = String::from_utf8(decode_bytes(buf)?.to_vec())
+      .map_err(|_| DecodeError::new("String is not valid UTF-8"))?;
+
+    Ok(())
+  }
+
+  // Empty strings are still written when they're part of a list, since each
+  // one is an element of it
+  pub fn encode_repeated(number: u32, values: &[String], buf: &mut Vec<u8>) {
+    for value in values {
+      encode_bytes(number, value.as_bytes(), buf);
+    }
+  }
+
+  pub fn merge_repeated(
+    wire: WireType,
+    values: &mut Vec<String>,
+    buf: &mut &[u8]
+  ) -> Result<(), DecodeError> {
+    let mut value = String::new();
+
+    merge(wire, &mut value, buf)?;
+    values.push(value);
+
+    Ok(())
+  }
+}
+
+pub mod bytes {
+  use super::*;
+
+  pub fn encode(number: u32, value: &[u8], buf: &mut Vec<u8>) {
+    if !value.is_empty() {
+      encode_bytes(number, value, buf);
+    }
+  }
+
+  pub fn merge(
+    wire: WireType,
+    value: &mut Vec<u8>,
+    buf: &mut &[u8]
+  ) -> Result<(), DecodeError> {
+    check_wire(WireType::LengthDelimited, wire)?;
+
+    *value = decode_bytes(buf)?.to_vec();
+
+    Ok(())
+  }
+}
+
+pub mod message {
+  use super::*;
+
+  pub fn encode<M: Message>(number: u32, value: &M, buf: &mut Vec<u8>) {
+    encode_bytes(number, &value.encode_to_vec(), buf);
+  }
+
+  // Repeated occurrences of a message field are merged into one another, as
+  // the Protobuf specification requires
+  pub fn merge<M: Message>(
+    wire: WireType,
+    value: &mut M,
+    buf: &mut &[u8]
+  ) -> Result<(), DecodeError> {
+    check_wire(WireType::LengthDelimited, wire)?;
+
+    let bytes = decode_bytes(buf)?;
+
+    nested(|| value.merge(bytes))
+  }
+
+  pub fn encode_repeated<M: Message>(
+    number: u32,
+    values: &[M],
+    buf: &mut Vec<u8>
+  ) {
+    for value in values {
+      encode(number, value, buf);
+    }
+  }
+
+  // Each occurrence of a repeated message field is a message of its own
+  pub fn merge_repeated<M: Message + Default>(
+    wire: WireType,
+    values: &mut Vec<M>,
+    buf: &mut &[u8]
+  ) -> Result<(), DecodeError> {
+    let mut value = M::default();
+
+    merge(wire, &mut value, buf)?;
+    values.push(value);
+
+    Ok(())
+  }
+}
+
+// Fields generated as a type of their own that converts to and from the
+// message they're encoded as, such as times generated as types of chrono
+pub mod converted {
+  use super::*;
+  use std::fmt::Display;
+
+  pub fn encode<M: Message + From<T>, T: Clone>(
+    number: u32,
+    value: &T,
+    buf: &mut Vec<u8>
+  ) {
+    message::encode(number, &M::from(value.clone()), buf);
+  }
+
+  // Occurrences of the field are merged as messages, and only converted
+  // back once they have been, failing if the result is out of range
+  pub fn merge<M, T>(
+    wire: WireType,
+    value: &mut Option<T>,
+    buf: &mut &[u8]
+  ) -> Result<(), DecodeError>
+  where
+    M: Message + Default + From<T> + TryInto<T>,
+    <M as TryInto<T>>::Error: Display
+  {
+    let mut message = value
+      .take()
+      .map(M::from)
+      .unwrap_or_default();
+
+    message::merge(wire, &mut message, buf)?;
+    *value = Some(
+      message
+        .try_into()
+        .map_err(|err| DecodeError::new(err.to_string()))?
+    );
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn varint_round_trip() -> Result<(), DecodeError> {
+    let mut buf = Vec::new();
+
+    encode_varint(300, &mut buf);
+
+    assert_eq!(buf, vec![0xac, 0x02]);
+    assert_eq!(decode_varint(&mut buf.as_slice())?, 300);
+
+    Ok(())
+  }
+
+  #[test]
+  fn fixed_round_trip() -> Result<(), DecodeError> {
+    let mut buf = Vec::new();
+    let (mut float, mut double) = (0.0, 0.0);
+
+    float::encode(1, &1.5, &mut buf);
+    double::encode(2, &-2.25, &mut buf);
+
+    let mut input = buf.as_slice();
+
+    float::merge(decode_key(&mut input)?.1, &mut float, &mut input)?;
+    double::merge(decode_key(&mut input)?.1, &mut double, &mut input)?;
+
+    assert_eq!((float, double, input.len()), (1.5, -2.25, 0));
+
+    Ok(())
+  }
+
+  #[test]
+  fn negative_int32() -> Result<(), DecodeError> {
+    let mut buf = Vec::new();
+    let mut value = 0;
+
+    int32::encode(1, &-1, &mut buf);
+
+    assert_eq!(buf.len(), 11);
+
+    let mut input = buf.as_slice();
+    let (number, wire) = decode_key(&mut input)?;
+
+    int32::merge(wire, &mut value, &mut input)?;
+
+    assert_eq!((number, value), (1, -1));
+
+    Ok(())
+  }
+
+  #[test]
+  fn skip_unknown_fields() -> Result<(), DecodeError> {
+    let mut buf = Vec::new();
+
+    string::encode(4, "foo", &mut buf);
+    int32::encode(5, &7, &mut buf);
+
+    let mut input = buf.as_slice();
+
+    while !input.is_empty() {
+      let (_, wire) = decode_key(&mut input)?;
+
+      skip_field(wire, &mut input)?;
+    }
+
+    Ok(())
+  }
+
+  #[derive(Default)]
+  struct Node {
+    next: Option<Box<Node>>
+  }
+
+  impl Message for Node {
+    fn encode(&self, buf: &mut Vec<u8>) {
+      if let Some(next) = &self.next {
+        message::encode(1, next, buf);
+      }
+    }
+
+    fn merge_field(
+      &mut self,
+      number: u32,
+      wire: WireType,
+      buf: &mut &[u8]
+    ) -> Result<(), DecodeError> {
+      match number {
+        1 => message::merge(
+          wire,
+          self
+            .next
+            .get_or_insert_with(Default::default),
+          buf
+        ),
+        _ => skip_field(wire, buf)
+      }
+    }
+  }
+
+  // Bytes of messages nested in field 1 of one another
+  fn nest(depth: usize) -> Vec<u8> {
+    (0..depth).fold(Vec::new(), |acc, _| {
+      let mut buf = Vec::new();
+
+      encode_bytes(1, &acc, &mut buf);
+
+      buf
+    })
+  }
+
+  #[test]
+  fn limit_recursion() -> Result<(), DecodeError> {
+    let mut groups = vec![0x1b; 100_000];
+
+    groups.extend(vec![0x1c; 100_000]);
+
+    assert!(Node::decode(&nest(RECURSION_LIMIT))?
+      .next
+      .is_some());
+    assert_eq!(
+      Node::decode(&nest(RECURSION_LIMIT + 1)).err(),
+      Some(DecodeError::new("Recursion limit exceeded"))
+    );
+    assert!(Node::decode(&groups).is_err());
+
+    // Failing part way down leaves the depth as it was for the next decode
+    assert!(Node::decode(&nest(RECURSION_LIMIT)).is_ok());
+
+    Ok(())
+  }
+
+  #[test]
+  fn convert_fields() -> Result<(), DecodeError> {
+    use crate::runtime::well_known::Duration;
+    use std::time::Duration as StdDuration;
+
+    let mut buf = Vec::new();
+    let mut value = None;
+
+    converted::encode::<Duration, _>(
+      1,
+      &StdDuration::from_millis(1500),
+      &mut buf
+    );
+    message::encode(1, &Duration::new(-5, 0), &mut buf);
+
+    let mut input = buf.as_slice();
+    let (_, wire) = decode_key(&mut input)?;
+
+    converted::merge::<Duration, _>(wire, &mut value, &mut input)?;
+
+    assert_eq!(value, Some(StdDuration::from_millis(1500)));
+
+    let (_, wire) = decode_key(&mut input)?;
+
+    // Negative durations can't be held by those of std
+    assert!(
+      converted::merge::<Duration, _>(wire, &mut value, &mut input).is_err()
+    );
+
+    Ok(())
+  }
+
+  #[cfg(feature = "chrono")]
+  #[test]
+  fn convert_out_of_range_fields() -> Result<(), DecodeError> {
+    use crate::runtime::well_known::Timestamp;
+
+    let mut buf = Vec::new();
+    let mut value = None::<chrono::DateTime<chrono::Utc>>;
+    let time = Timestamp {
+      seconds: i64::MAX,
+      nanos: i32::MAX
+    };
+
+    message::encode(1, &time, &mut buf);
+
+    let mut input = buf.as_slice();
+    let (_, wire) = decode_key(&mut input)?;
+
+    assert!(
+      converted::merge::<Timestamp, _>(wire, &mut value, &mut input).is_err()
+    );
+
+    Ok(())
+  }
+}
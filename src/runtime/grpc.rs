@@ -0,0 +1,511 @@
+use super::client::{CallOptions, Transport};
+use super::server::Router;
+use super::status::{Code, Metadata, Request, Response, Status};
+use super::stream::{channel, Streaming};
+use bytes::Bytes;
+use h2::{client, server, RecvStream, SendStream};
+use http::{HeaderMap, HeaderValue};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::time::{timeout_at, Instant};
+
+const CONTENT_TYPE: &str = "application/grpc";
+
+// Largest message that's read from a frame, as in other gRPC
+// implementations, so a length prefix can't make a peer buffer without limit
+const MAX_MESSAGE_SIZE: usize = 4 * 1024 * 1024;
+
+// Headers that belong to the protocol itself rather than to the metadata of a
+// call
+fn reserved(name: &str) -> bool {
+  name.starts_with("grpc-") || name == "content-type" || name == "te"
+}
+
+fn metadata(headers: &HeaderMap) -> Metadata {
+  headers
+    .iter()
+    .filter(|(k, _)| !reserved(k.as_str()))
+    .filter_map(|(k, v)| Some((k.to_string(), v.to_str().ok()?.to_string())))
+    .collect()
+}
+
+fn insert_metadata(headers: &mut HeaderMap, metadata: &Metadata) {
+  for (k, v) in metadata {
+    if let (Ok(k), Ok(v)) =
+      (k.parse::<http::HeaderName>(), HeaderValue::from_str(v))
+    {
+      headers.insert(k, v);
+    }
+  }
+}
+
+// Messages of a status are percent-encoded, since headers only allow visible
+// ASCII characters
+fn encode_message(message: &str) -> String {
+  message
+    .bytes()
+    .map(|v| match v {
+      b' '..=b'~' if v != b'%' => (v as char).to_string(),
+      _ => format!("%{:02X}", v)
+    })
+    .collect()
+}
+
+fn decode_message(message: &str) -> String {
+  let bytes = message.as_bytes();
+  let mut result = Vec::new();
+  let mut i = 0;
+
+  while i < bytes.len() {
+    let hex = bytes
+      .get(i + 1..i + 3)
+      .and_then(|v| std::str::from_utf8(v).ok())
+      .and_then(|v| u8::from_str_radix(v, 16).ok());
+
+    match (bytes[i], hex) {
+      (b'%', Some(v)) => {
+        result.push(v);
+        i += 3;
+      }
+      (v, _) => {
+        result.push(v);
+        i += 1;
+      }
+    }
+  }
+
+  String::from_utf8_lossy(&result).to_string()
+}
+
+fn status_headers(status: &Status) -> HeaderMap {
+  let mut headers = HeaderMap::new();
+
+  headers.insert("grpc-status", HeaderValue::from(status.code as i32));
+
+  if let Ok(v) = HeaderValue::from_str(&encode_message(&status.message)) {
+    headers.insert("grpc-message", v);
+  }
+
+  headers
+}
+
+// Status carried by the trailers of a response, if there is one
+fn status(headers: &HeaderMap) -> Option<Status> {
+  let code = headers
+    .get("grpc-status")?
+    .to_str()
+    .ok()?
+    .parse::<i32>()
+    .ok()?;
+  let message = headers
+    .get("grpc-message")
+    .and_then(|v| v.to_str().ok())
+    .map(decode_message)
+    .unwrap_or_default();
+
+  Some(Status::new(Code::from(code), message))
+}
+
+// Deadlines are written as at most eight digits followed by a unit, such as
+// "100m" for a hundred milliseconds
+fn encode_timeout(timeout: Duration) -> String {
+  match timeout.as_millis() {
+    v if v < 100_000_000 => format!("{}m", v.max(1)),
+    _ => format!("{}S", timeout.as_secs().min(99_999_999))
+  }
+}
+
+fn decode_timeout(value: &str) -> Option<Duration> {
+  let (digits, unit) = value.split_at(value.len().checked_sub(1)?);
+
+  // Deadlines come from the client, so anything beyond the eight digits the
+  // spec allows is rejected rather than overflowing
+  if digits.is_empty()
+    || digits.len() > 8
+    || !digits
+      .bytes()
+      .all(|v| v.is_ascii_digit())
+  {
+    return None;
+  }
+
+  let value = digits.parse::<u64>().ok()?;
+
+  match unit {
+    "H" => Some(Duration::from_secs(value.checked_mul(60 * 60)?)),
+    "M" => Some(Duration::from_secs(value.checked_mul(60)?)),
+    "S" => Some(Duration::from_secs(value)),
+    "m" => Some(Duration::from_millis(value)),
+    "u" => Some(Duration::from_micros(value)),
+    "n" => Some(Duration::from_nanos(value)),
+    _ => None
+  }
+}
+
+// Every message is prefixed with a compression flag and its length as a
+// big-endian 32-bit integer
+fn encode_frame(message: &[u8]) -> Bytes {
+  let mut frame = Vec::with_capacity(message.len() + 5);
+
+  frame.push(0);
+  frame.extend_from_slice(&(message.len() as u32).to_be_bytes());
+  frame.extend_from_slice(message);
+
+  Bytes::from(frame)
+}
+
+// Take the first complete frame off the buffer, leaving any partial frame for
+// the next chunk of data
+fn decode_frame(buf: &mut Vec<u8>) -> Option<Result<Vec<u8>, Status>> {
+  let len = u32::from_be_bytes(buf.get(1..5)?.try_into().ok()?) as usize;
+
+  if len > MAX_MESSAGE_SIZE {
+    return Some(Err(Status::resource_exhausted(format!(
+      "Message of {} bytes is larger than {}",
+      len, MAX_MESSAGE_SIZE
+    ))));
+  }
+
+  if buf.len() < len + 5 {
+    return None;
+  }
+
+  let frame = buf
+    .drain(..len + 5)
+    .collect::<Vec<u8>>();
+
+  match frame[0] {
+    0 => Some(Ok(frame[5..].to_vec())),
+    _ => Some(Err(Status::unimplemented(
+      "Compressed messages aren't supported"
+    )))
+  }
+}
+
+// Read the frames of a body into a stream of messages as they arrive, ending
+// the stream with the status in the trailers for responses
+fn read_frames(mut body: RecvStream, trailers: bool) -> Streaming<Vec<u8>> {
+  let (sender, stream) = channel();
+
+  tokio::spawn(async move {
+    let mut buf = Vec::new();
+
+    while let Some(chunk) = body.data().await {
+      let chunk = match chunk {
+        Ok(v) => v,
+        Err(err) => return sender.send(Err(Status::internal(err.to_string())))
+      };
+      let _ = body
+        .flow_control()
+        .release_capacity(chunk.len());
+
+      buf.extend_from_slice(&chunk);
+
+      // Frames that can't be read leave the rest of the body unreadable too
+      while let Some(frame) = decode_frame(&mut buf) {
+        if let Err(status) = frame {
+          return sender.send(Err(status));
+        }
+
+        sender.send(frame);
+      }
+    }
+
+    if !buf.is_empty() {
+      return sender.send(Err(Status::internal("Incomplete message frame")));
+    }
+
+    if trailers {
+      let result = match body.trailers().await {
+        Ok(Some(v)) => status(&v),
+        Ok(None) => None,
+        Err(err) => Some(Status::internal(err.to_string()))
+      };
+
+      match result {
+        Some(v) if v.code == Code::Ok => (),
+        Some(v) => sender.send(Err(v)),
+        None => sender.send(Err(Status::internal("Missing grpc-status")))
+      }
+    }
+  });
+
+  stream
+}
+
+// Serve every service of a router over HTTP/2 to each connection accepted by
+// the listener, until accepting a connection fails
+pub async fn serve(
+  listener: TcpListener,
+  router: Router
+) -> std::io::Result<()> {
+  let router = Arc::new(router);
+
+  loop {
+    let (socket, _) = listener.accept().await?;
+    let router = router.clone();
+
+    tokio::spawn(async move {
+      let _ = connection(socket, router).await;
+    });
+  }
+}
+
+async fn connection(
+  socket: TcpStream,
+  router: Arc<Router>
+) -> Result<(), h2::Error> {
+  let mut connection = server::handshake(socket).await?;
+
+  while let Some(result) = connection.accept().await {
+    let (req, respond) = result?;
+    let router = router.clone();
+
+    tokio::spawn(async move {
+      let _ = respond_to(&router, req, respond).await;
+    });
+  }
+
+  Ok(())
+}
+
+async fn respond_to(
+  router: &Router,
+  req: http::Request<RecvStream>,
+  mut respond: server::SendResponse<Bytes>
+) -> Result<(), h2::Error> {
+  let (parts, body) = req.into_parts();
+  let deadline = parts
+    .headers
+    .get("grpc-timeout")
+    .and_then(|v| v.to_str().ok())
+    .and_then(decode_timeout)
+    .map(|v| Instant::now() + v);
+  let req = Request {
+    metadata: metadata(&parts.headers),
+    message: read_frames(body, false)
+  };
+  let expired = || Status::deadline_exceeded("Deadline exceeded");
+  let call = router.call(parts.uri.path(), req);
+  let result = match deadline {
+    Some(v) => timeout_at(v, call)
+      .await
+      .unwrap_or_else(|_| Err(expired())),
+    None => call.await
+  };
+  let mut head = http::Response::builder()
+    .status(200)
+    .header("content-type", CONTENT_TYPE)
+    .body(())
+    .expect("Valid response head");
+
+  // Calls that fail before responding send the status alone, without any
+  // body or trailers
+  let response = match result {
+    Ok(v) => v,
+    Err(err) => {
+      head
+        .headers_mut()
+        .extend(status_headers(&err));
+
+      return respond
+        .send_response(head, true)
+        .map(|_| ());
+    }
+  };
+
+  insert_metadata(head.headers_mut(), &response.metadata);
+
+  let mut send = respond.send_response(head, false)?;
+  let mut message = response.message;
+  let status = loop {
+    let item = match deadline {
+      Some(v) => timeout_at(v, message.message())
+        .await
+        .unwrap_or_else(|_| Some(Err(expired()))),
+      None => message.message().await
+    };
+
+    match item {
+      Some(Ok(v)) => send.send_data(encode_frame(&v), false)?,
+      Some(Err(err)) => break err,
+      None => break Status::new(Code::Ok, "")
+    }
+  };
+
+  send.send_trailers(status_headers(&status))
+}
+
+// Client side of HTTP/2 connection to a gRPC server, which generated clients
+// can send calls over like any other transport
+#[derive(Clone)]
+pub struct Channel {
+  addr: SocketAddr,
+  sender: client::SendRequest<Bytes>
+}
+
+impl Channel {
+  pub async fn connect(addr: SocketAddr) -> Result<Self, Status> {
+    let unavailable =
+      |err: &dyn std::error::Error| Status::unavailable(err.to_string());
+    let socket = TcpStream::connect(addr)
+      .await
+      .map_err(|err| unavailable(&err))?;
+    let (sender, connection) = client::handshake(socket)
+      .await
+      .map_err(|err| unavailable(&err))?;
+
+    tokio::spawn(async move {
+      let _ = connection.await;
+    });
+
+    Ok(Self {
+      addr,
+      sender
+    })
+  }
+}
+
+// Send every message of a request as its own frame, resetting the stream if
+// the request fails part of the way through
+async fn write_frames(
+  mut send: SendStream<Bytes>,
+  mut body: Streaming<Vec<u8>>
+) {
+  while let Some(item) = body.message().await {
+    match item {
+      Ok(v) => {
+        if send
+          .send_data(encode_frame(&v), false)
+          .is_err()
+        {
+          return;
+        }
+      }
+      Err(_) => return send.send_reset(h2::Reason::CANCEL)
+    }
+  }
+
+  let _ = send.send_data(Bytes::new(), true);
+}
+
+impl Transport for Channel {
+  async fn call(
+    &self,
+    path: &str,
+    body: Streaming<Vec<u8>>,
+    options: CallOptions
+  ) -> Result<Response<Streaming<Vec<u8>>>, Status> {
+    let unavailable = |err: h2::Error| Status::unavailable(err.to_string());
+    let mut req = http::Request::builder()
+      .method("POST")
+      .uri(format!("http://{}{}", self.addr, path))
+      .header("content-type", CONTENT_TYPE)
+      .header("te", "trailers")
+      .body(())
+      .map_err(|err| Status::internal(err.to_string()))?;
+
+    if let Some(v) = options.timeout {
+      req.headers_mut().insert(
+        "grpc-timeout",
+        HeaderValue::from_str(&encode_timeout(v)).expect("Valid timeout")
+      );
+    }
+
+    insert_metadata(req.headers_mut(), &options.metadata);
+
+    let mut sender = self
+      .sender
+      .clone()
+      .ready()
+      .await
+      .map_err(unavailable)?;
+    let (response, send) = sender
+      .send_request(req, false)
+      .map_err(unavailable)?;
+
+    tokio::spawn(write_frames(send, body));
+
+    let response = match options.timeout {
+      Some(v) => tokio::time::timeout(v, response)
+        .await
+        .map_err(|_| Status::deadline_exceeded("Deadline exceeded"))?,
+      None => response.await
+    }
+    .map_err(|err| match err.reason() {
+      Some(h2::Reason::CANCEL) => Status::cancelled(err.to_string()),
+      _ => Status::unavailable(err.to_string())
+    })?;
+    let (parts, body) = response.into_parts();
+
+    if parts.status != http::StatusCode::OK {
+      return Err(Status::new(
+        Code::Unknown,
+        format!("Unexpected HTTP status {}", parts.status)
+      ));
+    }
+
+    // Responses made up of trailers alone carry their status in the headers
+    match status(&parts.headers) {
+      Some(v) if v.code != Code::Ok => Err(v),
+      Some(_) => Ok(Response {
+        metadata: metadata(&parts.headers),
+        message: Streaming::empty()
+      }),
+      None => Ok(Response {
+        metadata: metadata(&parts.headers),
+        message: read_frames(body, true)
+      })
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn split_frames() {
+    let mut buf = [encode_frame(b"foo"), encode_frame(b"bar")].concat();
+
+    buf.truncate(buf.len() - 1);
+
+    assert_eq!(decode_frame(&mut buf), Some(Ok(b"foo".to_vec())));
+    assert_eq!(decode_frame(&mut buf), None);
+    assert_eq!(buf.len(), 7);
+  }
+
+  #[test]
+  fn timeout_round_trip() {
+    let timeout = Duration::from_millis(250);
+
+    assert_eq!(decode_timeout(&encode_timeout(timeout)), Some(timeout));
+    assert_eq!(decode_timeout("2S"), Some(Duration::from_secs(2)));
+    assert_eq!(decode_timeout("2"), None);
+    assert_eq!(
+      decode_timeout("99999999H"),
+      Some(Duration::from_secs(99_999_999 * 60 * 60))
+    );
+    assert_eq!(decode_timeout("999999999999999999H"), None);
+    assert_eq!(decode_timeout("+5S"), None);
+  }
+
+  #[test]
+  fn reject_large_frames() {
+    let mut buf = vec![0, 0xff, 0xff, 0xff, 0xff];
+
+    assert_eq!(
+      decode_frame(&mut buf).map(|v| v.map_err(|v| v.code)),
+      Some(Err(Code::ResourceExhausted))
+    );
+  }
+
+  #[test]
+  fn message_round_trip() {
+    let message = "100% done\nüber";
+
+    assert_eq!(decode_message(&encode_message(message)), message);
+  }
+}
@@ -0,0 +1,1015 @@
+use super::encoding::{Message, RECURSION_LIMIT};
+use super::reflect::{DynamicMessage, FieldType, MessageDescriptor, Value};
+use super::registry::{is_any, pack_any, TypeRegistry};
+use super::well_known::{
+  self,
+  BoolValue,
+  BytesValue,
+  DoubleValue,
+  Duration,
+  FieldMask,
+  FloatValue,
+  Int32Value,
+  Int64Value,
+  ListValue,
+  StringValue,
+  Struct,
+  Timestamp,
+  UInt32Value,
+  UInt64Value
+};
+use std::fmt::Display;
+use std::iter::Peekable;
+use std::str::{Chars, FromStr};
+
+#[derive(Clone, Debug, PartialEq)]
+enum Json {
+  Null,
+  Bool(bool),
+  Number(String),
+  String(String),
+  Array(Vec<Json>),
+  Object(Vec<(String, Json)>)
+}
+
+const BASE64: &[u8; 64] =
+  b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+// Standard base64 with padding, as bytes are written to JSON
+pub(crate) fn encode_base64(bytes: &[u8]) -> String {
+  let mut result = String::new();
+
+  for chunk in bytes.chunks(3) {
+    let group = chunk
+      .iter()
+      .enumerate()
+      .fold(0, |acc, (i, v)| acc | u32::from(*v) << (16 - i * 8));
+
+    for i in 0..4 {
+      match i <= chunk.len() {
+        true => {
+          result.push(BASE64[(group >> (18 - i * 6) & 63) as usize] as char)
+        }
+        false => result.push('=')
+      }
+    }
+  }
+
+  result
+}
+
+// Base64 in either the standard or URL safe alphabet, with or without
+// padding, as any of them are accepted when reading JSON
+pub(crate) fn decode_base64(value: &str) -> Result<Vec<u8>, String> {
+  let mut result = Vec::new();
+  let (mut group, mut bits) = (0u32, 0);
+
+  for v in value.trim_end_matches('=').bytes() {
+    let digit = match v {
+      b'-' => 62,
+      b'_' => 63,
+      v => BASE64
+        .iter()
+        .position(|c| *c == v)
+        .ok_or_else(|| format!("Invalid base64 {}", value))?
+    };
+
+    group = group << 6 | digit as u32;
+    bits += 6;
+
+    if bits >= 8 {
+      bits -= 8;
+      result.push((group >> bits) as u8);
+    }
+  }
+
+  Ok(result)
+}
+
+fn indent(depth: usize) -> String {
+  "  ".repeat(depth)
+}
+
+fn escape(value: &str) -> String {
+  value
+    .chars()
+    .map(|v| match v {
+      '"' => "\\\"".to_string(),
+      '\\' => "\\\\".to_string(),
+      '\n' => "\\n".to_string(),
+      '\r' => "\\r".to_string(),
+      '\t' => "\\t".to_string(),
+      v if v.is_control() => format!("\\u{:04x}", v as u32),
+      v => v.to_string()
+    })
+    .collect()
+}
+
+// Items of a JSON object or array, each on a line of its own
+fn print_items(
+  items: Vec<String>,
+  open: char,
+  close: char,
+  depth: usize
+) -> String {
+  match items.is_empty() {
+    true => format!("{}{}", open, close),
+    false => format!(
+      "{}\n{}\n{}{}",
+      open,
+      items.join(",\n"),
+      indent(depth),
+      close
+    )
+  }
+}
+
+fn print_json(json: &Json, depth: usize) -> String {
+  match json {
+    Json::Null => "null".to_string(),
+    Json::Bool(v) => v.to_string(),
+    Json::Number(v) => v.clone(),
+    Json::String(v) => format!("\"{}\"", escape(v)),
+    Json::Array(values) => {
+      let items = values
+        .iter()
+        .map(|v| format!("{}{}", indent(depth + 1), print_json(v, depth + 1)))
+        .collect();
+
+      print_items(items, '[', ']', depth)
+    }
+    Json::Object(fields) => {
+      let items = fields
+        .iter()
+        .map(|(key, v)| {
+          format!(
+            "{}\"{}\": {}",
+            indent(depth + 1),
+            escape(key),
+            print_json(v, depth + 1)
+          )
+        })
+        .collect();
+
+      print_items(items, '{', '}', depth)
+    }
+  }
+}
+
+// Floats that JSON numbers can't hold are written as strings
+fn float_json<T: Display + Into<f64> + Copy>(value: T) -> Json {
+  match value.into() {
+    v if v.is_nan() => Json::String("NaN".to_string()),
+    f64::INFINITY => Json::String("Infinity".to_string()),
+    f64::NEG_INFINITY => Json::String("-Infinity".to_string()),
+    _ => Json::Number(value.to_string())
+  }
+}
+
+fn value_json(value: well_known::Value) -> Json {
+  match value {
+    well_known::Value::Null => Json::Null,
+    well_known::Value::Number(v) => float_json(v),
+    well_known::Value::String(v) => Json::String(v),
+    well_known::Value::Bool(v) => Json::Bool(v),
+    well_known::Value::Struct(v) => Json::Object(
+      v.fields
+        .into_iter()
+        .map(|(key, v)| (key, value_json(v)))
+        .collect()
+    ),
+    well_known::Value::List(v) => Json::Array(
+      v.values
+        .into_iter()
+        .map(value_json)
+        .collect()
+    )
+  }
+}
+
+// Well-known types other than Any are written to JSON in their own way,
+// which is what an Any holding one of them writes in "value"
+fn is_mapped(name: &str) -> bool {
+  well_known::descriptor(name).is_some() && name != "google.protobuf.Any"
+}
+
+// JSON mapping of a well-known type given its name and encoding, unless it
+// can't be decoded or is out of the range JSON can hold
+fn well_known_json(name: &str, value: &[u8]) -> Option<Json> {
+  let json = match name.strip_prefix("google.protobuf.")? {
+    "Timestamp" => Json::String(
+      Timestamp::decode(value)
+        .ok()?
+        .to_rfc3339()
+        .ok()?
+    ),
+    "Duration" => Json::String(
+      Duration::decode(value)
+        .ok()?
+        .to_string()
+    ),
+    "FieldMask" => Json::String(
+      FieldMask::decode(value)
+        .ok()?
+        .to_string()
+    ),
+    "Empty" => Json::Object(Vec::new()),
+    "BoolValue" => Json::Bool(BoolValue::decode(value).ok()?.value),
+    "StringValue" => Json::String(StringValue::decode(value).ok()?.value),
+    "BytesValue" => {
+      Json::String(encode_base64(&BytesValue::decode(value).ok()?.value))
+    }
+    "Int32Value" => Json::Number(
+      Int32Value::decode(value)
+        .ok()?
+        .value
+        .to_string()
+    ),
+    "UInt32Value" => Json::Number(
+      UInt32Value::decode(value)
+        .ok()?
+        .value
+        .to_string()
+    ),
+    // 64 bit integers are strings so they aren't rounded by readers that
+    // treat numbers as doubles
+    "Int64Value" => Json::String(
+      Int64Value::decode(value)
+        .ok()?
+        .value
+        .to_string()
+    ),
+    "UInt64Value" => Json::String(
+      UInt64Value::decode(value)
+        .ok()?
+        .value
+        .to_string()
+    ),
+    "FloatValue" => float_json(FloatValue::decode(value).ok()?.value),
+    "DoubleValue" => float_json(DoubleValue::decode(value).ok()?.value),
+    "Value" => value_json(well_known::Value::decode(value).ok()?),
+    "Struct" => {
+      value_json(well_known::Value::Struct(Struct::decode(value).ok()?))
+    }
+    "ListValue" => {
+      value_json(well_known::Value::List(ListValue::decode(value).ok()?))
+    }
+    _ => return None
+  };
+
+  Some(json)
+}
+
+// Well-known type held by a dynamic Any, with its type URL and its JSON
+// mapping
+fn unpack_well_known(
+  message: &DynamicMessage,
+  registry: &TypeRegistry
+) -> Option<(String, Json)> {
+  if !is_any(message.descriptor()) {
+    return None;
+  }
+
+  let Some(Value::String(type_url)) = message.get("type_url") else {
+    return None;
+  };
+  let value = match message.get("value") {
+    Some(Value::Bytes(value)) => value.as_slice(),
+    _ => &[]
+  };
+  let json = well_known_json(
+    registry
+      .resolve(type_url)
+      .ok()?
+      .full_name,
+    value
+  )?;
+
+  Some((type_url.clone(), json))
+}
+
+fn print_fields(
+  message: &DynamicMessage,
+  depth: usize,
+  registry: &TypeRegistry
+) -> Vec<String> {
+  message
+    .fields()
+    .map(|(field, value)| {
+      let value = match value {
+        Value::Int32(v) => v.to_string(),
+        // 64 bit integers are strings so they aren't rounded by readers that
+        // treat numbers as doubles
+        Value::Int64(v) => format!("\"{}\"", v),
+        Value::Bool(v) => v.to_string(),
+        Value::String(v) => format!("\"{}\"", escape(v)),
+        Value::Bytes(v) => format!("\"{}\"", encode_base64(v)),
+        Value::Message(v) => print_message(v, depth + 1, registry)
+      };
+
+      format!("{}\"{}\": {}", indent(depth + 1), field.json_name, value)
+    })
+    .collect()
+}
+
+fn print_message(
+  message: &DynamicMessage,
+  depth: usize,
+  registry: &TypeRegistry
+) -> String {
+  let name = message.descriptor().full_name;
+
+  // Well-known types are written in their own way wherever they're found,
+  // unless they're beyond what that can hold
+  if is_mapped(name) {
+    if let Some(json) = well_known_json(name, &message.encode_to_vec()) {
+      return print_json(&json, depth);
+    }
+  }
+
+  let at_type = |type_url: &str| {
+    format!("{}\"@type\": \"{}\"", indent(depth + 1), escape(type_url))
+  };
+
+  // Any fields are written as the fields of the message they hold, along
+  // with its type URL in "@type", except for well-known types, whose own
+  // mapping is written in "value"
+  let fields = match unpack_well_known(message, registry) {
+    Some((type_url, json)) => vec![
+      at_type(&type_url),
+      format!(
+        "{}\"value\": {}",
+        indent(depth + 1),
+        print_json(&json, depth + 1)
+      ),
+    ],
+    None => match registry.unpack_any(message) {
+      Some((type_url, message)) => [at_type(&type_url)]
+        .into_iter()
+        .chain(print_fields(&message, depth, registry))
+        .collect(),
+      None => print_fields(message, depth, registry)
+    }
+  };
+
+  print_items(fields, '{', '}', depth)
+}
+
+// JSON mapping of a message, where fields are keyed by their JSON names and
+// those that aren't set are left out
+pub fn print(message: &DynamicMessage) -> String {
+  print_with(message, &TypeRegistry::default())
+}
+
+// JSON mapping of a message, expanding Any fields of the types in a registry
+pub fn print_with(message: &DynamicMessage, registry: &TypeRegistry) -> String {
+  print_message(message, 0, registry)
+}
+
+fn skip_whitespace(chars: &mut Peekable<Chars>) {
+  while chars
+    .next_if(|v| v.is_whitespace())
+    .is_some()
+  {}
+}
+
+fn expect(chars: &mut Peekable<Chars>, expected: char) -> Result<(), String> {
+  skip_whitespace(chars);
+
+  match chars.next() {
+    Some(v) if v == expected => Ok(()),
+    Some(v) => Err(format!("Expected {} but found {}", expected, v)),
+    None => Err(format!("Expected {} but found the end", expected))
+  }
+}
+
+fn read_hex(chars: &mut Peekable<Chars>) -> Result<u32, String> {
+  let code = chars.take(4).collect::<String>();
+
+  match code.len() == 4
+    && code
+      .chars()
+      .all(|v| v.is_ascii_hexdigit())
+  {
+    true => u32::from_str_radix(&code, 16).map_err(|err| err.to_string()),
+    false => Err(format!("Invalid escape \\u{}", code))
+  }
+}
+
+fn read_string(chars: &mut Peekable<Chars>) -> Result<String, String> {
+  let mut value = String::new();
+
+  loop {
+    match chars
+      .next()
+      .ok_or("Unterminated string")?
+    {
+      '"' => return Ok(value),
+      '\\' => match chars
+        .next()
+        .ok_or("Unterminated string")?
+      {
+        'n' => value.push('\n'),
+        'r' => value.push('\r'),
+        't' => value.push('\t'),
+        'b' => value.push('\u{8}'),
+        'f' => value.push('\u{c}'),
+        'u' => {
+          let mut code = read_hex(chars)?;
+
+          // Characters outside the basic plane are escaped as a surrogate
+          // pair, whose second half follows immediately
+          if (0xd800..0xdc00).contains(&code) {
+            let low = match (chars.next(), chars.next()) {
+              (Some('\\'), Some('u')) => read_hex(chars)?,
+              _ => return Err("Unpaired surrogate".to_string())
+            };
+
+            if !(0xdc00..0xe000).contains(&low) {
+              return Err("Unpaired surrogate".to_string());
+            }
+
+            code = 0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00);
+          }
+
+          value.push(char::from_u32(code).ok_or("Invalid escape")?);
+        }
+        v @ ('"' | '\\' | '/') => value.push(v),
+        v => return Err(format!("Invalid escape \\{}", v))
+      },
+      v => value.push(v)
+    }
+  }
+}
+
+// JSON value, whose objects and arrays may be nested as deeply as messages
+// may be when decoded
+fn read_value(
+  chars: &mut Peekable<Chars>,
+  depth: usize
+) -> Result<Json, String> {
+  if depth > RECURSION_LIMIT {
+    return Err("Recursion limit exceeded".to_string());
+  }
+
+  skip_whitespace(chars);
+
+  match chars
+    .next()
+    .ok_or("Unexpected end of JSON")?
+  {
+    '"' => Ok(Json::String(read_string(chars)?)),
+    '{' => {
+      let mut fields = Vec::new();
+
+      skip_whitespace(chars);
+
+      if chars.next_if_eq(&'}').is_none() {
+        loop {
+          expect(chars, '"')?;
+
+          let key = read_string(chars)?;
+
+          expect(chars, ':')?;
+          fields.push((key, read_value(chars, depth + 1)?));
+          skip_whitespace(chars);
+
+          match chars.next() {
+            Some(',') => continue,
+            Some('}') => break,
+            _ => return Err("Expected , or } in object".to_string())
+          }
+        }
+      }
+
+      Ok(Json::Object(fields))
+    }
+    '[' => {
+      let mut values = Vec::new();
+
+      skip_whitespace(chars);
+
+      if chars.next_if_eq(&']').is_none() {
+        loop {
+          values.push(read_value(chars, depth + 1)?);
+          skip_whitespace(chars);
+
+          match chars.next() {
+            Some(',') => continue,
+            Some(']') => break,
+            _ => return Err("Expected , or ] in array".to_string())
+          }
+        }
+      }
+
+      Ok(Json::Array(values))
+    }
+    v if v.is_alphanumeric() || v == '-' => {
+      let mut word = v.to_string();
+
+      while let Some(v) =
+        chars.next_if(|v| v.is_alphanumeric() || "+-.".contains(*v))
+      {
+        word.push(v);
+      }
+
+      match word.as_str() {
+        "null" => Ok(Json::Null),
+        "true" => Ok(Json::Bool(true)),
+        "false" => Ok(Json::Bool(false)),
+        _ if word.parse::<f64>().is_ok() => Ok(Json::Number(word)),
+        _ => Err(format!("Unexpected {}", word))
+      }
+    }
+    v => Err(format!("Unexpected {}", v))
+  }
+}
+
+// Integers may be given as numbers, including those with exponents, or as
+// strings holding one
+fn to_integer<T: FromStr + TryFrom<i64>>(value: &str) -> Option<T> {
+  value.parse::<T>().ok().or_else(|| {
+    let value = value.parse::<f64>().ok()?;
+
+    match value.fract() == 0.0 && value.abs() < i64::MAX as f64 {
+      true => T::try_from(value as i64).ok(),
+      false => None
+    }
+  })
+}
+
+fn to_value(json: Json) -> Result<well_known::Value, String> {
+  Ok(match json {
+    Json::Null => well_known::Value::Null,
+    Json::Bool(v) => well_known::Value::Bool(v),
+    Json::Number(v) => well_known::Value::Number(
+      v.parse()
+        .map_err(|_| format!("Invalid number {}", v))?
+    ),
+    Json::String(v) => well_known::Value::String(v),
+    Json::Array(values) => well_known::Value::List(ListValue {
+      values: values
+        .into_iter()
+        .map(to_value)
+        .collect::<Result<_, _>>()?
+    }),
+    Json::Object(fields) => well_known::Value::Struct(Struct {
+      fields: fields
+        .into_iter()
+        .map(|(key, v)| Ok((key, to_value(v)?)))
+        .collect::<Result<_, String>>()?
+    })
+  })
+}
+
+// Encoding of a well-known type read from its JSON mapping
+fn well_known_value(name: &str, json: Json) -> Result<Vec<u8>, String> {
+  let invalid = || format!("Invalid value for {}", name);
+  let short = name.trim_start_matches("google.protobuf.");
+
+  Ok(match (short, json) {
+    ("Timestamp", Json::String(v)) => v.parse::<Timestamp>()?.encode_to_vec(),
+    ("Duration", Json::String(v)) => v.parse::<Duration>()?.encode_to_vec(),
+    ("FieldMask", Json::String(v)) => v.parse::<FieldMask>()?.encode_to_vec(),
+    ("Empty", Json::Object(_)) => Vec::new(),
+    ("BoolValue", Json::Bool(v)) => BoolValue::from(v).encode_to_vec(),
+    ("StringValue", Json::String(v)) => StringValue::from(v).encode_to_vec(),
+    ("BytesValue", Json::String(v)) => {
+      BytesValue::from(decode_base64(&v)?).encode_to_vec()
+    }
+    ("Int32Value", Json::Number(v) | Json::String(v)) => {
+      Int32Value::from(to_integer::<i32>(&v).ok_or_else(invalid)?)
+        .encode_to_vec()
+    }
+    ("UInt32Value", Json::Number(v) | Json::String(v)) => {
+      UInt32Value::from(to_integer::<u32>(&v).ok_or_else(invalid)?)
+        .encode_to_vec()
+    }
+    ("Int64Value", Json::Number(v) | Json::String(v)) => {
+      Int64Value::from(to_integer::<i64>(&v).ok_or_else(invalid)?)
+        .encode_to_vec()
+    }
+    ("UInt64Value", Json::Number(v) | Json::String(v)) => {
+      UInt64Value::from(to_integer::<u64>(&v).ok_or_else(invalid)?)
+        .encode_to_vec()
+    }
+    ("FloatValue", Json::Number(v) | Json::String(v)) => FloatValue::from(
+      v.parse::<f32>()
+        .map_err(|_| invalid())?
+    )
+    .encode_to_vec(),
+    ("DoubleValue", Json::Number(v) | Json::String(v)) => DoubleValue::from(
+      v.parse::<f64>()
+        .map_err(|_| invalid())?
+    )
+    .encode_to_vec(),
+    ("Value", json) => to_value(json)?.encode_to_vec(),
+    // Structs and lists are encoded as themselves, not as the Value holding
+    // them
+    ("Struct" | "ListValue", json) => match (short, to_value(json)?) {
+      ("Struct", well_known::Value::Struct(v)) => v.encode_to_vec(),
+      ("ListValue", well_known::Value::List(v)) => v.encode_to_vec(),
+      _ => return Err(invalid())
+    },
+    _ => return Err(invalid())
+  })
+}
+
+fn to_message(
+  descriptor: &'static MessageDescriptor,
+  json: Json,
+  registry: &TypeRegistry
+) -> Result<DynamicMessage, String> {
+  let name = descriptor.full_name;
+
+  // Well-known types are read from their own mapping, which fails when their
+  // descriptors can't hold every field it sets
+  if is_mapped(name) {
+    let value = well_known_value(name, json)?;
+    let message = DynamicMessage::decode(descriptor, &value)
+      .map_err(|err| err.to_string())?;
+
+    return match message.encode_to_vec() == value {
+      true => Ok(message),
+      false => Err(format!("Reflection can't hold every field of {}", name))
+    };
+  }
+
+  let Json::Object(mut fields) = json else {
+    return Err(format!("Expected an object for {}", descriptor.full_name));
+  };
+
+  // Any fields with a "@type" hold the fields of the message of that type
+  let at = fields
+    .iter()
+    .position(|(key, _)| key == "@type");
+
+  if let (true, Some(at)) = (is_any(descriptor), at) {
+    let Json::String(type_url) = fields.remove(at).1 else {
+      return Err("Expected a string for @type".to_string());
+    };
+    let r#type = registry.resolve(&type_url)?;
+
+    // Well-known types hold their own mapping in "value"
+    if is_mapped(r#type.full_name) {
+      let value = match <[_; 1]>::try_from(fields) {
+        Ok([(key, json)]) if key == "value" => json,
+        _ => return Err(format!("Expected only value for {}", type_url))
+      };
+
+      return pack_any(&type_url, well_known_value(r#type.full_name, value)?);
+    }
+
+    let message = to_message(r#type, Json::Object(fields), registry)?;
+
+    return pack_any(&type_url, message.encode_to_vec());
+  }
+
+  let mut message = DynamicMessage::new(descriptor);
+
+  for (key, json) in fields {
+    // Fields are keyed by either their JSON name or their original name
+    let field = descriptor
+      .fields
+      .iter()
+      .find(|v| v.json_name == key || v.name == key)
+      .ok_or_else(|| format!("Unknown field {}", key))?;
+    let invalid = || format!("Invalid value for field {}", key);
+
+    let value = match (field.r#type, json) {
+      (_, Json::Null) => continue,
+      (FieldType::Int32, Json::Number(v) | Json::String(v)) => {
+        Value::Int32(to_integer(&v).ok_or_else(invalid)?)
+      }
+      (FieldType::Int64, Json::Number(v) | Json::String(v)) => {
+        Value::Int64(to_integer(&v).ok_or_else(invalid)?)
+      }
+      (FieldType::Bool, Json::Bool(v)) => Value::Bool(v),
+      (FieldType::String, Json::String(v)) => Value::String(v),
+      (FieldType::Bytes, Json::String(v)) => {
+        Value::Bytes(decode_base64(&v).map_err(|_| invalid())?)
+      }
+      (FieldType::Message(r#type), json) => {
+        Value::Message(to_message(r#type.descriptor(), json, registry)?)
+      }
+      _ => return Err(invalid())
+    };
+
+    if message.get(field.name).is_some() {
+      return Err(format!("Field {} is set more than once", key));
+    }
+
+    message.set(field.name, value)?;
+  }
+
+  Ok(message)
+}
+
+// Read a message of the given type from its JSON mapping, where null leaves a
+// field unset
+pub fn parse(
+  descriptor: &'static MessageDescriptor,
+  input: &str
+) -> Result<DynamicMessage, String> {
+  parse_with(descriptor, input, &TypeRegistry::default())
+}
+
+// Read a message from its JSON mapping, where Any fields may hold messages
+// of the types in a registry
+pub fn parse_with(
+  descriptor: &'static MessageDescriptor,
+  input: &str,
+  registry: &TypeRegistry
+) -> Result<DynamicMessage, String> {
+  let mut chars = input.chars().peekable();
+  let json = read_value(&mut chars, 0)?;
+
+  skip_whitespace(&mut chars);
+
+  match chars.next() {
+    Some(v) => Err(format!("Unexpected {} after JSON", v)),
+    None => to_message(descriptor, json, registry)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::runtime::reflect::{
+    FieldDescriptor,
+    FieldType,
+    Label,
+    MessageDescriptor,
+    MessageType
+  };
+  use crate::runtime::well_known::Any;
+  use crate::runtime::Message;
+
+  static NODE: MessageDescriptor = MessageDescriptor {
+    name: "Node",
+    full_name: "test.Node",
+    fields: &[
+      FieldDescriptor {
+        name: "label",
+        json_name: "label",
+        number: 1,
+        label: Label::Optional,
+        r#type: FieldType::String
+      },
+      FieldDescriptor {
+        name: "next_node",
+        json_name: "nextNode",
+        number: 2,
+        label: Label::Optional,
+        r#type: FieldType::Message(MessageType::Static(|| &NODE))
+      }
+    ]
+  };
+
+  static ENVELOPE: MessageDescriptor = MessageDescriptor {
+    name: "Envelope",
+    full_name: "test.Envelope",
+    fields: &[FieldDescriptor {
+      name: "payload",
+      json_name: "payload",
+      number: 1,
+      label: Label::Optional,
+      r#type: FieldType::Message(MessageType::Static(Any::descriptor))
+    }]
+  };
+
+  static EVENT: MessageDescriptor = MessageDescriptor {
+    name: "Event",
+    full_name: "test.Event",
+    fields: &[
+      FieldDescriptor {
+        name: "at",
+        json_name: "at",
+        number: 1,
+        label: Label::Optional,
+        r#type: FieldType::Message(MessageType::Static(Timestamp::descriptor))
+      },
+      FieldDescriptor {
+        name: "weight",
+        json_name: "weight",
+        number: 2,
+        label: Label::Optional,
+        r#type: FieldType::Message(MessageType::Static(Int32Value::descriptor))
+      },
+      FieldDescriptor {
+        name: "mask",
+        json_name: "mask",
+        number: 3,
+        label: Label::Optional,
+        r#type: FieldType::Message(MessageType::Static(FieldMask::descriptor))
+      }
+    ]
+  };
+
+  #[test]
+  fn print_nested_messages() -> Result<(), String> {
+    let mut next = DynamicMessage::new(&NODE);
+    let mut message = DynamicMessage::new(&NODE);
+
+    next.set("label", Value::String("a\n\"b\"".to_string()))?;
+    message.set("next_node", Value::Message(next))?;
+
+    assert_eq!(
+      print(&message),
+      "{\n  \"nextNode\": {\n    \"label\": \"a\\n\\\"b\\\"\"\n  }\n}"
+    );
+    assert_eq!(print(&DynamicMessage::new(&NODE)), "{}");
+    assert_eq!(parse(&NODE, &print(&message))?, message);
+
+    Ok(())
+  }
+
+  #[test]
+  fn parse_json_names() -> Result<(), String> {
+    let message = parse(
+      &NODE,
+      r#"{"next_node": {"label": "\u00e9\ud83d\ude00"}, "label": null}"#
+    )?;
+
+    assert_eq!(
+      message
+        .get("next_node")
+        .and_then(|v| match v {
+          Value::Message(v) => v.get("label"),
+          _ => None
+        }),
+      Some(&Value::String("\u{e9}\u{1f600}".to_string()))
+    );
+    assert!(parse(&NODE, r#"{"label": 1}"#).is_err());
+    assert!(parse(&NODE, r#"{"missing": "a"}"#).is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn limit_recursion() {
+    let nested =
+      |depth| format!(r#"{}"a"{}"#, "[".repeat(depth), "]".repeat(depth));
+
+    assert!(read_value(
+      &mut nested(RECURSION_LIMIT)
+        .chars()
+        .peekable(),
+      0
+    )
+    .is_ok());
+    assert_eq!(
+      read_value(
+        &mut nested(RECURSION_LIMIT + 1)
+          .chars()
+          .peekable(),
+        0
+      ),
+      Err("Recursion limit exceeded".to_string())
+    );
+    assert!(parse(&NODE, &"{\"nextNode\": ".repeat(1_000_000)).is_err());
+  }
+
+  #[test]
+  fn reject_unpaired_surrogates() {
+    for input in [
+      r#"{"label": "\ud800\u0041"}"#,
+      r#"{"label": "\ud800 \udc00"}"#,
+      r#"{"label": "\ud800"}"#,
+      r#"{"label": "\udc00"}"#,
+      r#"{"label": "\u+041"}"#
+    ] {
+      assert!(parse(&NODE, input).is_err(), "{}", input);
+    }
+  }
+
+  #[test]
+  fn expand_any_fields() -> Result<(), String> {
+    let mut node = DynamicMessage::new(&NODE);
+    let mut message = DynamicMessage::new(&ENVELOPE);
+    let registry = TypeRegistry::default().add_descriptor(&NODE);
+
+    node.set("label", Value::String("a".to_string()))?;
+    message.set(
+      "payload",
+      Value::Message(pack_any(
+        "type.googleapis.com/test.Node",
+        node.encode_to_vec()
+      )?)
+    )?;
+
+    let expanded = print_with(&message, &registry);
+
+    assert_eq!(
+      expanded,
+      "{\n  \"payload\": {\n    \"@type\": \
+       \"type.googleapis.com/test.Node\",\n    \"label\": \"a\"\n  }\n}"
+    );
+    assert_eq!(parse_with(&ENVELOPE, &expanded, &registry)?, message);
+    assert!(print(&message).contains("\"value\": \"CgFh\""));
+    assert!(parse(&ENVELOPE, &expanded).is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn map_well_known_any_values() -> Result<(), String> {
+    let mut message = DynamicMessage::new(&ENVELOPE);
+    let time = Any::pack(&Timestamp::new(5, 0));
+
+    message.set(
+      "payload",
+      Value::Message(pack_any(&time.type_url, time.value)?)
+    )?;
+
+    let printed = print(&message);
+
+    assert_eq!(
+      printed,
+      "{\n  \"payload\": {\n    \"@type\": \
+       \"type.googleapis.com/google.protobuf.Timestamp\",\n    \"value\": \
+       \"1970-01-01T00:00:05Z\"\n  }\n}"
+    );
+    assert_eq!(parse(&ENVELOPE, &printed)?, message);
+
+    for (name, value, printed) in [
+      (
+        "Int64Value",
+        r#""-9007199254740993""#,
+        "\"-9007199254740993\""
+      ),
+      ("FloatValue", r#""NaN""#, "\"NaN\""),
+      ("Empty", "{}", "{}"),
+      (
+        "Struct",
+        r#"{"a": [1.5, null]}"#,
+        "{\n      \"a\": [\n        1.5,\n        null\n      ]\n    }"
+      )
+    ] {
+      let input = format!(
+        r#"{{"payload": {{"@type": "/google.protobuf.{}", "value": {}}}}}"#,
+        name, value
+      );
+
+      assert!(print(&parse(&ENVELOPE, &input)?)
+        .contains(&format!("\"value\": {}", printed)));
+    }
+
+    assert!(parse(
+      &ENVELOPE,
+      r#"{"payload": {"@type": "/google.protobuf.Duration", "value": 5}}"#
+    )
+    .is_err());
+    assert!(parse(
+      &ENVELOPE,
+      r#"{"payload": {"@type": "/google.protobuf.Duration", "seconds": 5}}"#
+    )
+    .is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn map_well_known_fields() -> Result<(), String> {
+    let input = r#"{"at": "2020-01-01T00:00:00.500Z", "weight": 7}"#;
+    let message = parse(&EVENT, input)?;
+    let Some(Value::Message(at)) = message.get("at") else {
+      panic!("Missing timestamp");
+    };
+
+    assert_eq!(
+      Timestamp::decode(&at.encode_to_vec()),
+      Ok(Timestamp::new(1_577_836_800, 500_000_000))
+    );
+    assert_eq!(
+      print(&message),
+      "{\n  \"at\": \"2020-01-01T00:00:00.500Z\",\n  \"weight\": 7\n}"
+    );
+    assert_eq!(parse(&EVENT, &print(&message))?, message);
+    assert_eq!(
+      print(
+        &DynamicMessage::decode(Timestamp::descriptor(), &[8, 5])
+          .map_err(|err| err.to_string())?
+      ),
+      "\"1970-01-01T00:00:05Z\""
+    );
+
+    // Field masks hold repeated fields, which reflection can't yet
+    assert!(parse(&EVENT, r#"{"mask": "a.b"}"#).is_err());
+    assert!(parse(&EVENT, r#"{"at": {"seconds": 5}}"#).is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn base64_round_trip() -> Result<(), String> {
+    for bytes in [&b""[..], b"f", b"fo", b"foo", b"\xff\xfe"] {
+      assert_eq!(decode_base64(&encode_base64(bytes))?, bytes);
+    }
+
+    assert_eq!(encode_base64(b"fo"), "Zm8=");
+    assert_eq!(decode_base64("__8")?, vec![255, 255]);
+
+    Ok(())
+  }
+}
@@ -0,0 +1,304 @@
+use super::well_known::FieldMask;
+use std::mem::swap;
+
+// Messages whose fields can be picked out by the paths of a FieldMask, as in
+// "user.display_name", which generated messages implement field by field
+// with the helpers below
+pub trait Masked {
+  // Whether a path, split at each dot, names a field of the message or a
+  // field within one of its message fields
+  fn is_path(path: &[&str]) -> bool;
+
+  // Swap the field named by a path with that of another message
+  fn merge_path(&mut self, source: &mut Self, path: &[&str]);
+
+  // Unset every field that isn't named by a path or reached by one
+  fn trim_paths(&mut self, paths: &[&[&str]]);
+
+  fn validate_mask(mask: &FieldMask) -> Result<(), String>
+  where
+    Self: Sized
+  {
+    match mask
+      .paths
+      .iter()
+      .find(|v| !Self::is_path(&split(v)))
+    {
+      Some(path) => Err(format!("Invalid field mask path {}", path)),
+      None => Ok(())
+    }
+  }
+
+  // Take the fields named by a mask from another message, as update RPCs do,
+  // so fields that are unset there are unset here too
+  fn apply_mask(
+    &mut self,
+    mut source: Self,
+    mask: &FieldMask
+  ) -> Result<(), String>
+  where
+    Self: Sized
+  {
+    Self::validate_mask(mask)?;
+
+    let mut paths = mask
+      .paths
+      .iter()
+      .map(|v| split(v))
+      .collect::<Vec<Vec<&str>>>();
+
+    paths.sort();
+    paths.dedup();
+
+    // Paths within another path of the mask are covered by it already, and
+    // would otherwise swap their fields back again
+    for path in paths.iter().filter(|v| {
+      !paths
+        .iter()
+        .any(|other| other.len() < v.len() && v.starts_with(other))
+    }) {
+      self.merge_path(&mut source, path);
+    }
+
+    Ok(())
+  }
+
+  // Unset every field that the mask doesn't name or reach within
+  fn trim_to_mask(&mut self, mask: &FieldMask) -> Result<(), String>
+  where
+    Self: Sized
+  {
+    Self::validate_mask(mask)?;
+
+    let paths = mask
+      .paths
+      .iter()
+      .map(|v| split(v))
+      .collect::<Vec<Vec<&str>>>();
+
+    self.trim_paths(
+      &paths
+        .iter()
+        .map(Vec::as_slice)
+        .collect::<Vec<&[&str]>>()
+    );
+
+    Ok(())
+  }
+}
+
+// Boxed messages are masked just as the message they contain
+impl<M: Masked> Masked for Box<M> {
+  fn is_path(path: &[&str]) -> bool {
+    M::is_path(path)
+  }
+
+  fn merge_path(&mut self, source: &mut Self, path: &[&str]) {
+    (**self).merge_path(source, path)
+  }
+
+  fn trim_paths(&mut self, paths: &[&[&str]]) {
+    (**self).trim_paths(paths)
+  }
+}
+
+fn split(path: &str) -> Vec<&str> {
+  path.split('.').collect()
+}
+
+// Paths within a field, given those of the message it's in
+fn within<'a, 'b>(paths: &[&'a [&'b str]], name: &str) -> Vec<&'a [&'b str]> {
+  paths
+    .iter()
+    .filter_map(|v| match v {
+      [first, rest @ ..] if *first == name => Some(rest),
+      _ => None
+    })
+    .collect()
+}
+
+pub fn is_field(path: &[&str], name: &str) -> bool {
+  path == [name]
+}
+
+// Message fields may be named themselves, or have paths within them
+pub fn is_message_field<M: Masked>(path: &[&str], name: &str) -> bool {
+  match path {
+    [first, rest @ ..] if *first == name => rest.is_empty() || M::is_path(rest),
+    _ => false
+  }
+}
+
+pub fn merge_field<T>(
+  value: &mut T,
+  source: &mut T,
+  path: &[&str],
+  name: &str
+) {
+  if is_field(path, name) {
+    swap(value, source);
+  }
+}
+
+// Paths within a message field set it when it's unset, so the fields they
+// name can be merged into it, unless it's unset in both messages
+pub fn merge_message_field<M: Masked + Default>(
+  value: &mut Option<M>,
+  source: &mut Option<M>,
+  path: &[&str],
+  name: &str
+) {
+  match path {
+    [first] if *first == name => swap(value, source),
+    [first, rest @ ..]
+      if *first == name && (value.is_some() || source.is_some()) =>
+    {
+      value
+        .get_or_insert_with(M::default)
+        .merge_path(source.get_or_insert_with(M::default), rest)
+    }
+    _ => {}
+  }
+}
+
+pub fn trim_field<T: Default>(value: &mut T, paths: &[&[&str]], name: &str) {
+  if within(paths, name).is_empty() {
+    *value = T::default();
+  }
+}
+
+// Message fields are kept whole when they're named themselves, and are
+// otherwise trimmed to the paths within them
+pub fn trim_message_field<M: Masked>(
+  value: &mut Option<M>,
+  paths: &[&[&str]],
+  name: &str
+) {
+  let paths = within(paths, name);
+
+  match value {
+    _ if paths.is_empty() => *value = None,
+    Some(value) if !paths.iter().any(|v| v.is_empty()) => {
+      value.trim_paths(&paths)
+    }
+    _ => {}
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[derive(Clone, Debug, Default, PartialEq)]
+  struct User {
+    name: String,
+    age: i32,
+    manager: Option<Box<User>>
+  }
+
+  impl Masked for User {
+    fn is_path(path: &[&str]) -> bool {
+      is_field(path, "name")
+        || is_field(path, "age")
+        || is_message_field::<User>(path, "manager")
+    }
+
+    fn merge_path(&mut self, source: &mut Self, path: &[&str]) {
+      merge_field(&mut self.name, &mut source.name, path, "name");
+      merge_field(&mut self.age, &mut source.age, path, "age");
+      merge_message_field(
+        &mut self.manager,
+        &mut source.manager,
+        path,
+        "manager"
+      );
+    }
+
+    fn trim_paths(&mut self, paths: &[&[&str]]) {
+      trim_field(&mut self.name, paths, "name");
+      trim_field(&mut self.age, paths, "age");
+      trim_message_field(&mut self.manager, paths, "manager");
+    }
+  }
+
+  fn user(name: &str, age: i32, manager: Option<User>) -> User {
+    User {
+      name: name.to_string(),
+      age,
+      manager: manager.map(Box::new)
+    }
+  }
+
+  fn mask(paths: &str) -> FieldMask {
+    FieldMask {
+      paths: paths
+        .split(',')
+        .map(String::from)
+        .collect()
+    }
+  }
+
+  #[test]
+  fn validate_paths() {
+    assert!(User::validate_mask(&mask("name,manager.manager.age")).is_ok());
+    assert!(User::validate_mask(&mask("manager")).is_ok());
+    assert_eq!(
+      User::validate_mask(&mask("name.first")),
+      Err("Invalid field mask path name.first".to_string())
+    );
+    assert!(User::validate_mask(&mask("manager.email")).is_err());
+  }
+
+  #[test]
+  fn apply_masks() -> Result<(), String> {
+    let mut target = user("a", 1, Some(user("b", 2, None)));
+    let source = user("c", 3, Some(user("d", 4, None)));
+
+    target.apply_mask(source.clone(), &mask("age,manager.name,age,manager"))?;
+
+    assert_eq!(target, user("a", 3, Some(user("d", 4, None))));
+
+    target.apply_mask(source, &mask("manager.manager.name,name"))?;
+
+    assert_eq!(target, user("c", 3, Some(user("d", 4, None))));
+
+    target.apply_mask(
+      user("", 0, Some(user("", 0, Some(user("e", 0, None))))),
+      &mask("manager.manager.name,manager.manager.manager.age")
+    )?;
+
+    assert_eq!(
+      target,
+      user("c", 3, Some(user("d", 4, Some(user("e", 0, None)))))
+    );
+
+    let mut empty = User::default();
+
+    empty.apply_mask(User::default(), &mask("manager.manager.age"))?;
+
+    assert_eq!(empty, User::default());
+    assert!(target
+      .apply_mask(User::default(), &mask("email"))
+      .is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn trim_to_paths() -> Result<(), String> {
+    let mut value = user("a", 1, Some(user("b", 2, Some(user("c", 3, None)))));
+
+    value.trim_to_mask(&mask("age,manager.manager,manager.name"))?;
+
+    assert_eq!(
+      value,
+      user("", 1, Some(user("b", 0, Some(user("c", 3, None)))))
+    );
+
+    value.trim_to_mask(&mask("name"))?;
+
+    assert_eq!(value, User::default());
+
+    Ok(())
+  }
+}
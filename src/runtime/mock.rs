@@ -0,0 +1,224 @@
+use super::status::{Request, Response, Status};
+use super::stream::Streaming;
+use std::collections::VecDeque;
+use std::sync::{Mutex, MutexGuard};
+
+type Handler<Req, Res> = Box<dyn Fn(&Req) -> Result<Res, Status> + Send + Sync>;
+type Predicate<Req> = Box<dyn Fn(&Req) -> bool + Send + Sync>;
+
+struct Expectation<Req> {
+  predicate: Predicate<Req>,
+  times: usize
+}
+
+// Method of a generated mock service, which records every request it's called
+// with and replies with whatever response was programmed for it. Streamed
+// requests are collected into a list before they're recorded
+pub struct MockMethod<Req, Res> {
+  expectations: Mutex<Vec<Expectation<Req>>>,
+  handler: Mutex<Option<Handler<Req, Res>>>,
+  path: &'static str,
+  queue: Mutex<VecDeque<Result<Res, Status>>>,
+  requests: Mutex<Vec<Request<Req>>>
+}
+
+// A handler or predicate that panics can only poison a lock that it reads
+// from, which leaves the data behind it intact for later calls
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+  mutex
+    .lock()
+    .unwrap_or_else(|err| err.into_inner())
+}
+
+impl<Req, Res> MockMethod<Req, Res> {
+  pub fn new(path: &'static str) -> Self {
+    Self {
+      expectations: Mutex::new(Vec::new()),
+      handler: Mutex::new(None),
+      path,
+      queue: Mutex::new(VecDeque::new()),
+      requests: Mutex::new(Vec::new())
+    }
+  }
+
+  // Reply to every call that doesn't have a response queued with the result of
+  // the given function
+  pub fn returns<F>(&self, f: F) -> &Self
+  where
+    F: Fn(&Req) -> Result<Res, Status> + Send + Sync + 'static
+  {
+    *lock(&self.handler) = Some(Box::new(f));
+
+    self
+  }
+
+  // Reply to the next call with the given result, before falling back to the
+  // function given to "returns"
+  pub fn returns_once(&self, result: Result<Res, Status>) -> &Self {
+    lock(&self.queue).push_back(result);
+
+    self
+  }
+
+  // Expect exactly the given number of calls with a request matching the
+  // predicate, as checked by "verify"
+  pub fn expect<F>(&self, times: usize, predicate: F) -> &Self
+  where
+    F: Fn(&Req) -> bool + Send + Sync + 'static
+  {
+    lock(&self.expectations).push(Expectation {
+      predicate: Box::new(predicate),
+      times
+    });
+
+    self
+  }
+
+  pub fn expect_calls(&self, times: usize) -> &Self {
+    self.expect(times, |_| true)
+  }
+
+  pub fn calls(&self) -> usize {
+    lock(&self.requests).len()
+  }
+
+  pub fn requests(&self) -> MutexGuard<'_, Vec<Request<Req>>> {
+    lock(&self.requests)
+  }
+
+  // Describe every expectation that the recorded calls don't meet
+  pub fn check(&self) -> Result<(), String> {
+    let requests = lock(&self.requests);
+    let failures = lock(&self.expectations)
+      .iter()
+      .enumerate()
+      .filter_map(|(i, v)| {
+        let calls = requests
+          .iter()
+          .filter(|req| (v.predicate)(&req.message))
+          .count();
+
+        (calls != v.times).then(|| {
+          format!(
+            "{} expectation {} wanted {} matching calls but got {}",
+            self.path,
+            i + 1,
+            v.times,
+            calls
+          )
+        })
+      })
+      .collect::<Vec<String>>();
+
+    match failures.is_empty() {
+      true => Ok(()),
+      false => Err(failures.join("\n"))
+    }
+  }
+
+  pub fn verify(&self) {
+    if let Err(err) = self.check() {
+      panic!("{}", err);
+    }
+  }
+
+  pub async fn call(&self, req: Request<Req>) -> Result<Response<Res>, Status> {
+    let queued = lock(&self.queue).pop_front();
+    let result = match queued {
+      Some(v) => v,
+      None => match &*lock(&self.handler) {
+        Some(handler) => handler(&req.message),
+        None => Err(Status::unimplemented(format!(
+          "No response programmed for {}",
+          self.path
+        )))
+      }
+    };
+
+    lock(&self.requests).push(req);
+
+    result.map(Response::new)
+  }
+}
+
+impl<T: Send + 'static, Res> MockMethod<Vec<T>, Res> {
+  pub async fn call_streaming(
+    &self,
+    req: Request<Streaming<T>>
+  ) -> Result<Response<Res>, Status> {
+    let message = req.message.collect().await?;
+
+    self
+      .call(Request {
+        metadata: req.metadata,
+        message
+      })
+      .await
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::runtime::block_on;
+  use crate::runtime::status::Code;
+
+  #[test]
+  fn program_responses() {
+    let method = MockMethod::<i32, i32>::new("/test.Counter/Increment");
+
+    method
+      .returns(|v| Ok(v + 1))
+      .returns_once(Err(Status::internal("Down")));
+
+    let first = block_on(method.call(Request::new(1))).map(|v| v.message);
+    let second = block_on(method.call(Request::new(1))).map(|v| v.message);
+
+    assert_eq!(first, Err(Status::internal("Down")));
+    assert_eq!(second, Ok(2));
+    assert_eq!(method.calls(), 2);
+  }
+
+  #[test]
+  fn verify_expectations() {
+    let method = MockMethod::<i32, i32>::new("/test.Counter/Increment");
+
+    method
+      .expect(2, |v| *v > 0)
+      .expect_calls(3);
+
+    for v in [1, 2, -1] {
+      let result = block_on(method.call(Request::new(v))).map_err(|v| v.code);
+
+      assert_eq!(result.map(|_| ()), Err(Code::Unimplemented));
+    }
+
+    assert_eq!(method.check(), Ok(()));
+
+    method.expect(1, |v| *v == 3);
+
+    assert_eq!(
+      method.check(),
+      Err(
+        "/test.Counter/Increment expectation 3 wanted 1 matching calls but \
+         got 0"
+          .to_string()
+      )
+    );
+  }
+
+  #[test]
+  fn collect_streams() -> Result<(), Status> {
+    let method = MockMethod::<Vec<i32>, i32>::new("/test.Counter/Sum");
+
+    method.returns(|v| Ok(v.iter().sum()));
+
+    let req = Request::new(Streaming::iter([Ok(1), Ok(2)]));
+    let response = block_on(method.call_streaming(req))?;
+
+    assert_eq!(response.message, 3);
+    assert_eq!(method.requests()[0].message, [1, 2]);
+
+    Ok(())
+  }
+}
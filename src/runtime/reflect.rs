@@ -0,0 +1,502 @@
+use super::descriptor::{
+  DescriptorProto,
+  FieldDescriptorProto,
+  FileDescriptorProto
+};
+use super::encoding::{self, DecodeError, Message, WireType};
+use super::well_known;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+use std::sync::OnceLock;
+
+// Static description of a generated message, which tools can walk to handle
+// any message without knowing its type
+#[derive(Debug)]
+pub struct MessageDescriptor {
+  pub name: &'static str,
+  // Name qualified with its package and any messages it's nested in, as in
+  // "pkg.Outer.Inner"
+  pub full_name: &'static str,
+  pub fields: &'static [FieldDescriptor]
+}
+
+impl MessageDescriptor {
+  pub const EMPTY: MessageDescriptor = MessageDescriptor {
+    name: "",
+    full_name: "",
+    fields: &[]
+  };
+
+  pub fn field(&self, name: &str) -> Option<&'static FieldDescriptor> {
+    self
+      .fields
+      .iter()
+      .find(|v| v.name == name)
+  }
+
+  pub fn field_by_number(
+    &self,
+    number: u32
+  ) -> Option<&'static FieldDescriptor> {
+    self
+      .fields
+      .iter()
+      .find(|v| v.number == number)
+  }
+}
+
+#[derive(Debug)]
+pub struct FieldDescriptor {
+  pub name: &'static str,
+  pub json_name: &'static str,
+  pub number: u32,
+  pub label: Label,
+  pub r#type: FieldType
+}
+
+// Fields are always optional until the lexer reads any other label
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Label {
+  Optional
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum FieldType {
+  Int32,
+  Int64,
+  Bool,
+  String,
+  Bytes,
+  Message(MessageType)
+}
+
+// Type of a message field, which is only resolved to its descriptor when it's
+// used so messages can contain themselves
+#[derive(Clone, Copy)]
+pub enum MessageType {
+  // Descriptor of a generated message
+  Static(fn() -> &'static MessageDescriptor),
+  // Descriptor built from a schema while the program runs, which is set once
+  // every message of the schema has been described
+  Dynamic(&'static OnceLock<&'static MessageDescriptor>)
+}
+
+impl MessageType {
+  pub fn descriptor(&self) -> &'static MessageDescriptor {
+    match self {
+      MessageType::Static(f) => f(),
+      MessageType::Dynamic(v) => v
+        .get()
+        .unwrap_or(&&MessageDescriptor::EMPTY)
+    }
+  }
+}
+
+// Types only show their name, since they may well contain themselves
+impl Debug for MessageType {
+  fn fmt(&self, f: &mut Formatter) -> FmtResult {
+    write!(f, "MessageType({})", self.descriptor().full_name)
+  }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+  Int32(i32),
+  Int64(i64),
+  Bool(bool),
+  String(String),
+  Bytes(Vec<u8>),
+  Message(DynamicMessage)
+}
+
+impl Value {
+  fn matches(&self, r#type: &FieldType) -> bool {
+    match (self, r#type) {
+      (Value::Int32(_), FieldType::Int32) => true,
+      (Value::Int64(_), FieldType::Int64) => true,
+      (Value::Bool(_), FieldType::Bool) => true,
+      (Value::String(_), FieldType::String) => true,
+      (Value::Bytes(_), FieldType::Bytes) => true,
+      (Value::Message(v), FieldType::Message(r#type)) => {
+        std::ptr::eq(v.descriptor, r#type.descriptor())
+      }
+      _ => false
+    }
+  }
+}
+
+// Message of any type, read and written by the names of its fields as given
+// by its descriptor. Fields that are unset take their default value when
+// encoded, just as those of generated messages do
+#[derive(Clone)]
+pub struct DynamicMessage {
+  descriptor: &'static MessageDescriptor,
+  values: BTreeMap<u32, Value>
+}
+
+// Messages show the name of their type rather than the whole descriptor
+impl Debug for DynamicMessage {
+  fn fmt(&self, f: &mut Formatter) -> FmtResult {
+    f.debug_struct("DynamicMessage")
+      .field("type", &self.descriptor.full_name)
+      .field("values", &self.values)
+      .finish()
+  }
+}
+
+impl PartialEq for DynamicMessage {
+  fn eq(&self, other: &Self) -> bool {
+    std::ptr::eq(self.descriptor, other.descriptor)
+      && self.values == other.values
+  }
+}
+
+impl DynamicMessage {
+  pub fn new(descriptor: &'static MessageDescriptor) -> Self {
+    Self {
+      descriptor,
+      values: BTreeMap::new()
+    }
+  }
+
+  pub fn decode(
+    descriptor: &'static MessageDescriptor,
+    buf: &[u8]
+  ) -> Result<Self, DecodeError> {
+    let mut message = Self::new(descriptor);
+
+    message.merge(buf)?;
+
+    Ok(message)
+  }
+
+  pub fn descriptor(&self) -> &'static MessageDescriptor {
+    self.descriptor
+  }
+
+  pub fn get(&self, name: &str) -> Option<&Value> {
+    self
+      .descriptor
+      .field(name)
+      .and_then(|v| self.values.get(&v.number))
+  }
+
+  pub fn get_mut(&mut self, name: &str) -> Option<&mut Value> {
+    self
+      .descriptor
+      .field(name)
+      .and_then(|v| self.values.get_mut(&v.number))
+  }
+
+  pub fn set(&mut self, name: &str, value: Value) -> Result<(), String> {
+    let field = self
+      .descriptor
+      .field(name)
+      .ok_or_else(|| format!("Unknown field {}", name))?;
+
+    if !value.matches(&field.r#type) {
+      return Err(format!("Mismatched type for field {}", name));
+    }
+
+    self.values.insert(field.number, value);
+
+    Ok(())
+  }
+
+  pub fn clear(&mut self, name: &str) -> Option<Value> {
+    self
+      .descriptor
+      .field(name)
+      .and_then(|v| self.values.remove(&v.number))
+  }
+
+  // Every field that's set, in the order of their numbers
+  pub fn fields(
+    &self
+  ) -> impl Iterator<Item = (&'static FieldDescriptor, &Value)> {
+    self
+      .values
+      .iter()
+      .filter_map(|(number, value)| {
+        Some((
+          self
+            .descriptor
+            .field_by_number(*number)?,
+          value
+        ))
+      })
+  }
+}
+
+impl Message for DynamicMessage {
+  fn encode(&self, buf: &mut Vec<u8>) {
+    for (field, value) in self.fields() {
+      match value {
+        Value::Int32(v) => encoding::int32::encode(field.number, v, buf),
+        Value::Int64(v) => encoding::int64::encode(field.number, v, buf),
+        Value::Bool(v) => encoding::bool::encode(field.number, v, buf),
+        Value::String(v) => encoding::string::encode(field.number, v, buf),
+        Value::Bytes(v) => encoding::bytes::encode(field.number, v, buf),
+        Value::Message(v) => encoding::message::encode(field.number, v, buf)
+      }
+    }
+  }
+
+  // Fields that the descriptor doesn't know of are skipped. Message fields
+  // are merged through the same helper as generated ones, so bytes nested
+  // past its recursion limit fail to decode
+  fn merge_field(
+    &mut self,
+    number: u32,
+    wire: WireType,
+    buf: &mut &[u8]
+  ) -> Result<(), DecodeError> {
+    let Some(field) = self.descriptor.field_by_number(number) else {
+      return encoding::skip_field(wire, buf);
+    };
+    let value = self
+      .values
+      .entry(number)
+      .or_insert_with(|| match field.r#type {
+        FieldType::Int32 => Value::Int32(0),
+        FieldType::Int64 => Value::Int64(0),
+        FieldType::Bool => Value::Bool(false),
+        FieldType::String => Value::String(String::new()),
+        FieldType::Bytes => Value::Bytes(Vec::new()),
+        FieldType::Message(v) => {
+          Value::Message(DynamicMessage::new(v.descriptor()))
+        }
+      });
+
+    match value {
+      Value::Int32(v) => encoding::int32::merge(wire, v, buf),
+      Value::Int64(v) => encoding::int64::merge(wire, v, buf),
+      Value::Bool(v) => encoding::bool::merge(wire, v, buf),
+      Value::String(v) => encoding::string::merge(wire, v, buf),
+      Value::Bytes(v) => encoding::bytes::merge(wire, v, buf),
+      Value::Message(v) => encoding::message::merge(wire, v, buf)
+    }
+  }
+}
+
+fn leak(value: &str) -> &'static str {
+  Box::leak(value.to_string().into_boxed_str())
+}
+
+fn collect_messages<'a>(
+  scope: &str,
+  messages: &'a [DescriptorProto],
+  result: &mut Vec<(String, &'a DescriptorProto)>
+) {
+  for message in messages {
+    let name = format!("{}.{}", scope, message.name);
+
+    collect_messages(&name, &message.nested_type, result);
+    result.push((name, message));
+  }
+}
+
+// Descriptors of every message in a schema, keyed by their full names. They
+// live for the rest of the program just as those of generated messages do,
+// so tools should only build them once for each schema they load
+pub fn leak_descriptors(
+  files: &[FileDescriptorProto]
+) -> HashMap<String, &'static MessageDescriptor> {
+  let mut messages = Vec::new();
+
+  for file in files {
+    let scope = match file.package.is_empty() {
+      true => String::new(),
+      false => format!(".{}", file.package)
+    };
+
+    collect_messages(&scope, &file.message_type, &mut messages);
+  }
+
+  // Every message gets a slot before any is described, so fields can refer
+  // to messages that are described after them
+  let slots = messages
+    .iter()
+    .map(|(name, _)| {
+      let slot: &'static OnceLock<&'static MessageDescriptor> =
+        Box::leak(Box::new(OnceLock::new()));
+
+      (name.clone(), slot)
+    })
+    .collect::<HashMap<String, &'static OnceLock<&'static MessageDescriptor>>>(
+    );
+  let describe_field = |field: &FieldDescriptorProto| {
+    let r#type = match field.r#type {
+      FieldDescriptorProto::TYPE_INT32 => FieldType::Int32,
+      FieldDescriptorProto::TYPE_INT64 => FieldType::Int64,
+      FieldDescriptorProto::TYPE_BOOL => FieldType::Bool,
+      FieldDescriptorProto::TYPE_STRING => FieldType::String,
+      FieldDescriptorProto::TYPE_BYTES => FieldType::Bytes,
+      // Well-known types are built in rather than read from the schema
+      _ => match well_known::descriptor(&field.type_name) {
+        Some(descriptor) => FieldType::Message(MessageType::Static(descriptor)),
+        None => {
+          let slot = slots
+            .get(&field.type_name)
+            .copied()
+            .unwrap_or_else(|| Box::leak(Box::new(OnceLock::new())));
+
+          FieldType::Message(MessageType::Dynamic(slot))
+        }
+      }
+    };
+
+    FieldDescriptor {
+      name: leak(&field.name),
+      json_name: leak(&field.json_name),
+      number: field.number as u32,
+      label: Label::Optional,
+      r#type
+    }
+  };
+
+  messages
+    .into_iter()
+    .map(|(name, message)| {
+      let fields = message
+        .field
+        .iter()
+        .map(describe_field)
+        .collect::<Vec<FieldDescriptor>>();
+      let descriptor: &'static MessageDescriptor =
+        Box::leak(Box::new(MessageDescriptor {
+          name: leak(&message.name),
+          full_name: leak(name.trim_start_matches('.')),
+          fields: Box::leak(fields.into_boxed_slice())
+        }));
+      let _ = slots[&name].set(descriptor);
+
+      (descriptor.full_name.to_string(), descriptor)
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  static NODE: MessageDescriptor = MessageDescriptor {
+    name: "Node",
+    full_name: "test.Node",
+    fields: &[
+      FieldDescriptor {
+        name: "value",
+        json_name: "value",
+        number: 1,
+        label: Label::Optional,
+        r#type: FieldType::Int32
+      },
+      FieldDescriptor {
+        name: "next_node",
+        json_name: "nextNode",
+        number: 2,
+        label: Label::Optional,
+        r#type: FieldType::Message(MessageType::Static(|| &NODE))
+      }
+    ]
+  };
+
+  #[test]
+  fn dynamic_round_trip() -> Result<(), DecodeError> {
+    let mut next = DynamicMessage::new(&NODE);
+    let mut message = DynamicMessage::new(&NODE);
+
+    assert_eq!(next.set("value", Value::Int32(2)), Ok(()));
+    assert_eq!(message.set("value", Value::Int32(1)), Ok(()));
+    assert_eq!(message.set("next_node", Value::Message(next)), Ok(()));
+
+    let result = DynamicMessage::decode(&NODE, &message.encode_to_vec())?;
+    let Some(Value::Message(next)) = result.get("next_node") else {
+      panic!("Missing next node");
+    };
+
+    assert_eq!(result, message);
+    assert_eq!(next.get("value"), Some(&Value::Int32(2)));
+
+    Ok(())
+  }
+
+  #[test]
+  fn limit_recursion() {
+    let mut headers = Vec::new();
+    let mut len = 0;
+
+    // Nodes nested in one another, built from the innermost out
+    for _ in 0..100_000 {
+      let mut header = Vec::new();
+
+      encoding::encode_key(2, WireType::LengthDelimited, &mut header);
+      encoding::encode_varint(len as u64, &mut header);
+      len += header.len();
+      headers.push(header);
+    }
+
+    let bytes = headers
+      .into_iter()
+      .rev()
+      .flatten()
+      .collect::<Vec<u8>>();
+
+    assert_eq!(
+      DynamicMessage::decode(&NODE, &bytes).err(),
+      Some(DecodeError::new("Recursion limit exceeded"))
+    );
+  }
+
+  #[test]
+  fn reject_mismatched_values() {
+    let mut message = DynamicMessage::new(&NODE);
+
+    assert_eq!(
+      message.set("value", Value::Bool(true)),
+      Err("Mismatched type for field value".to_string())
+    );
+    assert_eq!(
+      message.set("missing", Value::Int32(1)),
+      Err("Unknown field missing".to_string())
+    );
+  }
+
+  #[test]
+  fn describe_schema() {
+    let field =
+      |name: &str, r#type: i32, type_name: &str| FieldDescriptorProto {
+        name: name.to_string(),
+        number: 1,
+        r#type,
+        type_name: type_name.to_string(),
+        json_name: name.to_string(),
+        ..Default::default()
+      };
+    let file = FileDescriptorProto {
+      package: "test".to_string(),
+      message_type: vec![DescriptorProto {
+        name: "Node".to_string(),
+        field: vec![field(
+          "next",
+          FieldDescriptorProto::TYPE_MESSAGE,
+          ".test.Node"
+        )],
+        nested_type: vec![DescriptorProto {
+          name: "Leaf".to_string(),
+          field: vec![field("label", FieldDescriptorProto::TYPE_STRING, "")],
+          ..Default::default()
+        }]
+      }],
+      ..Default::default()
+    };
+    let result = leak_descriptors(&[file]);
+    let node = result["test.Node"];
+    let FieldType::Message(next) = node.fields[0].r#type else {
+      panic!("Missing message field");
+    };
+
+    assert!(std::ptr::eq(next.descriptor(), node));
+    assert_eq!(result["test.Node.Leaf"].fields[0].name, "label");
+  }
+}
@@ -0,0 +1,149 @@
+use super::encoding::Message;
+use super::reflect::{DynamicMessage, MessageDescriptor, Value};
+use super::well_known::{self, Any};
+use std::collections::HashMap;
+
+// Message types found by their full names, which lets JSON and text format
+// expand the messages packed in Any fields. Generated packages add each of
+// their messages with "register_types", and well-known types are always
+// registered
+#[derive(Clone, Debug)]
+pub struct TypeRegistry {
+  types: HashMap<&'static str, &'static MessageDescriptor>
+}
+
+impl Default for TypeRegistry {
+  fn default() -> Self {
+    well_known::DESCRIPTORS
+      .into_iter()
+      .fold(
+        Self {
+          types: HashMap::new()
+        },
+        |acc, v| acc.add_descriptor(v())
+      )
+  }
+}
+
+impl TypeRegistry {
+  pub fn add<M: Message>(self) -> Self {
+    self.add_descriptor(M::descriptor())
+  }
+
+  pub fn add_descriptor(
+    mut self,
+    descriptor: &'static MessageDescriptor
+  ) -> Self {
+    self
+      .types
+      .insert(descriptor.full_name, descriptor);
+
+    self
+  }
+
+  // Descriptor of a type given its full name, with or without its leading dot
+  pub fn get(&self, name: &str) -> Option<&'static MessageDescriptor> {
+    self
+      .types
+      .get(name.trim_start_matches('.'))
+      .copied()
+  }
+
+  // Descriptor of the type named by the URL of an Any field, which is named
+  // by whatever follows its last "/"
+  pub fn resolve(
+    &self,
+    type_url: &str
+  ) -> Result<&'static MessageDescriptor, String> {
+    let name = type_url
+      .rsplit('/')
+      .next()
+      .unwrap_or_default();
+
+    self
+      .get(name)
+      .ok_or_else(|| format!("Unknown type {}", type_url))
+  }
+
+  // Message held by a dynamic Any along with its type URL, when its type is
+  // registered. Messages are only expanded when their descriptor holds every
+  // field they were encoded with, which is checked by encoding them again
+  pub(crate) fn unpack_any(
+    &self,
+    message: &DynamicMessage
+  ) -> Option<(String, DynamicMessage)> {
+    if !is_any(message.descriptor()) {
+      return None;
+    }
+
+    let Some(Value::String(type_url)) = message.get("type_url") else {
+      return None;
+    };
+    let value = match message.get("value") {
+      Some(Value::Bytes(value)) => value.as_slice(),
+      _ => &[]
+    };
+    let packed =
+      DynamicMessage::decode(self.resolve(type_url).ok()?, value).ok()?;
+
+    match packed.encode_to_vec() == value {
+      true => Some((type_url.clone(), packed)),
+      false => None
+    }
+  }
+}
+
+pub(crate) fn is_any(descriptor: &MessageDescriptor) -> bool {
+  std::ptr::eq(descriptor, Any::descriptor())
+}
+
+// Dynamic Any holding the encoding of a message of the type named by a URL
+pub(crate) fn pack_any(
+  type_url: &str,
+  value: Vec<u8>
+) -> Result<DynamicMessage, String> {
+  let mut any = DynamicMessage::new(Any::descriptor());
+
+  any.set("type_url", Value::String(type_url.to_string()))?;
+  any.set("value", Value::Bytes(value))?;
+
+  Ok(any)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::runtime::well_known::{Duration, Timestamp};
+
+  #[test]
+  fn register_well_known_types() -> Result<(), String> {
+    let registry = TypeRegistry::default();
+    let time =
+      registry.resolve("type.googleapis.com/google.protobuf.Timestamp")?;
+
+    assert!(std::ptr::eq(time, Timestamp::descriptor()));
+    assert!(registry
+      .get(".google.protobuf.Duration")
+      .is_some());
+    assert!(registry
+      .resolve("type.googleapis.com/pkg.Missing")
+      .is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn add_types() {
+    let registry = TypeRegistry {
+      types: HashMap::new()
+    }
+    .add::<Duration>();
+
+    assert!(registry
+      .get("google.protobuf.Duration")
+      .is_some());
+    assert!(registry
+      .get("google.protobuf.Timestamp")
+      .is_none());
+  }
+}
@@ -0,0 +1,298 @@
+use super::client::{CallOptions, Transport};
+use super::encoding::Message;
+use super::status::{Request, Response, Status};
+use super::stream::Streaming;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+// Server side of a generated service, which dispatches encoded requests to
+// the method named by the full path of the call
+pub trait Service: Send + Sync + 'static {
+  // Fully-qualified name of the service, as in "pkg.Service"
+  fn name(&self) -> &'static str;
+
+  fn call<'a>(
+    &'a self,
+    path: &'a str,
+    req: Request<Streaming<Vec<u8>>>
+  ) -> BoxFuture<'a, Result<Response<Streaming<Vec<u8>>>, Status>>;
+}
+
+fn decode_all<Req>(req: Streaming<Vec<u8>>) -> Streaming<Req>
+where
+  Req: Message + Default + Send + 'static
+{
+  req.map(|v| {
+    Req::decode(&v).map_err(|err| Status::invalid_argument(err.to_string()))
+  })
+}
+
+fn encode_all<Res>(res: Streaming<Res>) -> Streaming<Vec<u8>>
+where
+  Res: Message + Send + 'static
+{
+  res.map(|v| Ok(v.encode_to_vec()))
+}
+
+// Read the only message of a request that isn't streamed
+async fn single<Req>(mut stream: Streaming<Req>) -> Result<Req, Status>
+where
+  Req: Send + 'static
+{
+  stream
+    .message()
+    .await
+    .unwrap_or_else(|| Err(Status::invalid_argument("Missing request message")))
+}
+
+// Decode a request, pass it to a method of the service and encode whatever
+// it returns, which is all each arm of a generated dispatcher needs to do
+pub async fn handle_unary<Req, Res, F, Fut>(
+  req: Request<Streaming<Vec<u8>>>,
+  method: F
+) -> Result<Response<Streaming<Vec<u8>>>, Status>
+where
+  Req: Message + Default + Send + 'static,
+  Res: Message + Send + 'static,
+  F: FnOnce(Request<Req>) -> Fut + Send,
+  Fut: Future<Output = Result<Response<Res>, Status>> + Send
+{
+  let message = single(decode_all(req.message)).await?;
+  let response = method(Request {
+    metadata: req.metadata,
+    message
+  })
+  .await?;
+
+  Ok(Response {
+    metadata: response.metadata,
+    message: Streaming::once(Ok(response.message.encode_to_vec()))
+  })
+}
+
+pub async fn handle_client_streaming<Req, Res, F, Fut>(
+  req: Request<Streaming<Vec<u8>>>,
+  method: F
+) -> Result<Response<Streaming<Vec<u8>>>, Status>
+where
+  Req: Message + Default + Send + 'static,
+  Res: Message + Send + 'static,
+  F: FnOnce(Request<Streaming<Req>>) -> Fut + Send,
+  Fut: Future<Output = Result<Response<Res>, Status>> + Send
+{
+  let response = method(Request {
+    metadata: req.metadata,
+    message: decode_all(req.message)
+  })
+  .await?;
+
+  Ok(Response {
+    metadata: response.metadata,
+    message: Streaming::once(Ok(response.message.encode_to_vec()))
+  })
+}
+
+pub async fn handle_server_streaming<Req, Res, F, Fut>(
+  req: Request<Streaming<Vec<u8>>>,
+  method: F
+) -> Result<Response<Streaming<Vec<u8>>>, Status>
+where
+  Req: Message + Default + Send + 'static,
+  Res: Message + Send + 'static,
+  F: FnOnce(Request<Req>) -> Fut + Send,
+  Fut: Future<Output = Result<Response<Streaming<Res>>, Status>> + Send
+{
+  let message = single(decode_all(req.message)).await?;
+  let response = method(Request {
+    metadata: req.metadata,
+    message
+  })
+  .await?;
+
+  Ok(Response {
+    metadata: response.metadata,
+    message: encode_all(response.message)
+  })
+}
+
+pub async fn handle_streaming<Req, Res, F, Fut>(
+  req: Request<Streaming<Vec<u8>>>,
+  method: F
+) -> Result<Response<Streaming<Vec<u8>>>, Status>
+where
+  Req: Message + Default + Send + 'static,
+  Res: Message + Send + 'static,
+  F: FnOnce(Request<Streaming<Req>>) -> Fut + Send,
+  Fut: Future<Output = Result<Response<Streaming<Res>>, Status>> + Send
+{
+  let response = method(Request {
+    metadata: req.metadata,
+    message: decode_all(req.message)
+  })
+  .await?;
+
+  Ok(Response {
+    metadata: response.metadata,
+    message: encode_all(response.message)
+  })
+}
+
+// Collection of services that routes each call to the service named in its
+// path, which any byte-level transport can hand requests to
+#[derive(Clone, Default)]
+pub struct Router {
+  services: Vec<Arc<dyn Service>>
+}
+
+impl Router {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn add_service(mut self, service: impl Service) -> Self {
+    self.services.push(Arc::new(service));
+
+    self
+  }
+
+  pub async fn call(
+    &self,
+    path: &str,
+    req: Request<Streaming<Vec<u8>>>
+  ) -> Result<Response<Streaming<Vec<u8>>>, Status> {
+    // Paths are written as "/pkg.Service/Method"
+    let name = path
+      .trim_start_matches('/')
+      .split('/')
+      .next()
+      .unwrap_or_default();
+    let service = self
+      .services
+      .iter()
+      .find(|v| v.name() == name)
+      .ok_or_else(|| {
+        Status::unimplemented(format!("Unknown service {}", name))
+      })?;
+
+    service.call(path, req).await
+  }
+}
+
+// Routers are transports in their own right, which lets clients call
+// services within the same process without any network in between
+impl Transport for Router {
+  async fn call(
+    &self,
+    path: &str,
+    body: Streaming<Vec<u8>>,
+    options: CallOptions
+  ) -> Result<Response<Streaming<Vec<u8>>>, Status> {
+    let req = Request {
+      metadata: options.metadata,
+      message: body
+    };
+
+    Router::call(self, path, req).await
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::runtime::block_on;
+  use crate::runtime::client::unary;
+  use crate::runtime::encoding::{self, DecodeError, WireType};
+  use crate::runtime::status::Code;
+
+  #[derive(Debug, Default, PartialEq)]
+  struct Count {
+    value: i32
+  }
+
+  impl Message for Count {
+    fn encode(&self, buf: &mut Vec<u8>) {
+      encoding::int32::encode(1, &self.value, buf);
+    }
+
+    fn merge_field(
+      &mut self,
+      number: u32,
+      wire: WireType,
+      buf: &mut &[u8]
+    ) -> Result<(), DecodeError> {
+      match number {
+        1 => encoding::int32::merge(wire, &mut self.value, buf),
+        _ => encoding::skip_field(wire, buf)
+      }
+    }
+  }
+
+  // Service written the way a generated dispatcher would be
+  struct Counter;
+
+  impl Service for Counter {
+    fn name(&self) -> &'static str {
+      "test.Counter"
+    }
+
+    fn call<'a>(
+      &'a self,
+      path: &'a str,
+      req: Request<Streaming<Vec<u8>>>
+    ) -> BoxFuture<'a, Result<Response<Streaming<Vec<u8>>>, Status>> {
+      match path {
+        "/test.Counter/Increment" => {
+          Box::pin(handle_unary(req, |req: Request<Count>| async move {
+            Ok(Response::new(Count {
+              value: req.message.value + 1
+            }))
+          }))
+        }
+        _ => Box::pin(async move { Err(Status::unimplemented(path)) })
+      }
+    }
+  }
+
+  #[test]
+  fn route_in_process() -> Result<(), Status> {
+    let router = Router::new().add_service(Counter);
+    let req = Count {
+      value: 41
+    };
+    let response: Response<Count> = block_on(unary(
+      &router,
+      "/test.Counter/Increment",
+      &req,
+      CallOptions::default()
+    ))?;
+
+    assert_eq!(response.message.value, 42);
+
+    Ok(())
+  }
+
+  #[test]
+  fn route_unknown() {
+    let router = Router::new().add_service(Counter);
+    let req = Request::new(Streaming::empty());
+    let missing_service = block_on(router.call("/test.Other/Increment", req));
+    let req = Request::new(Streaming::empty());
+    let missing_method = block_on(router.call("/test.Counter/Reset", req));
+
+    assert_eq!(
+      missing_service
+        .map(|_| ())
+        .map_err(|v| v.code),
+      Err(Code::Unimplemented)
+    );
+    assert_eq!(
+      missing_method
+        .map(|_| ())
+        .map_err(|v| v.code),
+      Err(Code::Unimplemented)
+    );
+  }
+}
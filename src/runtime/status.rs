@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+// Metadata sent alongside a request or response, such as gRPC headers
+pub type Metadata = HashMap<String, String>;
+
+// Status codes returned by services, numbered as they are in gRPC
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Code {
+  Ok = 0,
+  Cancelled = 1,
+  Unknown = 2,
+  InvalidArgument = 3,
+  DeadlineExceeded = 4,
+  NotFound = 5,
+  AlreadyExists = 6,
+  PermissionDenied = 7,
+  ResourceExhausted = 8,
+  FailedPrecondition = 9,
+  Aborted = 10,
+  OutOfRange = 11,
+  Unimplemented = 12,
+  Internal = 13,
+  Unavailable = 14,
+  DataLoss = 15,
+  Unauthenticated = 16
+}
+
+impl From<i32> for Code {
+  fn from(value: i32) -> Self {
+    match value {
+      0 => Code::Ok,
+      1 => Code::Cancelled,
+      3 => Code::InvalidArgument,
+      4 => Code::DeadlineExceeded,
+      5 => Code::NotFound,
+      6 => Code::AlreadyExists,
+      7 => Code::PermissionDenied,
+      8 => Code::ResourceExhausted,
+      9 => Code::FailedPrecondition,
+      10 => Code::Aborted,
+      11 => Code::OutOfRange,
+      12 => Code::Unimplemented,
+      13 => Code::Internal,
+      14 => Code::Unavailable,
+      15 => Code::DataLoss,
+      16 => Code::Unauthenticated,
+      _ => Code::Unknown
+    }
+  }
+}
+
+// Error returned by a service method, carrying a code and a message for the
+// caller
+#[derive(Clone, Debug, PartialEq)]
+pub struct Status {
+  pub code: Code,
+  pub message: String
+}
+
+impl Status {
+  pub fn new(code: Code, message: impl Into<String>) -> Self {
+    Self {
+      code,
+      message: message.into()
+    }
+  }
+
+  pub fn cancelled(message: impl Into<String>) -> Self {
+    Self::new(Code::Cancelled, message)
+  }
+
+  pub fn deadline_exceeded(message: impl Into<String>) -> Self {
+    Self::new(Code::DeadlineExceeded, message)
+  }
+
+  pub fn internal(message: impl Into<String>) -> Self {
+    Self::new(Code::Internal, message)
+  }
+
+  pub fn invalid_argument(message: impl Into<String>) -> Self {
+    Self::new(Code::InvalidArgument, message)
+  }
+
+  pub fn not_found(message: impl Into<String>) -> Self {
+    Self::new(Code::NotFound, message)
+  }
+
+  pub fn resource_exhausted(message: impl Into<String>) -> Self {
+    Self::new(Code::ResourceExhausted, message)
+  }
+
+  pub fn unavailable(message: impl Into<String>) -> Self {
+    Self::new(Code::Unavailable, message)
+  }
+
+  pub fn unimplemented(message: impl Into<String>) -> Self {
+    Self::new(Code::Unimplemented, message)
+  }
+}
+
+impl Display for Status {
+  fn fmt(&self, f: &mut Formatter) -> FmtResult {
+    write!(f, "{:?}: {}", self.code, self.message)
+  }
+}
+
+impl Error for Status {}
+
+// A message sent to a service method along with its metadata
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Request<T> {
+  pub metadata: Metadata,
+  pub message: T
+}
+
+impl<T> Request<T> {
+  pub fn new(message: T) -> Self {
+    Self {
+      metadata: Metadata::new(),
+      message
+    }
+  }
+
+  pub fn get_ref(&self) -> &T {
+    &self.message
+  }
+
+  pub fn into_inner(self) -> T {
+    self.message
+  }
+}
+
+// A message returned from a service method along with its metadata
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Response<T> {
+  pub metadata: Metadata,
+  pub message: T
+}
+
+impl<T> Response<T> {
+  pub fn new(message: T) -> Self {
+    Self {
+      metadata: Metadata::new(),
+      message
+    }
+  }
+
+  pub fn get_ref(&self) -> &T {
+    &self.message
+  }
+
+  pub fn into_inner(self) -> T {
+    self.message
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn code_from_number() {
+    assert_eq!(Code::from(12), Code::Unimplemented);
+    assert_eq!(Code::from(99), Code::Unknown);
+    assert_eq!(Code::Unauthenticated as i32, 16);
+  }
+}
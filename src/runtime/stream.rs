@@ -0,0 +1,229 @@
+use super::status::Status;
+use futures_core::Stream;
+use std::collections::VecDeque;
+use std::future::poll_fn;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+// Stream of messages sent to or from a streaming method, where a failed
+// message ends the stream with the status of the call
+pub struct Streaming<T> {
+  inner: Pin<Box<dyn Stream<Item = Result<T, Status>> + Send>>
+}
+
+impl<T: Send + 'static> Streaming<T> {
+  pub fn new<S>(stream: S) -> Self
+  where
+    S: Stream<Item = Result<T, Status>> + Send + 'static
+  {
+    Self {
+      inner: Box::pin(stream)
+    }
+  }
+
+  pub fn empty() -> Self {
+    Self::iter(Vec::new())
+  }
+
+  pub fn once(item: Result<T, Status>) -> Self {
+    Self::iter(vec![item])
+  }
+
+  pub fn iter<I>(items: I) -> Self
+  where
+    I: IntoIterator<Item = Result<T, Status>>,
+    I::IntoIter: Send + 'static
+  {
+    Self::new(Iter(items.into_iter()))
+  }
+
+  pub async fn message(&mut self) -> Option<Result<T, Status>> {
+    poll_fn(|cx| self.inner.as_mut().poll_next(cx)).await
+  }
+
+  // Collect every message until the stream ends, failing on the first error
+  pub async fn collect(mut self) -> Result<Vec<T>, Status> {
+    let mut result = Vec::new();
+
+    while let Some(item) = self.message().await {
+      result.push(item?);
+    }
+
+    Ok(result)
+  }
+
+  pub fn map<U, F>(self, mut f: F) -> Streaming<U>
+  where
+    U: Send + 'static,
+    F: FnMut(T) -> Result<U, Status> + Send + 'static
+  {
+    Streaming::new(Map(self, move |v: Result<T, Status>| v.and_then(&mut f)))
+  }
+}
+
+impl<T> Stream for Streaming<T> {
+  type Item = Result<T, Status>;
+
+  fn poll_next(
+    mut self: Pin<&mut Self>,
+    cx: &mut Context
+  ) -> Poll<Option<Self::Item>> {
+    self.inner.as_mut().poll_next(cx)
+  }
+}
+
+struct Iter<I>(I);
+
+// Neither the iterator nor the function of a map are ever pinned themselves
+impl<I> Unpin for Iter<I> {}
+
+impl<I: Iterator> Stream for Iter<I> {
+  type Item = I::Item;
+
+  fn poll_next(
+    mut self: Pin<&mut Self>,
+    _: &mut Context
+  ) -> Poll<Option<Self::Item>> {
+    Poll::Ready(self.0.next())
+  }
+}
+
+struct Map<S, F>(S, F);
+
+impl<S: Unpin, F> Unpin for Map<S, F> {}
+
+impl<S, F, T> Stream for Map<S, F>
+where
+  S: Stream + Unpin,
+  F: FnMut(S::Item) -> T
+{
+  type Item = T;
+
+  fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<T>> {
+    let this = &mut *self;
+
+    Pin::new(&mut this.0)
+      .poll_next(cx)
+      .map(|v| v.map(&mut this.1))
+  }
+}
+
+#[derive(Default)]
+struct Shared<T> {
+  items: VecDeque<Result<T, Status>>,
+  senders: usize,
+  waker: Option<Waker>
+}
+
+// Sending half of a channel, which ends the stream once every sender has
+// been dropped
+pub struct Sender<T> {
+  shared: Arc<Mutex<Shared<T>>>
+}
+
+impl<T> Sender<T> {
+  pub fn send(&self, item: Result<T, Status>) {
+    let mut shared = self.shared.lock().unwrap();
+
+    shared.items.push_back(item);
+
+    if let Some(waker) = shared.waker.take() {
+      waker.wake();
+    }
+  }
+}
+
+impl<T> Clone for Sender<T> {
+  fn clone(&self) -> Self {
+    self.shared.lock().unwrap().senders += 1;
+
+    Self {
+      shared: self.shared.clone()
+    }
+  }
+}
+
+impl<T> Drop for Sender<T> {
+  fn drop(&mut self) {
+    let mut shared = self.shared.lock().unwrap();
+
+    shared.senders -= 1;
+
+    if let Some(waker) = shared.waker.take() {
+      waker.wake();
+    }
+  }
+}
+
+struct Receiver<T> {
+  shared: Arc<Mutex<Shared<T>>>
+}
+
+impl<T> Stream for Receiver<T> {
+  type Item = Result<T, Status>;
+
+  fn poll_next(
+    self: Pin<&mut Self>,
+    cx: &mut Context
+  ) -> Poll<Option<Self::Item>> {
+    let mut shared = self.shared.lock().unwrap();
+
+    match shared.items.pop_front() {
+      Some(item) => Poll::Ready(Some(item)),
+      None if shared.senders == 0 => Poll::Ready(None),
+      None => {
+        shared.waker = Some(cx.waker().clone());
+
+        Poll::Pending
+      }
+    }
+  }
+}
+
+// Unbounded channel for producing a stream of messages from elsewhere, such
+// as a task feeding responses to a streaming method
+pub fn channel<T: Send + 'static>() -> (Sender<T>, Streaming<T>) {
+  let shared = Arc::new(Mutex::new(Shared {
+    items: VecDeque::new(),
+    senders: 1,
+    waker: None
+  }));
+  let receiver = Receiver {
+    shared: shared.clone()
+  };
+
+  (
+    Sender {
+      shared
+    },
+    Streaming::new(receiver)
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::runtime::block_on;
+
+  #[test]
+  fn map_stream() -> Result<(), Status> {
+    let stream = Streaming::iter(vec![Ok(1), Ok(2)]).map(|v| Ok(v * 2));
+
+    assert_eq!(block_on(stream.collect())?, vec![2, 4]);
+
+    Ok(())
+  }
+
+  #[test]
+  fn channel_ends_on_drop() {
+    let (sender, stream) = channel();
+
+    sender.send(Ok("foo"));
+    sender.send(Err(Status::internal("bar")));
+
+    drop(sender);
+
+    assert_eq!(block_on(stream.collect()), Err(Status::internal("bar")));
+  }
+}
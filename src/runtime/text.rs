@@ -0,0 +1,709 @@
+use super::encoding::{
+  decode_bytes,
+  decode_key,
+  decode_varint,
+  DecodeError,
+  Message
+};
+use super::reflect::{DynamicMessage, FieldType, MessageDescriptor, Value};
+use super::registry::{is_any, pack_any, TypeRegistry};
+use super::WireType;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::iter::Peekable;
+use std::str::Chars;
+use std::vec::IntoIter;
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+  Name(String),
+  Number(String),
+  // Strings may hold any bytes, which only need to be UTF-8 for string fields
+  String(Vec<u8>),
+  Symbol(char)
+}
+
+fn indent(depth: usize) -> String {
+  "  ".repeat(depth)
+}
+
+// Strings are quoted as in C, with any control character written in octal
+fn escape(value: &str) -> String {
+  value
+    .chars()
+    .map(|v| match v {
+      '"' => "\\\"".to_string(),
+      '\\' => "\\\\".to_string(),
+      '\n' => "\\n".to_string(),
+      '\r' => "\\r".to_string(),
+      '\t' => "\\t".to_string(),
+      v if v.is_control() && v.is_ascii() => format!("\\{:03o}", v as u8),
+      v => v.to_string()
+    })
+    .collect()
+}
+
+fn escape_bytes(value: &[u8]) -> String {
+  value
+    .iter()
+    .map(|v| match v {
+      b' '..=b'~' if *v != b'"' && *v != b'\\' => (*v as char).to_string(),
+      v => format!("\\{:03o}", v)
+    })
+    .collect()
+}
+
+fn print_fields(
+  message: &DynamicMessage,
+  depth: usize,
+  registry: &TypeRegistry,
+  out: &mut String
+) {
+  // Any fields are written as the message they hold, named by its type URL
+  // within brackets
+  if let Some((type_url, message)) = registry.unpack_any(message) {
+    out.push_str(&format!("{}[{}] {{\n", indent(depth), type_url));
+    print_fields(&message, depth + 1, registry, out);
+    out.push_str(&format!("{}}}\n", indent(depth)));
+
+    return;
+  }
+
+  for (field, value) in message.fields() {
+    let line = match value {
+      Value::Int32(v) => format!("{}: {}", field.name, v),
+      Value::Int64(v) => format!("{}: {}", field.name, v),
+      Value::Bool(v) => format!("{}: {}", field.name, v),
+      Value::String(v) => format!("{}: \"{}\"", field.name, escape(v)),
+      Value::Bytes(v) => format!("{}: \"{}\"", field.name, escape_bytes(v)),
+      Value::Message(v) => {
+        out.push_str(&format!("{}{} {{\n", indent(depth), field.name));
+        print_fields(v, depth + 1, registry, out);
+        out.push_str(&format!("{}}}\n", indent(depth)));
+
+        continue;
+      }
+    };
+
+    out.push_str(&format!("{}{}\n", indent(depth), line));
+  }
+}
+
+// Text format of a message, with a field on each line and nested messages
+// indented within braces
+pub fn print(message: &DynamicMessage) -> String {
+  print_with(message, &TypeRegistry::default())
+}
+
+// Text format of a message, expanding Any fields of the types in a registry
+pub fn print_with(message: &DynamicMessage, registry: &TypeRegistry) -> String {
+  let mut out = String::new();
+
+  print_fields(message, 0, registry, &mut out);
+
+  out
+}
+
+impl Display for DynamicMessage {
+  fn fmt(&self, f: &mut Formatter) -> FmtResult {
+    write!(f, "{}", print(self))
+  }
+}
+
+// Text format of generated messages, which are read and written through a
+// dynamic message of their descriptor. Any fields are expanded when they
+// hold a type in the registry they're given
+pub trait TextFormat: Sized {
+  fn to_text(&self) -> Result<String, String> {
+    self.to_text_with(&TypeRegistry::default())
+  }
+
+  fn from_text(input: &str) -> Result<Self, String> {
+    Self::from_text_with(input, &TypeRegistry::default())
+  }
+
+  fn to_text_with(&self, registry: &TypeRegistry) -> Result<String, String>;
+
+  fn from_text_with(
+    input: &str,
+    registry: &TypeRegistry
+  ) -> Result<Self, String>;
+}
+
+impl<M: Message + Default> TextFormat for M {
+  // Messages without a descriptor of their own would print as empty, having
+  // no fields to decode into
+  fn to_text_with(&self, registry: &TypeRegistry) -> Result<String, String> {
+    if M::descriptor().full_name.is_empty() {
+      return Err("Message has no descriptor to print it with".to_string());
+    }
+
+    DynamicMessage::decode(M::descriptor(), &self.encode_to_vec())
+      .map(|v| print_with(&v, registry))
+      .map_err(|v| v.to_string())
+  }
+
+  fn from_text_with(
+    input: &str,
+    registry: &TypeRegistry
+  ) -> Result<Self, String> {
+    let message = parse_with(M::descriptor(), input, registry)?;
+
+    M::decode(&message.encode_to_vec()).map_err(|v| v.to_string())
+  }
+}
+
+fn fixed<const N: usize>(buf: &mut &[u8]) -> Result<[u8; N], DecodeError> {
+  let bytes = buf
+    .get(..N)
+    .ok_or_else(|| DecodeError::new("Unexpected end of field"))?;
+
+  *buf = &buf[N..];
+
+  Ok(bytes.try_into().unwrap_or([0; N]))
+}
+
+// Deepest nesting of messages and groups that's read without a schema, and
+// of messages parsed from text, so crafted input can't overflow the stack
+const MAX_DEPTH: usize = 64;
+
+// Fields of a message decoded without its schema, ending at the end of the
+// buffer or at the end of the group that's being read
+fn print_raw(
+  buf: &mut &[u8],
+  depth: usize,
+  group: Option<u32>,
+  out: &mut String
+) -> Result<(), DecodeError> {
+  while !buf.is_empty() {
+    let (number, wire) = decode_key(buf)?;
+    let prefix = format!("{}{}", indent(depth), number);
+
+    match wire {
+      WireType::Varint => {
+        let value = decode_varint(buf)?;
+
+        out.push_str(&format!("{}: {}  # varint\n", prefix, value));
+      }
+      WireType::Fixed64 => {
+        let value = u64::from_le_bytes(fixed(buf)?);
+
+        out.push_str(&format!("{}: 0x{:016x}  # fixed64\n", prefix, value));
+      }
+      WireType::Fixed32 => {
+        let value = u32::from_le_bytes(fixed(buf)?);
+
+        out.push_str(&format!("{}: 0x{:08x}  # fixed32\n", prefix, value));
+      }
+      // Bytes that parse as a message are most likely one, and are otherwise
+      // shown as a string, as they are beyond the deepest nesting
+      WireType::LengthDelimited => {
+        let value = decode_bytes(buf)?;
+        let mut nested = String::new();
+        let result = match depth < MAX_DEPTH {
+          true => print_raw(&mut &value[..], depth + 1, None, &mut nested),
+          false => Err(DecodeError::new("Message nested too deeply"))
+        };
+
+        match result {
+          Ok(()) if !nested.is_empty() => {
+            out.push_str(&format!("{} {{  # length-delimited\n", prefix));
+            out.push_str(&nested);
+            out.push_str(&format!("{}}}\n", indent(depth)));
+          }
+          _ => {
+            let value = match std::str::from_utf8(value) {
+              Ok(v) => escape(v),
+              Err(_) => escape_bytes(value)
+            };
+
+            out.push_str(&format!(
+              "{}: \"{}\"  # length-delimited\n",
+              prefix, value
+            ));
+          }
+        }
+      }
+      WireType::StartGroup if depth >= MAX_DEPTH => {
+        return Err(DecodeError::new("Group nested too deeply"));
+      }
+      WireType::StartGroup => {
+        out.push_str(&format!("{} {{  # group\n", prefix));
+        print_raw(buf, depth + 1, Some(number), out)?;
+        out.push_str(&format!("{}}}\n", indent(depth)));
+      }
+      WireType::EndGroup => {
+        return match group == Some(number) {
+          true => Ok(()),
+          false => Err(DecodeError::new("Unexpected end group"))
+        };
+      }
+    }
+  }
+
+  match group {
+    Some(_) => Err(DecodeError::new("Missing end group")),
+    None => Ok(())
+  }
+}
+
+// Text format of a message without its schema, showing the number and wire
+// type of each field in place of its name
+pub fn decode_raw(mut buf: &[u8]) -> Result<String, DecodeError> {
+  let mut out = String::new();
+
+  print_raw(&mut buf, 0, None, &mut out)?;
+
+  Ok(out)
+}
+
+// Read a quoted string up to its closing quote, where escapes may give any
+// byte
+fn unescape(
+  chars: &mut Peekable<Chars>,
+  quote: char
+) -> Result<Vec<u8>, String> {
+  let mut bytes = Vec::new();
+
+  loop {
+    let v = chars
+      .next()
+      .ok_or("Unterminated string")?;
+
+    match v {
+      v if v == quote => break,
+      '\n' => return Err("Unterminated string".to_string()),
+      '\\' => {
+        let escape = chars
+          .next()
+          .ok_or("Unterminated string")?;
+        let (radix, digits) = match escape {
+          'x' => (16, 2),
+          '0'..='7' => (8, 3),
+          _ => (0, 0)
+        };
+
+        match escape {
+          'n' => bytes.push(b'\n'),
+          'r' => bytes.push(b'\r'),
+          't' => bytes.push(b'\t'),
+          '"' | '\'' | '\\' => bytes.push(escape as u8),
+          _ if radix > 0 => {
+            let mut code = match radix {
+              8 => escape.to_string(),
+              _ => String::new()
+            };
+
+            while code.len() < digits {
+              match chars.next_if(|v| v.is_digit(radix)) {
+                Some(v) => code.push(v),
+                None => break
+              }
+            }
+
+            bytes.push(
+              u8::from_str_radix(&code, radix)
+                .map_err(|_| format!("Invalid escape \\{}", code))?
+            );
+          }
+          _ => return Err(format!("Invalid escape \\{}", escape))
+        }
+      }
+      v => bytes.extend(v.to_string().as_bytes())
+    }
+  }
+
+  Ok(bytes)
+}
+
+fn tokenise(input: &str) -> Result<Vec<Token>, String> {
+  let mut chars = input.chars().peekable();
+  let mut tokens = Vec::new();
+
+  while let Some(v) = chars.next() {
+    let mut word = |first: char| {
+      let mut word = first.to_string();
+
+      while let Some(v) =
+        chars.next_if(|v| v.is_alphanumeric() || "_.".contains(*v))
+      {
+        word.push(v);
+      }
+
+      word
+    };
+
+    match v {
+      v if v.is_whitespace() => continue,
+      '#' => while chars.next_if(|v| *v != '\n').is_some() {},
+      '"' | '\'' => {
+        let value = unescape(&mut chars, v)?;
+
+        // Adjacent strings are joined as in C
+        match tokens.last_mut() {
+          Some(Token::String(last)) => last.extend(value),
+          _ => tokens.push(Token::String(value))
+        }
+      }
+      v if v.is_alphabetic() || v == '_' => tokens.push(Token::Name(word(v))),
+      v if v.is_ascii_digit() || v == '-' => {
+        tokens.push(Token::Number(word(v)))
+      }
+      v => tokens.push(Token::Symbol(v))
+    }
+  }
+
+  Ok(tokens)
+}
+
+// Integers may be written in decimal, in hex with "0x" or in octal with a
+// leading zero
+fn parse_int<T: TryFrom<i128>>(value: &str) -> Result<T, String> {
+  let (negative, digits) = match value.strip_prefix('-') {
+    Some(v) => (true, v),
+    None => (false, value)
+  };
+  let parsed = match digits {
+    v if v.starts_with("0x") || v.starts_with("0X") => {
+      i128::from_str_radix(&v[2..], 16)
+    }
+    v if v.len() > 1 && v.starts_with('0') => i128::from_str_radix(&v[1..], 8),
+    v => v.parse::<i128>()
+  };
+
+  parsed
+    .ok()
+    .map(|v| {
+      if negative {
+        -v
+      } else {
+        v
+      }
+    })
+    .and_then(|v| T::try_from(v).ok())
+    .ok_or_else(|| format!("Invalid integer {}", value))
+}
+
+// Type URL of an expanded Any field, as in "[type.googleapis.com/pkg.Msg]",
+// once its opening bracket has been read
+fn parse_type_url(
+  tokens: &mut Peekable<IntoIter<Token>>
+) -> Result<String, String> {
+  let mut type_url = String::new();
+
+  loop {
+    match tokens.next() {
+      Some(Token::Symbol(']')) => return Ok(type_url),
+      Some(Token::Name(v)) => type_url.push_str(&v),
+      Some(Token::Symbol('/')) => type_url.push('/'),
+      _ => return Err(format!("Invalid type URL {}", type_url))
+    }
+  }
+}
+
+fn parse_fields(
+  descriptor: &'static MessageDescriptor,
+  tokens: &mut Peekable<IntoIter<Token>>,
+  end: Option<char>,
+  depth: usize,
+  registry: &TypeRegistry
+) -> Result<DynamicMessage, String> {
+  let mut message = DynamicMessage::new(descriptor);
+
+  if depth > MAX_DEPTH {
+    return Err("Message nested too deeply".to_string());
+  }
+
+  loop {
+    let name = match tokens.next() {
+      Some(Token::Name(v)) => v,
+      Some(Token::Symbol(v)) if Some(v) == end => return Ok(message),
+      // Extensions and expanded Any fields are named within brackets, and
+      // only the latter can be described yet
+      Some(Token::Symbol('[')) if is_any(descriptor) => {
+        let type_url = parse_type_url(tokens)?;
+
+        tokens.next_if_eq(&Token::Symbol(':'));
+
+        let close = match tokens.next() {
+          Some(Token::Symbol('{')) => '}',
+          Some(Token::Symbol('<')) => '>',
+          _ => return Err(format!("Expected a message for {}", type_url))
+        };
+        let packed = parse_fields(
+          registry.resolve(&type_url)?,
+          tokens,
+          Some(close),
+          depth + 1,
+          registry
+        )?;
+
+        if message.fields().next().is_some() {
+          return Err(format!("Field {} is set more than once", type_url));
+        }
+
+        message = pack_any(&type_url, packed.encode_to_vec())?;
+        tokens.next_if(|v| matches!(v, Token::Symbol(',' | ';')));
+
+        continue;
+      }
+      Some(Token::Symbol('[')) => {
+        return Err("Extension fields are not supported".to_string())
+      }
+      None if end.is_none() => return Ok(message),
+      None => return Err("Unexpected end of message".to_string()),
+      Some(_) => {
+        return Err(format!("Expected a field of {}", descriptor.full_name))
+      }
+    };
+    let field = descriptor
+      .field(&name)
+      .ok_or_else(|| format!("Unknown field {}", name))?;
+    // Colons are only optional before messages
+    let colon = tokens
+      .next_if_eq(&Token::Symbol(':'))
+      .is_some();
+
+    let value = match (field.r#type, tokens.next()) {
+      (FieldType::Message(r#type), Some(Token::Symbol(open @ ('{' | '<')))) => {
+        let close = if open == '{' {
+          '}'
+        } else {
+          '>'
+        };
+
+        Value::Message(parse_fields(
+          r#type.descriptor(),
+          tokens,
+          Some(close),
+          depth + 1,
+          registry
+        )?)
+      }
+      (FieldType::Int32, Some(Token::Number(v))) if colon => {
+        Value::Int32(parse_int(&v)?)
+      }
+      (FieldType::Int64, Some(Token::Number(v))) if colon => {
+        Value::Int64(parse_int(&v)?)
+      }
+      (FieldType::Bool, Some(Token::Name(v) | Token::Number(v))) if colon => {
+        match v.as_str() {
+          "true" | "True" | "t" | "1" => Value::Bool(true),
+          "false" | "False" | "f" | "0" => Value::Bool(false),
+          _ => return Err(format!("Invalid bool {}", v))
+        }
+      }
+      (FieldType::String, Some(Token::String(v))) if colon => Value::String(
+        String::from_utf8(v).map_err(|_| "Invalid UTF-8 in string")?
+      ),
+      (FieldType::Bytes, Some(Token::String(v))) if colon => Value::Bytes(v),
+      // Lists are only written for repeated fields, which the lexer doesn't
+      // read yet
+      (_, Some(Token::Symbol('['))) if colon => {
+        return Err("Repeated fields are not supported".to_string())
+      }
+      _ => return Err(format!("Invalid value for field {}", name))
+    };
+
+    if message.get(&name).is_some() {
+      return Err(format!("Field {} is set more than once", name));
+    }
+
+    message.set(&name, value)?;
+    tokens.next_if(|v| matches!(v, Token::Symbol(',' | ';')));
+  }
+}
+
+// Read a message of the given type from text format, as written by protoc or
+// by hand, with comments starting with "#"
+pub fn parse(
+  descriptor: &'static MessageDescriptor,
+  input: &str
+) -> Result<DynamicMessage, String> {
+  parse_with(descriptor, input, &TypeRegistry::default())
+}
+
+// Read a message from text format, where Any fields may hold messages of the
+// types in a registry
+pub fn parse_with(
+  descriptor: &'static MessageDescriptor,
+  input: &str,
+  registry: &TypeRegistry
+) -> Result<DynamicMessage, String> {
+  let mut tokens = tokenise(input)?.into_iter().peekable();
+
+  parse_fields(descriptor, &mut tokens, None, 0, registry)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::runtime::encoding;
+  use crate::runtime::reflect::{FieldDescriptor, Label, MessageType};
+  use crate::runtime::well_known::{Any, BytesValue};
+
+  static NODE: MessageDescriptor = MessageDescriptor {
+    name: "Node",
+    full_name: "test.Node",
+    fields: &[
+      FieldDescriptor {
+        name: "id",
+        json_name: "id",
+        number: 1,
+        label: Label::Optional,
+        r#type: FieldType::Int32
+      },
+      FieldDescriptor {
+        name: "label",
+        json_name: "label",
+        number: 2,
+        label: Label::Optional,
+        r#type: FieldType::String
+      },
+      FieldDescriptor {
+        name: "next",
+        json_name: "next",
+        number: 3,
+        label: Label::Optional,
+        r#type: FieldType::Message(MessageType::Static(|| &NODE))
+      }
+    ]
+  };
+
+  #[test]
+  fn parse_printed_message() -> Result<(), String> {
+    let input = "# first\nid: -0x10 next < label: 'a\\n' \"\\142\" >";
+    let message = parse(&NODE, input)?;
+
+    assert_eq!(print(&message), "id: -16\nnext {\n  label: \"a\\nb\"\n}\n");
+    assert_eq!(parse(&NODE, &print(&message))?, message);
+
+    Ok(())
+  }
+
+  #[test]
+  fn reject_invalid_text() {
+    assert!(parse(&NODE, "id: \"1\"").is_err());
+    assert!(parse(&NODE, "id: 1 id: 2").is_err());
+    assert!(parse(&NODE, "next { id: 1").is_err());
+    assert!(parse(&NODE, "missing: 1").is_err());
+    assert_eq!(
+      parse(&NODE, "[pkg.ext]: 1").err(),
+      Some("Extension fields are not supported".to_string())
+    );
+    assert_eq!(
+      parse(&NODE, "id: [1, 2]").err(),
+      Some("Repeated fields are not supported".to_string())
+    );
+  }
+
+  #[test]
+  fn limit_parse_depth() {
+    let nested = |depth| "next { ".repeat(depth) + &"} ".repeat(depth);
+
+    assert!(parse(&NODE, &nested(MAX_DEPTH)).is_ok());
+    assert_eq!(
+      parse(&NODE, &nested(MAX_DEPTH + 1)).err(),
+      Some("Message nested too deeply".to_string())
+    );
+    assert!(parse(&NODE, &"next < ".repeat(100_000)).is_err());
+  }
+
+  #[test]
+  fn expand_any_fields() -> Result<(), String> {
+    let registry = TypeRegistry::default().add_descriptor(&NODE);
+    let input = "[type.googleapis.com/test.Node] { id: 1 }";
+    let message = parse_with(Any::descriptor(), input, &registry)?;
+    let bytes = BytesValue::from(vec![0, 255]);
+
+    assert_eq!(
+      print(&message),
+      "type_url: \"type.googleapis.com/test.Node\"\nvalue: \"\\010\\001\"\n"
+    );
+    assert!(parse(Any::descriptor(), input).is_err());
+    assert!(parse_with(&NODE, "label: \"\\377\"", &registry).is_err());
+    assert_eq!(
+      Any::pack(&bytes).to_text()?,
+      "[type.googleapis.com/google.protobuf.BytesValue] {\n  value: \
+       \"\\000\\377\"\n}\n"
+    );
+    assert_eq!(
+      Any::from_text(&Any::pack(&bytes).to_text()?)?,
+      Any::pack(&bytes)
+    );
+
+    Ok(())
+  }
+
+  #[test]
+  fn print_raw_fields() -> Result<(), DecodeError> {
+    let mut nested = Vec::new();
+    let mut buf = Vec::new();
+
+    encoding::int32::encode(1, &150, &mut nested);
+    encoding::int32::encode(1, &-1, &mut buf);
+    encoding::encode_bytes(2, &nested, &mut buf);
+    encoding::string::encode(3, "a \"b\"", &mut buf);
+
+    assert_eq!(
+      decode_raw(&buf)?,
+      "1: 18446744073709551615  # varint\n2 {  # length-delimited\n  1: 150  \
+       # varint\n}\n3: \"a \\\"b\\\"\"  # length-delimited\n"
+    );
+
+    Ok(())
+  }
+
+  #[test]
+  fn reject_truncated_fields() {
+    assert!(decode_raw(&[0x0a, 0x05, 0x01]).is_err());
+    assert!(decode_raw(&[0x0b, 0x08, 0x01]).is_err());
+  }
+
+  #[test]
+  fn limit_raw_depth() -> Result<(), DecodeError> {
+    let groups = [0x0b].repeat(100_000);
+    let mut message = vec![0x08, 0x01];
+
+    for _ in 0..1_000 {
+      let mut outer = Vec::new();
+
+      encoding::bytes::encode(1, &message, &mut outer);
+      message = outer;
+    }
+
+    let text = decode_raw(&message)?;
+
+    assert_eq!(
+      text
+        .matches("# length-delimited")
+        .count(),
+      MAX_DEPTH + 1
+    );
+    assert!(decode_raw(&groups).is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn print_without_descriptor() {
+    #[derive(Default)]
+    struct Raw;
+
+    impl Message for Raw {
+      fn encode(&self, buf: &mut Vec<u8>) {
+        encoding::int32::encode(1, &1, buf);
+      }
+
+      fn merge_field(
+        &mut self,
+        _: u32,
+        wire: WireType,
+        buf: &mut &[u8]
+      ) -> Result<(), DecodeError> {
+        encoding::skip_field(wire, buf)
+      }
+    }
+
+    assert!(Raw.to_text().is_err());
+  }
+}
@@ -0,0 +1,221 @@
+use super::encoding::{self, DecodeError, Message, WireType};
+use super::reflect::{FieldDescriptor, FieldType, Label, MessageDescriptor};
+use heck::ToLowerCamelCase;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::str::FromStr;
+
+// Descriptor of a well-known type, which only describes its fields when
+// reflection can hold every one of them
+macro_rules! describe {
+  ($name:ident $(, $field:expr)*) => {
+    fn descriptor() -> &'static MessageDescriptor {
+      static DESCRIPTOR: MessageDescriptor = MessageDescriptor {
+        name: stringify!($name),
+        full_name: concat!("google.protobuf.", stringify!($name)),
+        fields: &[$($field),*]
+      };
+
+      &DESCRIPTOR
+    }
+  };
+}
+
+mod any;
+#[cfg(feature = "serde")]
+mod serde;
+mod time;
+mod value;
+mod wrappers;
+
+pub use any::{Any, TYPE_URL_PREFIX};
+pub use time::{Duration, Timestamp};
+pub use value::{ListValue, Struct, Value};
+pub use wrappers::{
+  BoolValue,
+  BytesValue,
+  DoubleValue,
+  FloatValue,
+  Int32Value,
+  Int64Value,
+  StringValue,
+  UInt32Value,
+  UInt64Value
+};
+
+// Types of the "google.protobuf" package, which generated code refers to in
+// place of generating them, and which are written to JSON in their own way
+// when the "serde" feature is enabled
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Empty {}
+
+impl Message for Empty {
+  describe!(Empty);
+
+  fn encode(&self, _: &mut Vec<u8>) {}
+
+  fn merge_field(
+    &mut self,
+    _: u32,
+    wire: WireType,
+    buf: &mut &[u8]
+  ) -> Result<(), DecodeError> {
+    encoding::skip_field(wire, buf)
+  }
+}
+
+// Paths of fields within a message, as in "user.display_name", which are
+// written to JSON as a single string of camelCase paths separated by commas
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct FieldMask {
+  pub paths: Vec<String>
+}
+
+impl Message for FieldMask {
+  describe!(FieldMask);
+
+  fn encode(&self, buf: &mut Vec<u8>) {
+    encoding::string::encode_repeated(1, &self.paths, buf);
+  }
+
+  fn merge_field(
+    &mut self,
+    number: u32,
+    wire: WireType,
+    buf: &mut &[u8]
+  ) -> Result<(), DecodeError> {
+    match number {
+      1 => encoding::string::merge_repeated(wire, &mut self.paths, buf),
+      _ => encoding::skip_field(wire, buf)
+    }
+  }
+}
+
+// Field of a well-known type whose JSON name is the same as its name
+const fn field(
+  name: &'static str,
+  number: u32,
+  r#type: FieldType
+) -> FieldDescriptor {
+  FieldDescriptor {
+    name,
+    json_name: name,
+    number,
+    label: Label::Optional,
+    r#type
+  }
+}
+
+// Descriptors of every well-known type
+pub const DESCRIPTORS: [fn() -> &'static MessageDescriptor; 17] = [
+  Any::descriptor,
+  BoolValue::descriptor,
+  BytesValue::descriptor,
+  DoubleValue::descriptor,
+  Duration::descriptor,
+  Empty::descriptor,
+  FieldMask::descriptor,
+  FloatValue::descriptor,
+  Int32Value::descriptor,
+  Int64Value::descriptor,
+  ListValue::descriptor,
+  StringValue::descriptor,
+  Struct::descriptor,
+  Timestamp::descriptor,
+  UInt32Value::descriptor,
+  UInt64Value::descriptor,
+  Value::descriptor
+];
+
+// Descriptor of a well-known type given its fully-qualified name, with or
+// without its leading dot
+pub fn descriptor(name: &str) -> Option<fn() -> &'static MessageDescriptor> {
+  DESCRIPTORS
+    .into_iter()
+    .find(|v| v().full_name == name.trim_start_matches('.'))
+}
+
+impl Display for FieldMask {
+  fn fmt(&self, f: &mut Formatter) -> FmtResult {
+    let paths = self
+      .paths
+      .iter()
+      .map(|path| {
+        path
+          .split('.')
+          .map(|v| v.to_lower_camel_case())
+          .collect::<Vec<String>>()
+          .join(".")
+      })
+      .collect::<Vec<String>>();
+
+    write!(f, "{}", paths.join(","))
+  }
+}
+
+impl FromStr for FieldMask {
+  type Err = String;
+
+  fn from_str(value: &str) -> Result<Self, Self::Err> {
+    let paths = value
+      .split(',')
+      .filter(|v| !v.is_empty())
+      .map(|path| {
+        // Paths are written in camelCase, so any underscore is ambiguous
+        if path.contains('_') {
+          return Err(format!("Invalid field mask path {}", path));
+        }
+
+        let segments = path
+          .split('.')
+          .map(|v| {
+            v.chars()
+              .fold(String::new(), |mut acc, v| {
+                if v.is_ascii_uppercase() {
+                  acc.push('_');
+                }
+
+                acc.push(v.to_ascii_lowercase());
+                acc
+              })
+          })
+          .collect::<Vec<String>>();
+
+        Ok(segments.join("."))
+      })
+      .collect::<Result<Vec<String>, String>>()?;
+
+    Ok(Self {
+      paths
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn field_mask_json() -> Result<(), String> {
+    let mask = "user.displayName,photo".parse::<FieldMask>()?;
+
+    assert_eq!(mask.paths, vec!["user.display_name", "photo"]);
+    assert_eq!(mask.to_string(), "user.displayName,photo");
+    assert!("user.display_name"
+      .parse::<FieldMask>()
+      .is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn empty_round_trip() -> Result<(), DecodeError> {
+    let mut buf = Vec::new();
+
+    encoding::int32::encode(1, &5, &mut buf);
+
+    assert_eq!(Empty::decode(&buf)?, Empty {});
+    assert!(Empty {}.encode_to_vec().is_empty());
+
+    Ok(())
+  }
+}
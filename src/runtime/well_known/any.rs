@@ -0,0 +1,103 @@
+use super::encoding::{self, DecodeError, Message, WireType};
+use super::{field, FieldDescriptor, FieldType, Label, MessageDescriptor};
+
+// Prefix of the URL that messages are packed with, ahead of their full name
+pub const TYPE_URL_PREFIX: &str = "type.googleapis.com/";
+
+// Message of any type, encoded along with a URL naming its type, as in
+// "type.googleapis.com/pkg.Msg"
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Any {
+  pub type_url: String,
+  pub value: Vec<u8>
+}
+
+impl Any {
+  // Pack a message with the URL of its type, named from its descriptor
+  pub fn pack<M: Message>(message: &M) -> Self {
+    Self {
+      type_url: format!("{}{}", TYPE_URL_PREFIX, M::descriptor().full_name),
+      value: message.encode_to_vec()
+    }
+  }
+
+  // Full name of the packed type, which is whatever follows the last "/" of
+  // its URL
+  pub fn type_name(&self) -> &str {
+    self
+      .type_url
+      .rsplit('/')
+      .next()
+      .unwrap_or_default()
+  }
+
+  pub fn is<M: Message>(&self) -> bool {
+    self.type_name() == M::descriptor().full_name
+  }
+
+  // Decode the packed message, failing when it's of another type
+  pub fn unpack<M: Message + Default>(&self) -> Result<M, DecodeError> {
+    match self.is::<M>() {
+      true => M::decode(&self.value),
+      false => Err(DecodeError::new(format!(
+        "Expected {} but found {}",
+        M::descriptor().full_name,
+        self.type_url
+      )))
+    }
+  }
+}
+
+impl Message for Any {
+  describe!(
+    Any,
+    FieldDescriptor {
+      name: "type_url",
+      json_name: "typeUrl",
+      number: 1,
+      label: Label::Optional,
+      r#type: FieldType::String
+    },
+    field("value", 2, FieldType::Bytes)
+  );
+
+  fn encode(&self, buf: &mut Vec<u8>) {
+    encoding::string::encode(1, &self.type_url, buf);
+    encoding::bytes::encode(2, &self.value, buf);
+  }
+
+  fn merge_field(
+    &mut self,
+    number: u32,
+    wire: WireType,
+    buf: &mut &[u8]
+  ) -> Result<(), DecodeError> {
+    match number {
+      1 => encoding::string::merge(wire, &mut self.type_url, buf),
+      2 => encoding::bytes::merge(wire, &mut self.value, buf),
+      _ => encoding::skip_field(wire, buf)
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::super::{Duration, Timestamp};
+  use super::*;
+
+  #[test]
+  fn pack_and_unpack() -> Result<(), DecodeError> {
+    let time = Timestamp::new(5, 0);
+    let any = Any::pack(&time);
+
+    assert_eq!(
+      any.type_url,
+      "type.googleapis.com/google.protobuf.Timestamp"
+    );
+    assert!(any.is::<Timestamp>());
+    assert_eq!(any.unpack::<Timestamp>()?, time);
+    assert!(any.unpack::<Duration>().is_err());
+
+    Ok(())
+  }
+}
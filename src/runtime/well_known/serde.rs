@@ -0,0 +1,601 @@
+use super::*;
+use crate::runtime::json::{decode_base64, encode_base64};
+use ::serde::de::value::{
+  Error as ValueError,
+  MapDeserializer,
+  SeqDeserializer
+};
+use ::serde::de::{
+  self,
+  Deserializer,
+  IgnoredAny,
+  IntoDeserializer,
+  MapAccess,
+  SeqAccess,
+  Visitor
+};
+use ::serde::ser::{self, SerializeMap, Serializer};
+use ::serde::{forward_to_deserialize_any, Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Formatter;
+
+// Scalar read from JSON, where numbers may also be written as strings
+enum Scalar {
+  Bool(bool),
+  Signed(i64),
+  Unsigned(u64),
+  Float(f64),
+  String(String)
+}
+
+impl Scalar {
+  fn to_f64<E: de::Error>(&self) -> Result<f64, E> {
+    match self {
+      Self::Signed(v) => Ok(*v as f64),
+      Self::Unsigned(v) => Ok(*v as f64),
+      Self::Float(v) => Ok(*v),
+      Self::String(v) => match v.as_str() {
+        "NaN" => Ok(f64::NAN),
+        "Infinity" => Ok(f64::INFINITY),
+        "-Infinity" => Ok(f64::NEG_INFINITY),
+        v => v.parse().map_err(E::custom)
+      },
+      Self::Bool(_) => Err(E::custom("Expected a number"))
+    }
+  }
+
+  // Integers, which may be written as floats without a fraction
+  fn to_int<T: TryFrom<i64> + TryFrom<u64>, E: de::Error>(
+    &self
+  ) -> Result<T, E> {
+    let out_of_range = || E::custom("Number is out of range");
+
+    match self {
+      Self::Signed(v) => T::try_from(*v).map_err(|_| out_of_range()),
+      Self::Unsigned(v) => T::try_from(*v).map_err(|_| out_of_range()),
+      Self::Float(v) if v.fract() == 0.0 && v.abs() < 2f64.powi(63) => {
+        T::try_from(*v as i64).map_err(|_| out_of_range())
+      }
+      Self::String(v) => match v.parse::<i64>() {
+        Ok(v) => Self::Signed(v).to_int(),
+        Err(_) => match v.parse::<u64>() {
+          Ok(v) => Self::Unsigned(v).to_int(),
+          Err(_) => Self::Float(v.parse().map_err(E::custom)?).to_int()
+        }
+      },
+      _ => Err(E::custom("Expected an integer"))
+    }
+  }
+}
+
+struct ScalarVisitor;
+
+impl<'de> Visitor<'de> for ScalarVisitor {
+  type Value = Scalar;
+
+  fn expecting(&self, f: &mut Formatter) -> FmtResult {
+    write!(f, "a boolean, number or string")
+  }
+
+  fn visit_bool<E: de::Error>(self, v: bool) -> Result<Scalar, E> {
+    Ok(Scalar::Bool(v))
+  }
+
+  fn visit_i64<E: de::Error>(self, v: i64) -> Result<Scalar, E> {
+    Ok(Scalar::Signed(v))
+  }
+
+  fn visit_u64<E: de::Error>(self, v: u64) -> Result<Scalar, E> {
+    Ok(Scalar::Unsigned(v))
+  }
+
+  fn visit_f64<E: de::Error>(self, v: f64) -> Result<Scalar, E> {
+    Ok(Scalar::Float(v))
+  }
+
+  fn visit_str<E: de::Error>(self, v: &str) -> Result<Scalar, E> {
+    Ok(Scalar::String(v.to_string()))
+  }
+}
+
+impl<'de> Deserialize<'de> for Scalar {
+  fn deserialize<D: Deserializer<'de>>(
+    deserializer: D
+  ) -> Result<Self, D::Error> {
+    deserializer.deserialize_any(ScalarVisitor)
+  }
+}
+
+// Floats that JSON numbers can't hold are written as strings
+fn serialize_float<S: Serializer>(
+  value: f64,
+  serializer: S
+) -> Result<S::Ok, S::Error> {
+  match value {
+    v if v.is_nan() => serializer.serialize_str("NaN"),
+    f64::INFINITY => serializer.serialize_str("Infinity"),
+    f64::NEG_INFINITY => serializer.serialize_str("-Infinity"),
+    v => serializer.serialize_f64(v)
+  }
+}
+
+// Types written to JSON as strings, through their FromStr impls and either
+// their Display impls or a function that fails for values JSON can't hold
+macro_rules! string_json {
+  ($name:ident) => {
+    string_json!($name, |v: &$name| Ok::<String, String>(v.to_string()));
+  };
+  ($name:ident, $print:expr) => {
+    impl Serialize for $name {
+      fn serialize<S: Serializer>(
+        &self,
+        serializer: S
+      ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&$print(self).map_err(ser::Error::custom)?)
+      }
+    }
+
+    impl<'de> Deserialize<'de> for $name {
+      fn deserialize<D: Deserializer<'de>>(
+        deserializer: D
+      ) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+          .parse()
+          .map_err(de::Error::custom)
+      }
+    }
+  };
+}
+
+string_json!(Timestamp, Timestamp::to_rfc3339);
+string_json!(Duration);
+string_json!(FieldMask);
+
+// Wrappers are written as the value they wrap, with 64 bit integers as
+// strings so they aren't rounded by readers that treat numbers as doubles
+macro_rules! wrapper_json {
+  ($name:ident, |$value:ident, $serializer:ident| $serialize:expr, |$scalar:ident| $deserialize:expr) => {
+    impl Serialize for $name {
+      fn serialize<S: Serializer>(
+        &self,
+        $serializer: S
+      ) -> Result<S::Ok, S::Error> {
+        let $value = &self.value;
+
+        $serialize
+      }
+    }
+
+    impl<'de> Deserialize<'de> for $name {
+      fn deserialize<D: Deserializer<'de>>(
+        deserializer: D
+      ) -> Result<Self, D::Error> {
+        let $scalar = Scalar::deserialize(deserializer)?;
+
+        Ok(Self {
+          value: $deserialize
+        })
+      }
+    }
+  };
+}
+
+wrapper_json!(
+  BoolValue,
+  |value, serializer| serializer.serialize_bool(*value),
+  |scalar| match scalar {
+    Scalar::Bool(v) => v,
+    _ => return Err(de::Error::custom("Expected a boolean"))
+  }
+);
+wrapper_json!(
+  BytesValue,
+  |value, serializer| serializer.serialize_str(&encode_base64(value)),
+  |scalar| match scalar {
+    Scalar::String(v) => decode_base64(&v).map_err(de::Error::custom)?,
+    _ => return Err(de::Error::custom("Expected a base64 string"))
+  }
+);
+wrapper_json!(
+  DoubleValue,
+  |value, serializer| serialize_float(*value, serializer),
+  |scalar| scalar.to_f64()?
+);
+wrapper_json!(
+  FloatValue,
+  |value, serializer| serialize_float((*value).into(), serializer),
+  |scalar| scalar.to_f64()? as f32
+);
+wrapper_json!(
+  Int32Value,
+  |value, serializer| serializer.serialize_i32(*value),
+  |scalar| scalar.to_int()?
+);
+wrapper_json!(
+  Int64Value,
+  |value, serializer| serializer.collect_str(value),
+  |scalar| scalar.to_int()?
+);
+wrapper_json!(
+  StringValue,
+  |value, serializer| serializer.serialize_str(value),
+  |scalar| match scalar {
+    Scalar::String(v) => v,
+    _ => return Err(de::Error::custom("Expected a string"))
+  }
+);
+wrapper_json!(
+  UInt32Value,
+  |value, serializer| serializer.serialize_u32(*value),
+  |scalar| scalar.to_int()?
+);
+wrapper_json!(
+  UInt64Value,
+  |value, serializer| serializer.collect_str(value),
+  |scalar| scalar.to_int()?
+);
+
+impl Serialize for Empty {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_map(Some(0))?.end()
+  }
+}
+
+impl<'de> Deserialize<'de> for Empty {
+  fn deserialize<D: Deserializer<'de>>(
+    deserializer: D
+  ) -> Result<Self, D::Error> {
+    BTreeMap::<String, IgnoredAny>::deserialize(deserializer)?;
+
+    Ok(Self {})
+  }
+}
+
+impl Serialize for Struct {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    self.fields.serialize(serializer)
+  }
+}
+
+impl<'de> Deserialize<'de> for Struct {
+  fn deserialize<D: Deserializer<'de>>(
+    deserializer: D
+  ) -> Result<Self, D::Error> {
+    Ok(Self {
+      fields: BTreeMap::deserialize(deserializer)?
+    })
+  }
+}
+
+impl Serialize for ListValue {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    self.values.serialize(serializer)
+  }
+}
+
+impl<'de> Deserialize<'de> for ListValue {
+  fn deserialize<D: Deserializer<'de>>(
+    deserializer: D
+  ) -> Result<Self, D::Error> {
+    Ok(Self {
+      values: Vec::deserialize(deserializer)?
+    })
+  }
+}
+
+impl Serialize for Value {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    match self {
+      Self::Null => serializer.serialize_unit(),
+      Self::Number(v) => serialize_float(*v, serializer),
+      Self::String(v) => serializer.serialize_str(v),
+      Self::Bool(v) => serializer.serialize_bool(*v),
+      Self::Struct(v) => v.serialize(serializer),
+      Self::List(v) => v.serialize(serializer)
+    }
+  }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+  type Value = Value;
+
+  fn expecting(&self, f: &mut Formatter) -> FmtResult {
+    write!(f, "any JSON value")
+  }
+
+  fn visit_unit<E: de::Error>(self) -> Result<Value, E> {
+    Ok(Value::Null)
+  }
+
+  fn visit_none<E: de::Error>(self) -> Result<Value, E> {
+    Ok(Value::Null)
+  }
+
+  fn visit_some<D: Deserializer<'de>>(
+    self,
+    deserializer: D
+  ) -> Result<Value, D::Error> {
+    Value::deserialize(deserializer)
+  }
+
+  fn visit_bool<E: de::Error>(self, v: bool) -> Result<Value, E> {
+    Ok(Value::Bool(v))
+  }
+
+  fn visit_i64<E: de::Error>(self, v: i64) -> Result<Value, E> {
+    Ok(Value::Number(v as f64))
+  }
+
+  fn visit_u64<E: de::Error>(self, v: u64) -> Result<Value, E> {
+    Ok(Value::Number(v as f64))
+  }
+
+  fn visit_f64<E: de::Error>(self, v: f64) -> Result<Value, E> {
+    Ok(Value::Number(v))
+  }
+
+  fn visit_str<E: de::Error>(self, v: &str) -> Result<Value, E> {
+    Ok(Value::String(v.to_string()))
+  }
+
+  fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+    let mut values = Vec::new();
+
+    while let Some(value) = seq.next_element()? {
+      values.push(value);
+    }
+
+    Ok(Value::List(ListValue {
+      values
+    }))
+  }
+
+  fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+    let mut fields = BTreeMap::new();
+
+    while let Some((key, value)) = map.next_entry()? {
+      fields.insert(key, value);
+    }
+
+    Ok(Value::Struct(Struct {
+      fields
+    }))
+  }
+}
+
+impl<'de> Deserialize<'de> for Value {
+  fn deserialize<D: Deserializer<'de>>(
+    deserializer: D
+  ) -> Result<Self, D::Error> {
+    deserializer.deserialize_any(ValueVisitor)
+  }
+}
+
+// Values are also read as the JSON they hold, which lets an Any read the
+// mapping of the well-known type it holds once it knows which type that is
+impl<'de> Deserializer<'de> for Value {
+  type Error = ValueError;
+
+  forward_to_deserialize_any! {
+    bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+    bytes byte_buf option unit unit_struct newtype_struct seq tuple
+    tuple_struct map struct enum identifier ignored_any
+  }
+
+  fn deserialize_any<V: Visitor<'de>>(
+    self,
+    visitor: V
+  ) -> Result<V::Value, Self::Error> {
+    match self {
+      Self::Null => visitor.visit_unit(),
+      Self::Number(v) => visitor.visit_f64(v),
+      Self::String(v) => visitor.visit_string(v),
+      Self::Bool(v) => visitor.visit_bool(v),
+      Self::Struct(v) => {
+        visitor.visit_map(MapDeserializer::new(v.fields.into_iter()))
+      }
+      Self::List(v) => {
+        visitor.visit_seq(SeqDeserializer::new(v.values.into_iter()))
+      }
+    }
+  }
+}
+
+impl IntoDeserializer<'_, ValueError> for Value {
+  type Deserializer = Self;
+
+  fn into_deserializer(self) -> Self {
+    self
+  }
+}
+
+// Well-known types packed in an Any are written with their own mapping in
+// "value". The fields of other types can't be known without their
+// descriptors, so their encoded bytes are written in "value" instead
+macro_rules! any_json {
+  ($($name:ident),*) => {
+    fn serialize_any_value<S: SerializeMap>(
+      any: &Any,
+      map: &mut S
+    ) -> Result<(), S::Error> {
+      $(
+        if any.is::<$name>() {
+          let value = any.unpack::<$name>().map_err(ser::Error::custom)?;
+
+          return map.serialize_entry("value", &value);
+        }
+      )*
+
+      map.serialize_entry("value", &encode_base64(&any.value))
+    }
+
+    fn deserialize_any_value<E: de::Error>(
+      any: &Any,
+      value: Value
+    ) -> Result<Vec<u8>, E> {
+      $(
+        if any.is::<$name>() {
+          let value = $name::deserialize(value).map_err(E::custom)?;
+
+          return Ok(value.encode_to_vec());
+        }
+      )*
+
+      match value {
+        Value::String(value) => decode_base64(&value).map_err(E::custom),
+        _ => Err(E::custom(format!(
+          "Expected base64 for {}, whose fields aren't known",
+          any.type_url
+        )))
+      }
+    }
+  };
+}
+
+any_json!(
+  Any,
+  BoolValue,
+  BytesValue,
+  DoubleValue,
+  Duration,
+  Empty,
+  FieldMask,
+  FloatValue,
+  Int32Value,
+  Int64Value,
+  ListValue,
+  StringValue,
+  Struct,
+  Timestamp,
+  UInt32Value,
+  UInt64Value,
+  Value
+);
+
+// Messages of any type are written with their type URL in "@type"
+impl Serialize for Any {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    let mut map = serializer.serialize_map(Some(2))?;
+
+    map.serialize_entry("@type", &self.type_url)?;
+    serialize_any_value(self, &mut map)?;
+    map.end()
+  }
+}
+
+impl<'de> Deserialize<'de> for Any {
+  fn deserialize<D: Deserializer<'de>>(
+    deserializer: D
+  ) -> Result<Self, D::Error> {
+    let mut fields = BTreeMap::<String, Value>::deserialize(deserializer)?;
+    let type_url = match fields.remove("@type") {
+      Some(Value::String(type_url)) => type_url,
+      Some(_) => return Err(de::Error::custom("Expected a string for @type")),
+      None => return Err(de::Error::missing_field("@type"))
+    };
+    let mut any = Self {
+      type_url,
+      value: Vec::new()
+    };
+
+    if let Some(value) = fields.remove("value") {
+      any.value = deserialize_any_value(&any, value)?;
+    }
+
+    match fields.keys().next() {
+      Some(key) => Err(de::Error::unknown_field(key, &["@type", "value"])),
+      None => Ok(any)
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::{from_str, json, to_value};
+
+  #[test]
+  fn write_json() -> Result<(), serde_json::Error> {
+    let value = Value::List(ListValue {
+      values: vec![Value::Null, Value::Number(1.5), Value::Bool(true)]
+    });
+
+    assert_eq!(
+      to_value(Timestamp::new(0, 1_000_000))?,
+      json!("1970-01-01T00:00:00.001Z")
+    );
+    assert_eq!(to_value(Duration::new(-1, -500_000_000))?, json!("-1.500s"));
+    assert_eq!(to_value(Int64Value::from(-2))?, json!("-2"));
+    assert_eq!(
+      to_value(DoubleValue::from(f64::INFINITY))?,
+      json!("Infinity")
+    );
+    assert_eq!(to_value(BytesValue::from(b"foo".to_vec()))?, json!("Zm9v"));
+    assert_eq!(to_value(Empty {})?, json!({}));
+    assert_eq!(to_value(value)?, json!([null, 1.5, true]));
+    assert!(to_value(Timestamp::new(i64::MAX, 0)).is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn read_json() -> Result<(), serde_json::Error> {
+    let value = from_str::<Struct>(r#"{"a": {"b": [1, "c"]}, "d": null}"#)?;
+
+    assert_eq!(
+      from_str::<UInt64Value>(r#""18446744073709551615""#)?.value,
+      u64::MAX
+    );
+    assert_eq!(from_str::<Int32Value>("1e2")?.value, 100);
+    assert_eq!(
+      from_str::<FloatValue>(r#""-Infinity""#)?.value,
+      f32::NEG_INFINITY
+    );
+    assert_eq!(
+      from_str::<Duration>(r#""0.5s""#)?,
+      Duration::new(0, 500_000_000)
+    );
+    assert_eq!(value.fields["d"], Value::Null);
+    assert!(from_str::<Int32Value>("2147483648").is_err());
+    assert!(from_str::<Int32Value>("1.5").is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn map_any_values() -> Result<(), serde_json::Error> {
+    let time = Any::pack(&Timestamp::new(5, 0));
+    let json = json!({
+      "@type": "type.googleapis.com/google.protobuf.Timestamp",
+      "value": "1970-01-01T00:00:05Z"
+    });
+    let nested = Any::pack(&Any::pack(&Struct {
+      fields: BTreeMap::from([("a".to_string(), Value::Bool(true))])
+    }));
+
+    assert_eq!(to_value(&time)?, json);
+    assert_eq!(serde_json::from_value::<Any>(json)?, time);
+    assert_eq!(serde_json::from_value::<Any>(to_value(&nested)?)?, nested);
+    assert_eq!(
+      to_value(Any::pack(&Int64Value::from(-2)))?["value"],
+      json!("-2")
+    );
+    assert!(from_str::<Any>(
+      r#"{"@type": "/google.protobuf.Timestamp", "value": 5}"#
+    )
+    .is_err());
+    assert!(from_str::<Any>(r#"{"@type": "/pkg.Msg", "a": 1}"#).is_err());
+
+    let other = Any {
+      type_url: "type.googleapis.com/pkg.Msg".to_string(),
+      value: b"foo".to_vec()
+    };
+
+    assert_eq!(to_value(&other)?["value"], json!("Zm9v"));
+    assert_eq!(serde_json::from_value::<Any>(to_value(&other)?)?, other);
+
+    Ok(())
+  }
+}
@@ -0,0 +1,549 @@
+use super::encoding::{self, DecodeError, Message, WireType};
+use super::{field, FieldType, MessageDescriptor};
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::str::FromStr;
+use std::time::{Duration as StdDuration, SystemTime, UNIX_EPOCH};
+
+const NANOS: i64 = 1_000_000_000;
+
+// Seconds in the range of timestamps, from 0001-01-01 to 9999-12-31
+const MIN_SECONDS: i64 = -62_135_596_800;
+const MAX_SECONDS: i64 = 253_402_300_799;
+
+// Point in time as seconds and nanoseconds since the Unix epoch, written to
+// JSON as an RFC 3339 string in UTC
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp {
+  pub seconds: i64,
+  pub nanos: i32
+}
+
+// Span of time, where seconds and nanoseconds have the same sign, written to
+// JSON as seconds with a trailing "s", as in "1.5s"
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Duration {
+  pub seconds: i64,
+  pub nanos: i32
+}
+
+macro_rules! seconds_and_nanos {
+  ($name:ident) => {
+    impl Message for $name {
+      describe!(
+        $name,
+        field("seconds", 1, FieldType::Int64),
+        field("nanos", 2, FieldType::Int32)
+      );
+
+      fn encode(&self, buf: &mut Vec<u8>) {
+        encoding::int64::encode(1, &self.seconds, buf);
+        encoding::int32::encode(2, &self.nanos, buf);
+      }
+
+      fn merge_field(
+        &mut self,
+        number: u32,
+        wire: WireType,
+        buf: &mut &[u8]
+      ) -> Result<(), DecodeError> {
+        match number {
+          1 => encoding::int64::merge(wire, &mut self.seconds, buf),
+          2 => encoding::int32::merge(wire, &mut self.nanos, buf),
+          _ => encoding::skip_field(wire, buf)
+        }
+      }
+    }
+  };
+}
+
+seconds_and_nanos!(Timestamp);
+seconds_and_nanos!(Duration);
+
+// Fraction of a second with 3, 6 or 9 digits, as few as will hold it
+fn format_nanos(nanos: u32) -> String {
+  match nanos {
+    0 => String::new(),
+    v if v % 1_000_000 == 0 => format!(".{:03}", v / 1_000_000),
+    v if v % 1_000 == 0 => format!(".{:06}", v / 1_000),
+    v => format!(".{:09}", v)
+  }
+}
+
+// Nanoseconds of up to 9 digits following a decimal point
+fn parse_nanos(digits: &str) -> Option<i32> {
+  match digits.len() {
+    1..=9
+      if digits
+        .bytes()
+        .all(|v| v.is_ascii_digit()) =>
+    {
+      format!("{:0<9}", digits).parse().ok()
+    }
+    _ => None
+  }
+}
+
+fn parse_number(digits: &str) -> Option<i64> {
+  match digits
+    .bytes()
+    .all(|v| v.is_ascii_digit())
+  {
+    true => digits.parse().ok(),
+    false => None
+  }
+}
+
+// Fields of an RFC 3339 date or time, each with exactly as many digits as
+// it's written with, so years beyond 9999 are never worked with
+fn parse_fields(
+  value: &str,
+  separator: char,
+  lengths: &[usize]
+) -> Option<Vec<i64>> {
+  let fields = value
+    .split(separator)
+    .collect::<Vec<&str>>();
+
+  match fields.len() == lengths.len() {
+    true => fields
+      .iter()
+      .zip(lengths)
+      .map(|(v, len)| parse_number(v).filter(|_| v.len() == *len))
+      .collect(),
+    false => None
+  }
+}
+
+// Days since the Unix epoch of a date in the proleptic Gregorian calendar
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+  let year = if month <= 2 {
+    year - 1
+  } else {
+    year
+  };
+  let era = year.div_euclid(400);
+  let year_of_era = year - era * 400;
+  let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+  let day_of_era =
+    year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+  era * 146_097 + day_of_era - 719_468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+  let days = days + 719_468;
+  let era = days.div_euclid(146_097);
+  let day_of_era = days - era * 146_097;
+  let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524
+    - day_of_era / 146_096)
+    / 365;
+  let day_of_year =
+    day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+  let month = (5 * day_of_year + 2) / 153;
+  let day = day_of_year - (153 * month + 2) / 5 + 1;
+  let month = if month < 10 {
+    month + 3
+  } else {
+    month - 9
+  };
+  let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+  (year, month, day)
+}
+
+impl Timestamp {
+  // Timestamp with seconds and nanoseconds in range, where nanoseconds carry
+  // into seconds, which saturate rather than overflow
+  pub fn new(seconds: i64, nanos: i64) -> Self {
+    Self {
+      seconds: seconds.saturating_add(nanos.div_euclid(NANOS)),
+      nanos: nanos.rem_euclid(NANOS) as i32
+    }
+  }
+
+  // Timestamp as new gives it, unless carrying nanoseconds into seconds
+  // would overflow them
+  pub fn checked_new(seconds: i64, nanos: i64) -> Option<Self> {
+    Some(Self {
+      seconds: seconds.checked_add(nanos.div_euclid(NANOS))?,
+      nanos: nanos.rem_euclid(NANOS) as i32
+    })
+  }
+
+  // RFC 3339 string of a timestamp, as JSON writes it, which only holds years
+  // from 0001 to 9999
+  pub fn to_rfc3339(&self) -> Result<String, String> {
+    match Self::checked_new(self.seconds, self.nanos.into()) {
+      Some(time) if (MIN_SECONDS..=MAX_SECONDS).contains(&time.seconds) => {
+        Ok(time.to_string())
+      }
+      _ => Err(format!(
+        "Timestamp of {} seconds and {} nanos is out of range",
+        self.seconds, self.nanos
+      ))
+    }
+  }
+
+  pub fn now() -> Self {
+    SystemTime::now().into()
+  }
+}
+
+impl Display for Timestamp {
+  fn fmt(&self, f: &mut Formatter) -> FmtResult {
+    let time = Self::new(self.seconds, self.nanos.into());
+    let (year, month, day) = civil_from_days(time.seconds.div_euclid(86_400));
+    let seconds = time.seconds.rem_euclid(86_400);
+
+    write!(
+      f,
+      "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}{}Z",
+      year,
+      month,
+      day,
+      seconds / 3600,
+      seconds / 60 % 60,
+      seconds % 60,
+      format_nanos(time.nanos as u32)
+    )
+  }
+}
+
+impl FromStr for Timestamp {
+  type Err = String;
+
+  // Parse an RFC 3339 timestamp, as in "1972-01-01T10:00:20.021-05:00"
+  fn from_str(value: &str) -> Result<Self, Self::Err> {
+    let invalid = || format!("Invalid timestamp {}", value);
+    let (date, time) = value
+      .split_once(['T', 't'])
+      .ok_or_else(invalid)?;
+    let (time, offset) = match time.strip_suffix(['Z', 'z']) {
+      Some(time) => (time, 0),
+      None => {
+        let at = time
+          .rfind(['+', '-'])
+          .ok_or_else(invalid)?;
+        let offset = parse_fields(&time[at + 1..], ':', &[2, 2])
+          .filter(|v| v[0] < 24 && v[1] < 60)
+          .ok_or_else(invalid)?;
+        let offset = offset[0] * 3600 + offset[1] * 60;
+
+        match &time[at..at + 1] {
+          "-" => (&time[..at], -offset),
+          _ => (&time[..at], offset)
+        }
+      }
+    };
+    let (time, nanos) = match time.split_once('.') {
+      Some((time, nanos)) => (time, parse_nanos(nanos).ok_or_else(invalid)?),
+      None => (time, 0)
+    };
+    let date = parse_fields(date, '-', &[4, 2, 2]).ok_or_else(invalid)?;
+    let time = parse_fields(time, ':', &[2, 2, 2]).ok_or_else(invalid)?;
+    let days = days_from_civil(date[0], date[1], date[2]);
+
+    // Dates that don't exist, such as February 30th, move to the next month
+    // when converted, so they're caught by converting back again
+    if !(1..=12).contains(&date[1])
+      || civil_from_days(days) != (date[0], date[1], date[2])
+      || time[0] > 23
+      || time[1] > 59
+      || time[2] > 59
+    {
+      return Err(invalid());
+    }
+
+    let seconds =
+      days * 86_400 + time[0] * 3600 + time[1] * 60 + time[2] - offset;
+
+    match (MIN_SECONDS..=MAX_SECONDS).contains(&seconds) {
+      true => Ok(Self {
+        seconds,
+        nanos
+      }),
+      false => Err(format!("Timestamp {} is out of range", value))
+    }
+  }
+}
+
+impl From<SystemTime> for Timestamp {
+  fn from(time: SystemTime) -> Self {
+    match time.duration_since(UNIX_EPOCH) {
+      Ok(since) => {
+        Self::new(since.as_secs() as i64, since.subsec_nanos().into())
+      }
+      Err(err) => {
+        let before = err.duration();
+
+        Self::new(
+          -(before.as_secs() as i64),
+          -i64::from(before.subsec_nanos())
+        )
+      }
+    }
+  }
+}
+
+impl From<Timestamp> for SystemTime {
+  fn from(time: Timestamp) -> Self {
+    let time = Timestamp::new(time.seconds, time.nanos.into());
+    let seconds = StdDuration::from_secs(time.seconds.unsigned_abs());
+    let nanos = StdDuration::from_nanos(time.nanos as u64);
+
+    match time.seconds < 0 {
+      true => UNIX_EPOCH - seconds + nanos,
+      false => UNIX_EPOCH + seconds + nanos
+    }
+  }
+}
+
+impl Duration {
+  // Duration with seconds and nanoseconds in range, where nanoseconds carry
+  // into seconds and both take the sign of the total
+  pub fn new(seconds: i64, nanos: i64) -> Self {
+    let total = i128::from(seconds) * i128::from(NANOS) + i128::from(nanos);
+
+    Self {
+      seconds: (total / i128::from(NANOS)) as i64,
+      nanos: (total % i128::from(NANOS)) as i32
+    }
+  }
+}
+
+impl Display for Duration {
+  fn fmt(&self, f: &mut Formatter) -> FmtResult {
+    let duration = Self::new(self.seconds, self.nanos.into());
+    let sign = match duration.seconds < 0 || duration.nanos < 0 {
+      true => "-",
+      false => ""
+    };
+
+    write!(
+      f,
+      "{}{}{}s",
+      sign,
+      duration.seconds.unsigned_abs(),
+      format_nanos(duration.nanos.unsigned_abs())
+    )
+  }
+}
+
+impl FromStr for Duration {
+  type Err = String;
+
+  fn from_str(value: &str) -> Result<Self, Self::Err> {
+    let invalid = || format!("Invalid duration {}", value);
+    let digits = value
+      .strip_suffix('s')
+      .ok_or_else(invalid)?;
+    let (negative, digits) = match digits.strip_prefix('-') {
+      Some(digits) => (true, digits),
+      None => (false, digits)
+    };
+    let (seconds, nanos) = match digits.split_once('.') {
+      Some((seconds, nanos)) => (seconds, parse_nanos(nanos)),
+      None => (digits, Some(0))
+    };
+    let seconds = parse_number(seconds)
+      .filter(|v| *v <= 315_576_000_000)
+      .zip(nanos)
+      .ok_or_else(invalid)?;
+
+    match negative {
+      true => Ok(Self::new(-seconds.0, -i64::from(seconds.1))),
+      false => Ok(Self::new(seconds.0, seconds.1.into()))
+    }
+  }
+}
+
+impl From<StdDuration> for Duration {
+  fn from(duration: StdDuration) -> Self {
+    Self::new(duration.as_secs() as i64, duration.subsec_nanos().into())
+  }
+}
+
+impl TryFrom<Duration> for StdDuration {
+  type Error = String;
+
+  // Durations that are negative can't be represented
+  fn try_from(duration: Duration) -> Result<Self, Self::Error> {
+    let duration = Duration::new(duration.seconds, duration.nanos.into());
+
+    match duration.seconds < 0 || duration.nanos < 0 {
+      true => Err(format!("Duration {} is negative", duration)),
+      false => Ok(Self::new(duration.seconds as u64, duration.nanos as u32))
+    }
+  }
+}
+
+#[cfg(feature = "chrono")]
+impl From<chrono::DateTime<chrono::Utc>> for Timestamp {
+  fn from(time: chrono::DateTime<chrono::Utc>) -> Self {
+    Self::new(time.timestamp(), time.timestamp_subsec_nanos().into())
+  }
+}
+
+#[cfg(feature = "chrono")]
+impl TryFrom<Timestamp> for chrono::DateTime<chrono::Utc> {
+  type Error = String;
+
+  fn try_from(time: Timestamp) -> Result<Self, Self::Error> {
+    Timestamp::checked_new(time.seconds, time.nanos.into())
+      .and_then(|v| Self::from_timestamp(v.seconds, v.nanos as u32))
+      .ok_or_else(|| format!("Timestamp {} is out of range", time))
+  }
+}
+
+#[cfg(feature = "time")]
+impl From<time::OffsetDateTime> for Timestamp {
+  fn from(time: time::OffsetDateTime) -> Self {
+    Self::new(time.unix_timestamp(), time.nanosecond().into())
+  }
+}
+
+#[cfg(feature = "time")]
+impl TryFrom<Timestamp> for time::OffsetDateTime {
+  type Error = String;
+
+  fn try_from(time: Timestamp) -> Result<Self, Self::Error> {
+    let nanos =
+      i128::from(time.seconds) * i128::from(NANOS) + i128::from(time.nanos);
+
+    Self::from_unix_timestamp_nanos(nanos)
+      .map_err(|_| format!("Timestamp {} is out of range", time))
+  }
+}
+
+#[cfg(feature = "time")]
+impl From<time::Duration> for Duration {
+  fn from(duration: time::Duration) -> Self {
+    Self::new(
+      duration.whole_seconds(),
+      duration.subsec_nanoseconds().into()
+    )
+  }
+}
+
+#[cfg(feature = "time")]
+impl From<Duration> for time::Duration {
+  fn from(duration: Duration) -> Self {
+    let duration = Duration::new(duration.seconds, duration.nanos.into());
+
+    Self::new(duration.seconds, duration.nanos)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn timestamp_strings() -> Result<(), String> {
+    let time = "1972-01-01T10:00:20.021-05:00".parse::<Timestamp>()?;
+
+    assert_eq!(time, Timestamp::new(63_126_020, 21_000_000));
+    assert_eq!(time.to_string(), "1972-01-01T15:00:20.021Z");
+    assert_eq!(
+      Timestamp::new(-1, 1).to_string(),
+      "1969-12-31T23:59:59.000000001Z"
+    );
+    assert_eq!(
+      "0001-01-01T00:00:00Z"
+        .parse::<Timestamp>()?
+        .seconds,
+      MIN_SECONDS
+    );
+    assert!("2023-02-29T00:00:00Z"
+      .parse::<Timestamp>()
+      .is_err());
+    assert!("2023-01-01 00:00:00Z"
+      .parse::<Timestamp>()
+      .is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn reject_out_of_range_timestamps() {
+    for input in [
+      "1000000000000-01-01T00:00:00Z",
+      "10000-01-01T00:00:00Z",
+      "2023-1-01T00:00:00Z",
+      "2023-01-01T00:00:00+100:00"
+    ] {
+      assert!(input.parse::<Timestamp>().is_err(), "{}", input);
+    }
+
+    let max = Timestamp {
+      seconds: i64::MAX,
+      nanos: i32::MAX
+    };
+
+    assert_eq!(
+      Timestamp::new(max.seconds, max.nanos.into()).seconds,
+      i64::MAX
+    );
+    assert_eq!(Timestamp::checked_new(max.seconds, max.nanos.into()), None);
+    assert!(!max.to_string().is_empty());
+    assert!(max.to_rfc3339().is_err());
+    assert!(Timestamp::new(MAX_SECONDS + 1, 0)
+      .to_rfc3339()
+      .is_err());
+    assert_eq!(
+      Timestamp::new(MAX_SECONDS, 0).to_rfc3339(),
+      Ok("9999-12-31T23:59:59Z".to_string())
+    );
+  }
+
+  #[test]
+  fn duration_strings() -> Result<(), String> {
+    assert_eq!("1.5s".parse::<Duration>()?, Duration::new(1, 500_000_000));
+    assert_eq!("-0.000001s".parse::<Duration>()?, Duration::new(0, -1_000));
+    assert_eq!(Duration::new(-2, 500_000_000).to_string(), "-1.500s");
+    assert_eq!(Duration::new(3, 0).to_string(), "3s");
+    assert!("1.5".parse::<Duration>().is_err());
+    assert!("1.1234567891s"
+      .parse::<Duration>()
+      .is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn system_time() {
+    let time = UNIX_EPOCH - StdDuration::from_millis(1500);
+
+    assert_eq!(Timestamp::from(time), Timestamp::new(-2, 500_000_000));
+    assert_eq!(SystemTime::from(Timestamp::from(time)), time);
+  }
+
+  #[cfg(feature = "chrono")]
+  #[test]
+  fn chrono_types() -> Result<(), String> {
+    let time = "2024-02-29T12:00:00.5Z".parse::<Timestamp>()?;
+    let converted = chrono::DateTime::<chrono::Utc>::try_from(time)?;
+
+    assert_eq!(converted.timestamp(), 1_709_208_000);
+    assert_eq!(converted.timestamp_subsec_millis(), 500);
+    assert_eq!(Timestamp::from(converted), time);
+
+    Ok(())
+  }
+
+  #[cfg(feature = "time")]
+  #[test]
+  fn time_types() -> Result<(), String> {
+    let time = Timestamp::new(-1, 250_000_000);
+    let converted = time::OffsetDateTime::try_from(time)?;
+
+    assert_eq!(converted.unix_timestamp(), -1);
+    assert_eq!(Timestamp::from(converted), time);
+    assert_eq!(
+      Duration::from(time::Duration::from(Duration::new(-1, -5))),
+      Duration::new(-1, -5)
+    );
+
+    Ok(())
+  }
+}
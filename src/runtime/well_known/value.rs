@@ -0,0 +1,206 @@
+use super::encoding::{self, DecodeError, Message, WireType};
+use super::MessageDescriptor;
+use std::collections::BTreeMap;
+use std::mem::take;
+
+// JSON object, whose fields are kept in order so it's always encoded the same
+// way
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Struct {
+  pub fields: BTreeMap<String, Value>
+}
+
+// JSON value, which is a oneof of each kind in its message
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Value {
+  #[default]
+  Null,
+  Number(f64),
+  String(String),
+  Bool(bool),
+  Struct(Struct),
+  List(ListValue)
+}
+
+// JSON array
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ListValue {
+  pub values: Vec<Value>
+}
+
+impl Message for Struct {
+  describe!(Struct);
+
+  // Fields are a map, which is encoded as repeated entries of a key and value
+  fn encode(&self, buf: &mut Vec<u8>) {
+    for (key, value) in &self.fields {
+      let mut entry = Vec::new();
+
+      encoding::string::encode(1, key, &mut entry);
+      encoding::message::encode(2, value, &mut entry);
+      encoding::encode_bytes(1, &entry, buf);
+    }
+  }
+
+  fn merge_field(
+    &mut self,
+    number: u32,
+    wire: WireType,
+    buf: &mut &[u8]
+  ) -> Result<(), DecodeError> {
+    if number != 1 {
+      return encoding::skip_field(wire, buf);
+    }
+
+    encoding::check_wire(WireType::LengthDelimited, wire)?;
+
+    let mut entry = encoding::decode_bytes(buf)?;
+    let (mut key, mut value) = (String::new(), Value::default());
+
+    while !entry.is_empty() {
+      match encoding::decode_key(&mut entry)? {
+        (1, wire) => encoding::string::merge(wire, &mut key, &mut entry)?,
+        (2, wire) => encoding::message::merge(wire, &mut value, &mut entry)?,
+        (_, wire) => encoding::skip_field(wire, &mut entry)?
+      }
+    }
+
+    self.fields.insert(key, value);
+
+    Ok(())
+  }
+}
+
+impl Message for Value {
+  describe!(Value);
+
+  // Whichever kind is set is always written, even when it's a default
+  fn encode(&self, buf: &mut Vec<u8>) {
+    match self {
+      Self::Null => {
+        encoding::encode_key(1, WireType::Varint, buf);
+        encoding::encode_varint(0, buf);
+      }
+      Self::Number(value) => {
+        encoding::encode_key(2, WireType::Fixed64, buf);
+        buf.extend(value.to_le_bytes());
+      }
+      Self::String(value) => encoding::encode_bytes(3, value.as_bytes(), buf),
+      Self::Bool(value) => {
+        encoding::encode_key(4, WireType::Varint, buf);
+        encoding::encode_varint(u64::from(*value), buf);
+      }
+      Self::Struct(value) => encoding::message::encode(5, value, buf),
+      Self::List(value) => encoding::message::encode(6, value, buf)
+    }
+  }
+
+  fn merge_field(
+    &mut self,
+    number: u32,
+    wire: WireType,
+    buf: &mut &[u8]
+  ) -> Result<(), DecodeError> {
+    *self = match number {
+      1 => {
+        encoding::check_wire(WireType::Varint, wire)?;
+        encoding::decode_varint(buf)?;
+
+        Self::Null
+      }
+      2 => {
+        let mut value = 0.0;
+
+        encoding::double::merge(wire, &mut value, buf)?;
+
+        Self::Number(value)
+      }
+      3 => {
+        let mut value = String::new();
+
+        encoding::string::merge(wire, &mut value, buf)?;
+
+        Self::String(value)
+      }
+      4 => {
+        let mut value = false;
+
+        encoding::bool::merge(wire, &mut value, buf)?;
+
+        Self::Bool(value)
+      }
+      // Messages set more than once are merged, as with any oneof
+      5 => {
+        let mut value = match take(self) {
+          Self::Struct(value) => value,
+          _ => Struct::default()
+        };
+
+        encoding::message::merge(wire, &mut value, buf)?;
+
+        Self::Struct(value)
+      }
+      6 => {
+        let mut value = match take(self) {
+          Self::List(value) => value,
+          _ => ListValue::default()
+        };
+
+        encoding::message::merge(wire, &mut value, buf)?;
+
+        Self::List(value)
+      }
+      _ => return encoding::skip_field(wire, buf)
+    };
+
+    Ok(())
+  }
+}
+
+impl Message for ListValue {
+  describe!(ListValue);
+
+  fn encode(&self, buf: &mut Vec<u8>) {
+    encoding::message::encode_repeated(1, &self.values, buf);
+  }
+
+  fn merge_field(
+    &mut self,
+    number: u32,
+    wire: WireType,
+    buf: &mut &[u8]
+  ) -> Result<(), DecodeError> {
+    match number {
+      1 => encoding::message::merge_repeated(wire, &mut self.values, buf),
+      _ => encoding::skip_field(wire, buf)
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn struct_round_trip() -> Result<(), DecodeError> {
+    let list = ListValue {
+      values: vec![Value::Null, Value::Number(0.0), Value::Bool(false)]
+    };
+    let value = Struct {
+      fields: BTreeMap::from([
+        ("a".to_string(), Value::String("b".to_string())),
+        ("c".to_string(), Value::List(list))
+      ])
+    };
+
+    assert_eq!(Struct::decode(&value.encode_to_vec())?, value);
+
+    Ok(())
+  }
+
+  #[test]
+  fn encode_default_kinds() {
+    assert_eq!(Value::Null.encode_to_vec(), vec![8, 0]);
+    assert_eq!(Value::Bool(false).encode_to_vec(), vec![32, 0]);
+  }
+}
@@ -0,0 +1,70 @@
+use super::encoding::{self, DecodeError, Message, WireType};
+use super::{field, FieldType, MessageDescriptor};
+
+// Messages that wrap a single scalar in field 1, so it can be told apart
+// from its default when it's unset
+macro_rules! wrapper {
+  ($name:ident, $type:ty, $encoding:ident $(, $field:expr)?) => {
+    #[derive(Clone, Debug, Default, PartialEq)]
+    pub struct $name {
+      pub value: $type
+    }
+
+    impl Message for $name {
+      describe!($name $(, field("value", 1, $field))?);
+
+      fn encode(&self, buf: &mut Vec<u8>) {
+        encoding::$encoding::encode(1, &self.value, buf);
+      }
+
+      fn merge_field(
+        &mut self,
+        number: u32,
+        wire: WireType,
+        buf: &mut &[u8]
+      ) -> Result<(), DecodeError> {
+        match number {
+          1 => encoding::$encoding::merge(wire, &mut self.value, buf),
+          _ => encoding::skip_field(wire, buf)
+        }
+      }
+    }
+
+    impl From<$type> for $name {
+      fn from(value: $type) -> Self {
+        Self {
+          value
+        }
+      }
+    }
+  };
+}
+
+wrapper!(BoolValue, bool, bool, FieldType::Bool);
+wrapper!(BytesValue, Vec<u8>, bytes, FieldType::Bytes);
+wrapper!(DoubleValue, f64, double);
+wrapper!(FloatValue, f32, float);
+wrapper!(Int32Value, i32, int32, FieldType::Int32);
+wrapper!(Int64Value, i64, int64, FieldType::Int64);
+wrapper!(StringValue, String, string, FieldType::String);
+wrapper!(UInt32Value, u32, uint32);
+wrapper!(UInt64Value, u64, uint64);
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn wrapper_round_trip() -> Result<(), DecodeError> {
+    let value = Int64Value::from(-3);
+    let bytes = BytesValue::from(vec![0, 1]);
+
+    assert_eq!(Int64Value::decode(&value.encode_to_vec())?, value);
+    assert_eq!(BytesValue::decode(&bytes.encode_to_vec())?, bytes);
+    assert!(StringValue::default()
+      .encode_to_vec()
+      .is_empty());
+
+    Ok(())
+  }
+}
@@ -2,7 +2,7 @@ use regex::{Error as RegexError, Regex};
 
 type TokenResult<T> = Result<T, RegexError>;
 
-type TokenVector<'a, T> = TokenResult<Vec<T>>;
+type TokenVector<T> = TokenResult<Vec<T>>;
 
 fn strip_comments(raw_str: &str) -> TokenResult<String> {
   let re = Regex::new(r"//.*")?;
@@ -11,8 +11,10 @@ fn strip_comments(raw_str: &str) -> TokenResult<String> {
   Ok(result.to_string())
 }
 
-fn into_tokens<'a>(raw_str: &str) -> TokenVector<String> {
-  let re = Regex::new("[[:alnum:]]+|[[:punct:]]")?;
+fn into_tokens(raw_str: &str) -> TokenVector<String> {
+  // Identifiers keep their dots and underscores so qualified names such as
+  // "foo.bar.Baz" or ".foo.Baz" remain a single token
+  let re = Regex::new(r"\.?[[:word:]]+(?:\.[[:word:]]+)*|[[:punct:]]")?;
   let result = re
     .captures_iter(raw_str)
     .flat_map(|v| {
@@ -25,7 +27,7 @@ fn into_tokens<'a>(raw_str: &str) -> TokenVector<String> {
   Ok(result)
 }
 
-pub fn translate<'a>(input: &str) -> TokenVector<String> {
+pub fn translate(input: &str) -> TokenVector<String> {
   let stripped = strip_comments(input)?;
   let tokens = into_tokens(&stripped)?;
 
@@ -88,4 +90,33 @@ mod tests {
 
     Ok(())
   }
+
+  #[test]
+  fn translate_qualified_names() -> Result<(), RegexError> {
+    let input = "
+      package foo.bar_baz.v1;
+      message Foo {
+        .foo.Bar some_bar = 1;
+      }
+    ";
+    let result = translate(input)?;
+    let expect = vec![
+      "package",
+      "foo.bar_baz.v1",
+      ";",
+      "message",
+      "Foo",
+      "{",
+      ".foo.Bar",
+      "some_bar",
+      "=",
+      "1",
+      ";",
+      "}",
+    ];
+
+    assert_eq!(result, expect);
+
+    Ok(())
+  }
 }
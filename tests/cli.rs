@@ -0,0 +1,158 @@
+use std::fs::{create_dir_all, read, read_to_string, remove_dir_all, write};
+use std::path::{Path, PathBuf};
+use std::process::{id, Command, Output};
+
+// Directory of a test's own under the temp directory, which is removed again
+// once the test is done with it
+struct TempDir(PathBuf);
+
+impl TempDir {
+  fn new(name: &str) -> Self {
+    let path = std::env::temp_dir().join(format!("pbrs_{}_{}", name, id()));
+    let _ = remove_dir_all(&path);
+
+    create_dir_all(&path).unwrap();
+
+    Self(path)
+  }
+
+  fn path(&self) -> &Path {
+    &self.0
+  }
+}
+
+impl Drop for TempDir {
+  fn drop(&mut self) {
+    let _ = remove_dir_all(&self.0);
+  }
+}
+
+fn pbrs(args: &[&str]) -> Output {
+  Command::new(env!("CARGO_BIN_EXE_pbrs"))
+    .args(args)
+    .output()
+    .unwrap()
+}
+
+#[test]
+fn generate_files() {
+  let output = pbrs(&["generate", "-I", "tests", "fixtures/*.proto"]);
+  let expected = read_to_string("tests/fixtures/counter.rs").unwrap();
+
+  assert_eq!(output.status.code(), Some(0));
+  assert_eq!(String::from_utf8_lossy(&output.stdout), expected);
+}
+
+#[test]
+fn report_problems() {
+  let dir = TempDir::new("report_problems");
+  let path = dir.path().join("a.proto");
+
+  write(&path, "package pkg;\nmessage Foo {\n  Bar bar = 1;\n}\n").unwrap();
+
+  let output = pbrs(&["check", "--quiet", path.to_str().unwrap()]);
+
+  assert_eq!(output.status.code(), Some(1));
+  assert_eq!(
+    String::from_utf8_lossy(&output.stdout),
+    format!("{}: pkg.Foo.bar: Unknown type Bar\n", path.display())
+  );
+}
+
+#[test]
+fn generate_checked_files() {
+  let dir = TempDir::new("generate_checked_files");
+  let path = dir.path().join("a.proto");
+  let out = dir.path().join("out");
+
+  write(&path, "package pkg;\nmessage Foo {\n  int64 big = 1;\n}\n").unwrap();
+
+  let output = pbrs(&[
+    "generate",
+    path.to_str().unwrap(),
+    "--out-dir",
+    out.to_str().unwrap()
+  ]);
+
+  assert_eq!(output.status.code(), Some(1));
+  assert_eq!(
+    String::from_utf8_lossy(&output.stderr),
+    format!(
+      "{}: pkg.Foo.big: Unknown type int64\nerror: Found 1 problems\n",
+      path.display()
+    )
+  );
+  assert!(!out.exists());
+}
+
+#[test]
+fn report_malformed_files() {
+  let dir = TempDir::new("report_malformed_files");
+  let path = dir.path().join("a.proto");
+
+  write(&path, "message A {\n  int32 a = x;\n}\n").unwrap();
+
+  let output = pbrs(&["check", "--quiet", path.to_str().unwrap()]);
+
+  assert_eq!(output.status.code(), Some(1));
+  assert_eq!(
+    String::from_utf8_lossy(&output.stdout),
+    format!("{}: Invalid number x for field a\n", path.display())
+  );
+}
+
+#[test]
+fn write_descriptor_sets() {
+  let dir = TempDir::new("write_descriptor_sets");
+  let set = dir.path().join("set.pb");
+  let output = pbrs(&[
+    "generate",
+    "-I",
+    "tests",
+    "fixtures/counter.proto",
+    "--descriptor-set-out",
+    set.to_str().unwrap()
+  ]);
+
+  assert_eq!(output.status.code(), Some(0));
+  assert!(set.exists());
+}
+
+// The expected set was written by protoc 36.2, with
+// "protoc -I tests/fixtures/shapes --descriptor_set_out=shapes.pb shapes.proto"
+#[test]
+fn match_protoc_descriptor_sets() {
+  let dir = TempDir::new("match_protoc_descriptor_sets");
+  let set = dir.path().join("set.pb");
+  let output = pbrs(&[
+    "generate",
+    "-I",
+    "tests/fixtures/shapes",
+    "shapes.proto",
+    "--descriptor-set-out",
+    set.to_str().unwrap()
+  ]);
+
+  assert_eq!(output.status.code(), Some(0));
+  assert_eq!(
+    read(&set).unwrap(),
+    read("tests/fixtures/shapes/shapes.pb").unwrap()
+  );
+}
+
+#[test]
+fn reject_usage() {
+  assert_eq!(pbrs(&[]).status.code(), Some(2));
+  assert_eq!(
+    pbrs(&["lint", "--unknown", "a.proto"])
+      .status
+      .code(),
+    Some(2)
+  );
+  assert_eq!(
+    pbrs(&["check", "missing.proto"])
+      .status
+      .code(),
+    Some(1)
+  );
+}
@@ -0,0 +1,11 @@
+
+tests/fixtures/counter.protoz
+tests/fixtures/counter.protocounter"3
+Count
+value (Rvalue
+label (	Rlabel2
+Counter+
+	Increment.counter.Count.counter.Count'
+Sum.counter.Count.counter.Count()
+Range.counter.Count.counter.Count0,
+Double.counter.Count.counter.Count(0bproto3
\ No newline at end of file
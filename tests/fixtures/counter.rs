@@ -0,0 +1,274 @@
+pub mod counter {
+  #[derive(Clone, Debug)]
+  pub struct CounterClient<T> {
+    transport: T
+  }
+
+  impl<T: ::pbrs::runtime::Transport> CounterClient<T> {
+    pub fn new(transport: T) -> Self {
+      Self { transport }
+    }
+
+    pub async fn increment(&self, req: &Count) -> Result<Count, ::pbrs::runtime::Status> {
+      self
+        .increment_with_options(req, Default::default())
+        .await
+        .map(|v| v.message)
+    }
+
+    pub async fn increment_with_options(
+      &self,
+      req: &Count,
+      options: ::pbrs::runtime::CallOptions
+    ) -> Result<::pbrs::runtime::Response<Count>, ::pbrs::runtime::Status> {
+      ::pbrs::runtime::unary(&self.transport, "/counter.Counter/Increment", req, options).await
+    }
+
+    pub async fn sum(&self, req: ::pbrs::runtime::Streaming<Count>) -> Result<Count, ::pbrs::runtime::Status> {
+      self
+        .sum_with_options(req, Default::default())
+        .await
+        .map(|v| v.message)
+    }
+
+    pub async fn sum_with_options(
+      &self,
+      req: ::pbrs::runtime::Streaming<Count>,
+      options: ::pbrs::runtime::CallOptions
+    ) -> Result<::pbrs::runtime::Response<Count>, ::pbrs::runtime::Status> {
+      ::pbrs::runtime::client_streaming(&self.transport, "/counter.Counter/Sum", req, options).await
+    }
+
+    pub async fn range(&self, req: &Count) -> Result<::pbrs::runtime::Streaming<Count>, ::pbrs::runtime::Status> {
+      self
+        .range_with_options(req, Default::default())
+        .await
+        .map(|v| v.message)
+    }
+
+    pub async fn range_with_options(
+      &self,
+      req: &Count,
+      options: ::pbrs::runtime::CallOptions
+    ) -> Result<::pbrs::runtime::Response<::pbrs::runtime::Streaming<Count>>, ::pbrs::runtime::Status> {
+      ::pbrs::runtime::server_streaming(&self.transport, "/counter.Counter/Range", req, options).await
+    }
+
+    pub async fn double(&self, req: ::pbrs::runtime::Streaming<Count>) -> Result<::pbrs::runtime::Streaming<Count>, ::pbrs::runtime::Status> {
+      self
+        .double_with_options(req, Default::default())
+        .await
+        .map(|v| v.message)
+    }
+
+    pub async fn double_with_options(
+      &self,
+      req: ::pbrs::runtime::Streaming<Count>,
+      options: ::pbrs::runtime::CallOptions
+    ) -> Result<::pbrs::runtime::Response<::pbrs::runtime::Streaming<Count>>, ::pbrs::runtime::Status> {
+      ::pbrs::runtime::streaming(&self.transport, "/counter.Counter/Double", req, options).await
+    }
+  }
+
+  #[derive(Clone, Debug, PartialEq, Default)]
+  pub struct Count {
+    pub value: i32,
+    pub label: String
+  }
+
+  impl ::pbrs::runtime::Message for Count {
+    fn encode(&self, buf: &mut Vec<u8>) {
+      ::pbrs::runtime::encoding::int32::encode(1, &self.value, buf);
+      ::pbrs::runtime::encoding::string::encode(2, &self.label, buf);
+    }
+
+    fn merge_field(
+      &mut self,
+      number: u32,
+      wire: ::pbrs::runtime::WireType,
+      buf: &mut &[u8]
+    ) -> Result<(), ::pbrs::runtime::DecodeError> {
+      match number {
+        1 => ::pbrs::runtime::encoding::int32::merge(wire, &mut self.value, buf),
+        2 => ::pbrs::runtime::encoding::string::merge(wire, &mut self.label, buf),
+        _ => ::pbrs::runtime::encoding::skip_field(wire, buf)
+      }
+    }
+
+    fn descriptor() -> &'static ::pbrs::runtime::MessageDescriptor {
+      static DESCRIPTOR: ::pbrs::runtime::MessageDescriptor =
+        ::pbrs::runtime::MessageDescriptor {
+          name: "Count",
+          full_name: "counter.Count",
+          fields: &[
+            ::pbrs::runtime::FieldDescriptor {
+              name: "value",
+              json_name: "value",
+              number: 1,
+              label: ::pbrs::runtime::Label::Optional,
+              r#type: ::pbrs::runtime::FieldType::Int32
+            },
+            ::pbrs::runtime::FieldDescriptor {
+              name: "label",
+              json_name: "label",
+              number: 2,
+              label: ::pbrs::runtime::Label::Optional,
+              r#type: ::pbrs::runtime::FieldType::String
+            }
+          ]
+        };
+
+      &DESCRIPTOR
+    }
+  }
+
+  impl ::pbrs::runtime::Masked for Count {
+    fn is_path(path: &[&str]) -> bool {
+      ::pbrs::runtime::mask::is_field(path, "value")
+        || ::pbrs::runtime::mask::is_field(path, "label")
+    }
+
+    fn merge_path(&mut self, source: &mut Self, path: &[&str]) {
+      ::pbrs::runtime::mask::merge_field(&mut self.value, &mut source.value, path, "value");
+      ::pbrs::runtime::mask::merge_field(&mut self.label, &mut source.label, path, "label");
+    }
+
+    fn trim_paths(&mut self, paths: &[&[&str]]) {
+      ::pbrs::runtime::mask::trim_field(&mut self.value, paths, "value");
+      ::pbrs::runtime::mask::trim_field(&mut self.label, paths, "label");
+    }
+  }
+
+  pub trait Counter: Send + Sync + 'static {
+    fn increment(
+      &self,
+      req: ::pbrs::runtime::Request<Count>
+    ) -> impl ::std::future::Future<Output = Result<::pbrs::runtime::Response<Count>, ::pbrs::runtime::Status>> + Send;
+
+    fn sum(
+      &self,
+      req: ::pbrs::runtime::Request<::pbrs::runtime::Streaming<Count>>
+    ) -> impl ::std::future::Future<Output = Result<::pbrs::runtime::Response<Count>, ::pbrs::runtime::Status>> + Send;
+
+    fn range(
+      &self,
+      req: ::pbrs::runtime::Request<Count>
+    ) -> impl ::std::future::Future<Output = Result<::pbrs::runtime::Response<::pbrs::runtime::Streaming<Count>>, ::pbrs::runtime::Status>> + Send;
+
+    fn double(
+      &self,
+      req: ::pbrs::runtime::Request<::pbrs::runtime::Streaming<Count>>
+    ) -> impl ::std::future::Future<Output = Result<::pbrs::runtime::Response<::pbrs::runtime::Streaming<Count>>, ::pbrs::runtime::Status>> + Send;
+  }
+
+  pub struct CounterServer<S> {
+    inner: ::std::sync::Arc<S>
+  }
+
+  impl<S: Counter> CounterServer<S> {
+    pub fn new(inner: S) -> Self {
+      Self::from_arc(::std::sync::Arc::new(inner))
+    }
+
+    pub fn from_arc(inner: ::std::sync::Arc<S>) -> Self {
+      Self { inner }
+    }
+  }
+
+  impl<S: Counter> ::pbrs::runtime::Service for CounterServer<S> {
+    fn name(&self) -> &'static str {
+      "counter.Counter"
+    }
+
+    fn call<'a>(
+      &'a self,
+      path: &'a str,
+      req: ::pbrs::runtime::Request<::pbrs::runtime::Streaming<Vec<u8>>>
+    ) -> ::pbrs::runtime::BoxFuture<'a, Result<::pbrs::runtime::Response<::pbrs::runtime::Streaming<Vec<u8>>>, ::pbrs::runtime::Status>> {
+      match path {
+        "/counter.Counter/Increment" => Box::pin(::pbrs::runtime::handle_unary(req, |req| {
+          self.inner.increment(req)
+        })),
+        "/counter.Counter/Sum" => Box::pin(::pbrs::runtime::handle_client_streaming(req, |req| {
+          self.inner.sum(req)
+        })),
+        "/counter.Counter/Range" => Box::pin(::pbrs::runtime::handle_server_streaming(req, |req| {
+          self.inner.range(req)
+        })),
+        "/counter.Counter/Double" => Box::pin(::pbrs::runtime::handle_streaming(req, |req| {
+          self.inner.double(req)
+        })),
+        _ => Box::pin(async move { Err(::pbrs::runtime::Status::unimplemented(path)) })
+      }
+    }
+  }
+
+  pub struct MockCounter {
+    pub increment: ::pbrs::runtime::MockMethod<Count, Count>,
+    pub sum: ::pbrs::runtime::MockMethod<Vec<Count>, Count>,
+    pub range: ::pbrs::runtime::MockMethod<Count, ::pbrs::runtime::Streaming<Count>>,
+    pub double: ::pbrs::runtime::MockMethod<Vec<Count>, ::pbrs::runtime::Streaming<Count>>
+  }
+
+  impl MockCounter {
+    pub fn new() -> Self {
+      Self {
+        increment: ::pbrs::runtime::MockMethod::new("/counter.Counter/Increment"),
+        sum: ::pbrs::runtime::MockMethod::new("/counter.Counter/Sum"),
+        range: ::pbrs::runtime::MockMethod::new("/counter.Counter/Range"),
+        double: ::pbrs::runtime::MockMethod::new("/counter.Counter/Double")
+      }
+    }
+
+    // Panic unless every method was called as expected
+    pub fn verify(&self) {
+      self.increment.verify();
+      self.sum.verify();
+      self.range.verify();
+      self.double.verify();
+    }
+  }
+
+  impl Default for MockCounter {
+    fn default() -> Self {
+      Self::new()
+    }
+  }
+
+  impl Counter for MockCounter {
+    fn increment(
+      &self,
+      req: ::pbrs::runtime::Request<Count>
+    ) -> impl ::std::future::Future<Output = Result<::pbrs::runtime::Response<Count>, ::pbrs::runtime::Status>> + Send {
+      self.increment.call(req)
+    }
+
+    fn sum(
+      &self,
+      req: ::pbrs::runtime::Request<::pbrs::runtime::Streaming<Count>>
+    ) -> impl ::std::future::Future<Output = Result<::pbrs::runtime::Response<Count>, ::pbrs::runtime::Status>> + Send {
+      self.sum.call_streaming(req)
+    }
+
+    fn range(
+      &self,
+      req: ::pbrs::runtime::Request<Count>
+    ) -> impl ::std::future::Future<Output = Result<::pbrs::runtime::Response<::pbrs::runtime::Streaming<Count>>, ::pbrs::runtime::Status>> + Send {
+      self.range.call(req)
+    }
+
+    fn double(
+      &self,
+      req: ::pbrs::runtime::Request<::pbrs::runtime::Streaming<Count>>
+    ) -> impl ::std::future::Future<Output = Result<::pbrs::runtime::Response<::pbrs::runtime::Streaming<Count>>, ::pbrs::runtime::Status>> + Send {
+      self.double.call_streaming(req)
+    }
+  }
+
+  pub fn register_types(
+    registry: ::pbrs::runtime::TypeRegistry
+  ) -> ::pbrs::runtime::TypeRegistry {
+    registry
+      .add::<Count>()
+  }
+}
@@ -0,0 +1,17 @@
+
+
+shapes.proto	shapes.v1"
+Shape!
+display_name (	RdisplayName.
+origin (2.shapes.v1.Shape.PointRorigin
+	is_closed (RisClosed1
+Point
+x_pos (RxPos
+y_pos (RyPos"j
+Drawing.
+anchor (2.shapes.v1.Shape.PointRanchor/
+
+main_shape (2.shapes.v1.ShapeR	mainShape2m
+Canvas,
+Draw.shapes.v1.Shape.shapes.v1.Drawing5
+Watch.shapes.v1.Drawing.shapes.v1.Shape.Point0bproto3
\ No newline at end of file
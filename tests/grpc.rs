@@ -0,0 +1,296 @@
+#![cfg(feature = "grpc")]
+
+use pbrs::runtime::encoding::{self, WireType};
+use pbrs::runtime::grpc::{serve, Channel};
+use pbrs::runtime::{
+  channel,
+  CallOptions,
+  Code,
+  DecodeError,
+  Message,
+  Request,
+  Response,
+  Router,
+  Status,
+  Streaming
+};
+use std::net::SocketAddr;
+use std::process::Command;
+use std::time::Duration;
+use tokio::net::TcpListener;
+
+include!("fixtures/counter.rs");
+
+use counter::{Count, Counter, CounterClient, CounterServer};
+
+struct Service;
+
+impl Counter for Service {
+  async fn increment(
+    &self,
+    req: Request<Count>
+  ) -> Result<Response<Count>, Status> {
+    let label = req
+      .metadata
+      .get("label")
+      .cloned()
+      .unwrap_or_default();
+
+    match req.message.value {
+      v if v < 0 => Err(Status::invalid_argument("Negative count")),
+      v => Ok(Response::new(Count {
+        value: v + 1,
+        label
+      }))
+    }
+  }
+
+  async fn sum(
+    &self,
+    req: Request<Streaming<Count>>
+  ) -> Result<Response<Count>, Status> {
+    let counts = req.message.collect().await?;
+
+    Ok(Response::new(Count {
+      value: counts.iter().map(|v| v.value).sum(),
+      ..Default::default()
+    }))
+  }
+
+  async fn range(
+    &self,
+    req: Request<Count>
+  ) -> Result<Response<Streaming<Count>>, Status> {
+    let counts = (0..req.message.value).map(|value| {
+      Ok(Count {
+        value,
+        ..Default::default()
+      })
+    });
+
+    Ok(Response::new(Streaming::iter(counts.collect::<Vec<_>>())))
+  }
+
+  async fn double(
+    &self,
+    req: Request<Streaming<Count>>
+  ) -> Result<Response<Streaming<Count>>, Status> {
+    let counts = req.message.map(|v| match v.value {
+      0 => Err(Status::new(Code::OutOfRange, "Zero count")),
+      value => Ok(Count {
+        value: value * 2,
+        ..Default::default()
+      })
+    });
+
+    Ok(Response::new(counts))
+  }
+}
+
+// Request written byte for byte, as a peer that doesn't follow the schema
+// might send it
+struct Raw(Vec<u8>);
+
+impl Message for Raw {
+  fn encode(&self, buf: &mut Vec<u8>) {
+    buf.extend_from_slice(&self.0);
+  }
+
+  fn merge_field(
+    &mut self,
+    _: u32,
+    wire: WireType,
+    buf: &mut &[u8]
+  ) -> Result<(), DecodeError> {
+    encoding::skip_field(wire, buf)
+  }
+}
+
+async fn connect() -> Channel {
+  let listener = TcpListener::bind("127.0.0.1:0")
+    .await
+    .unwrap();
+  let addr: SocketAddr = listener.local_addr().unwrap();
+
+  tokio::spawn(serve(
+    listener,
+    Router::new().add_service(CounterServer::new(Service))
+  ));
+
+  Channel::connect(addr).await.unwrap()
+}
+
+async fn client() -> CounterClient<Channel> {
+  CounterClient::new(connect().await)
+}
+
+fn counts(values: &[i32]) -> Streaming<Count> {
+  Streaming::iter(
+    values
+      .iter()
+      .map(|&value| {
+        Ok(Count {
+          value,
+          ..Default::default()
+        })
+      })
+      .collect::<Vec<_>>()
+  )
+}
+
+#[test]
+fn generate_fixture() {
+  let output = Command::new(env!("CARGO_BIN_EXE_pbrs"))
+    .arg("tests/fixtures/counter.proto")
+    .output()
+    .unwrap();
+
+  assert_eq!(
+    String::from_utf8_lossy(&output.stdout),
+    include_str!("fixtures/counter.rs")
+  );
+}
+
+#[tokio::test]
+async fn unary() -> Result<(), Status> {
+  let client = client().await;
+  let options = CallOptions::default().metadata("label", "foo");
+  let req = Count {
+    value: 41,
+    ..Default::default()
+  };
+  let response = client
+    .increment_with_options(&req, options)
+    .await?;
+
+  assert_eq!(response.message.value, 42);
+  assert_eq!(response.message.label, "foo");
+
+  let req = Count {
+    value: -1,
+    ..Default::default()
+  };
+  let result = client.increment(&req).await;
+
+  assert_eq!(result, Err(Status::invalid_argument("Negative count")));
+
+  Ok(())
+}
+
+#[tokio::test]
+async fn client_streaming() -> Result<(), Status> {
+  let client = client().await;
+  let result = client.sum(counts(&[1, 2, 3])).await?;
+
+  assert_eq!(result.value, 6);
+
+  Ok(())
+}
+
+#[tokio::test]
+async fn server_streaming() -> Result<(), Status> {
+  let client = client().await;
+  let req = Count {
+    value: 3,
+    ..Default::default()
+  };
+  let result = client
+    .range(&req)
+    .await?
+    .collect()
+    .await?;
+
+  assert_eq!(
+    result
+      .iter()
+      .map(|v| v.value)
+      .collect::<Vec<_>>(),
+    [0, 1, 2]
+  );
+
+  Ok(())
+}
+
+#[tokio::test]
+async fn bidirectional_streaming() -> Result<(), Status> {
+  let client = client().await;
+  let (sender, req) = channel();
+  let mut stream = client.double(req).await?;
+
+  sender.send(Ok(Count {
+    value: 2,
+    ..Default::default()
+  }));
+
+  assert_eq!(stream.message().await.unwrap()?.value, 4);
+
+  sender.send(Ok(Count::default()));
+  drop(sender);
+
+  let result = stream
+    .message()
+    .await
+    .unwrap()
+    .map_err(|v| v.code);
+
+  assert_eq!(result, Err(Code::OutOfRange));
+  assert!(stream.message().await.is_none());
+
+  Ok(())
+}
+
+#[tokio::test]
+async fn deadline() {
+  let client = client().await;
+  let (_sender, req) = channel();
+  let options = CallOptions::default().timeout(Duration::from_millis(50));
+  let result = client
+    .sum_with_options(req, options)
+    .await
+    .map_err(|v| v.code);
+
+  assert_eq!(result, Err(Code::DeadlineExceeded));
+}
+
+#[tokio::test]
+async fn deeply_nested_request() -> Result<(), Status> {
+  let channel = connect().await;
+
+  // Groups of an unknown field nested far deeper than any message could be,
+  // which the server rejects rather than overflowing its stack
+  let mut nested = vec![0x1b; 1_000_000];
+
+  nested.extend(vec![0x1c; 1_000_000]);
+
+  let result = pbrs::runtime::unary::<_, Raw, Count>(
+    &channel,
+    "/counter.Counter/Increment",
+    &Raw(nested),
+    CallOptions::default()
+  )
+  .await
+  .map(|v| v.message.value);
+
+  assert_eq!(
+    result,
+    Err(Status::invalid_argument(
+      "Failed to decode message: Recursion limit exceeded"
+    ))
+  );
+
+  // The server is still there to answer the next request
+  let req = Count {
+    value: 1,
+    ..Default::default()
+  };
+
+  assert_eq!(
+    CounterClient::new(channel)
+      .increment(&req)
+      .await?
+      .value,
+    2
+  );
+
+  Ok(())
+}
@@ -0,0 +1,44 @@
+use pbrs::runtime::well_known::FieldMask;
+use pbrs::runtime::Masked;
+
+include!("fixtures/counter.rs");
+
+use counter::Count;
+
+fn mask(input: &str) -> Result<FieldMask, String> {
+  input
+    .parse::<FieldMask>()
+    .map_err(|v| v.to_string())
+}
+
+#[test]
+fn update_with_mask() -> Result<(), String> {
+  let mut count = Count {
+    value: 1,
+    label: "one".to_string()
+  };
+  let update = Count {
+    value: 2,
+    label: "two".to_string()
+  };
+
+  count.apply_mask(update.clone(), &mask("label")?)?;
+
+  assert_eq!(count.value, 1);
+  assert_eq!(count.label, "two");
+  assert!(count
+    .apply_mask(update, &mask("label.text")?)
+    .is_err());
+
+  count.trim_to_mask(&mask("value")?)?;
+
+  assert_eq!(
+    count,
+    Count {
+      value: 1,
+      label: String::new()
+    }
+  );
+
+  Ok(())
+}
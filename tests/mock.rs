@@ -0,0 +1,81 @@
+use pbrs::runtime::{Router, Status, Streaming};
+use std::sync::Arc;
+
+include!("fixtures/counter.rs");
+
+use counter::{Count, CounterClient, CounterServer, MockCounter};
+
+fn count(value: i32) -> Count {
+  Count {
+    value,
+    ..Default::default()
+  }
+}
+
+#[tokio::test]
+async fn mock_unary() -> Result<(), Status> {
+  let mock = Arc::new(MockCounter::new());
+  let client = CounterClient::new(
+    Router::new().add_service(CounterServer::from_arc(mock.clone()))
+  );
+
+  mock
+    .increment
+    .returns(|v| Ok(count(v.value + 1)))
+    .returns_once(Err(Status::unavailable("Down")))
+    .expect(2, |v| v.value == 1);
+
+  assert_eq!(
+    client.increment(&count(1)).await,
+    Err(Status::unavailable("Down"))
+  );
+  assert_eq!(client.increment(&count(1)).await?.value, 2);
+
+  mock.verify();
+
+  Ok(())
+}
+
+#[tokio::test]
+async fn mock_streaming() -> Result<(), Status> {
+  let mock = Arc::new(MockCounter::new());
+  let client = CounterClient::new(
+    Router::new().add_service(CounterServer::from_arc(mock.clone()))
+  );
+
+  mock
+    .sum
+    .returns(|v| Ok(count(v.iter().map(|v| v.value).sum())))
+    .expect(1, |v| v.len() == 2);
+  mock
+    .range
+    .returns(|v| Ok(Streaming::iter(vec![Ok(count(v.value)); 2])));
+
+  let req = Streaming::iter([Ok(count(1)), Ok(count(2))]);
+
+  assert_eq!(client.sum(req).await?.value, 3);
+  assert_eq!(
+    client
+      .range(&count(4))
+      .await?
+      .collect()
+      .await?
+      .len(),
+    2
+  );
+  assert_eq!(mock.range.calls(), 1);
+  assert!(mock.double.check().is_ok());
+
+  mock.verify();
+
+  Ok(())
+}
+
+#[test]
+#[should_panic(expected = "/counter.Counter/Increment expectation 1")]
+fn mock_verify() {
+  let mock = MockCounter::new();
+
+  mock.increment.expect_calls(1);
+  mock.verify();
+}
@@ -0,0 +1,51 @@
+use pbrs::plugin::{CodeGeneratorRequest, CodeGeneratorResponse};
+use pbrs::runtime::Message;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+// Request recorded from the descriptor of "fixtures/counter.proto", as protoc
+// would send it
+const REQUEST: &[u8] = include_bytes!("fixtures/counter.request.pb");
+
+fn run_plugin(input: &[u8]) -> CodeGeneratorResponse {
+  let mut child = Command::new(env!("CARGO_BIN_EXE_protoc-gen-pbrs"))
+    .stdin(Stdio::piped())
+    .stdout(Stdio::piped())
+    .spawn()
+    .unwrap();
+
+  child
+    .stdin
+    .take()
+    .unwrap()
+    .write_all(input)
+    .unwrap();
+
+  let output = child.wait_with_output().unwrap();
+
+  CodeGeneratorResponse::decode(&output.stdout).unwrap()
+}
+
+#[test]
+fn generate_requested_files() {
+  let response = run_plugin(REQUEST);
+
+  assert_eq!(response.error, "");
+  assert_eq!(response.file[0].name, "tests/fixtures/counter.rs");
+  assert_eq!(
+    response.file[0].content,
+    include_str!("fixtures/counter.rs")
+  );
+}
+
+#[test]
+fn report_invalid_parameters() {
+  let mut request = CodeGeneratorRequest::decode(REQUEST).unwrap();
+
+  request.parameter = "serde,boxed".to_string();
+
+  let response = run_plugin(&request.encode_to_vec());
+
+  assert_eq!(response.error, "Unknown option boxed");
+  assert!(response.file.is_empty());
+}
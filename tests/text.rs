@@ -0,0 +1,65 @@
+use pbrs::runtime::well_known::Any;
+use pbrs::runtime::{json, DynamicMessage, Message, TextFormat, TypeRegistry};
+use std::fs::read_to_string;
+
+include!("fixtures/counter.rs");
+
+use counter::Count;
+
+#[test]
+fn read_golden_file() -> Result<(), String> {
+  let input = read_to_string("tests/fixtures/count.textproto")
+    .map_err(|v| v.to_string())?;
+  let count = Count::from_text(&input)?;
+
+  assert_eq!(count.value, 3);
+  assert_eq!(count.label, "café \"best\"");
+  assert_eq!(count.to_text()?, "value: 3\nlabel: \"café \\\"best\\\"\"\n");
+  assert_eq!(Count::from_text(&count.to_text()?)?, count);
+
+  Ok(())
+}
+
+#[test]
+fn display_dynamic_messages() -> Result<(), String> {
+  let count = Count {
+    value: -1,
+    ..Default::default()
+  };
+  let message =
+    DynamicMessage::decode(Count::descriptor(), &count.encode_to_vec())
+      .map_err(|v| v.to_string())?;
+
+  assert_eq!(message.to_string(), "value: -1\n");
+  assert!(Count::from_text("value: 1 unknown: 2").is_err());
+
+  Ok(())
+}
+
+#[test]
+fn expand_registered_any() -> Result<(), String> {
+  let registry = counter::register_types(TypeRegistry::default());
+  let count = Count {
+    value: 2,
+    ..Default::default()
+  };
+  let any = Any::pack(&count);
+  let text = any.to_text_with(&registry)?;
+  let message = DynamicMessage::decode(Any::descriptor(), &any.encode_to_vec())
+    .map_err(|v| v.to_string())?;
+
+  assert_eq!(
+    text,
+    "[type.googleapis.com/counter.Count] {\n  value: 2\n}\n"
+  );
+  assert_eq!(
+    Any::from_text_with(&text, &registry)?.unpack::<Count>(),
+    Ok(count)
+  );
+  assert_eq!(
+    json::print_with(&message, &registry),
+    "{\n  \"@type\": \"type.googleapis.com/counter.Count\",\n  \"value\": 2\n}"
+  );
+
+  Ok(())
+}